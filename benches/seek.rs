@@ -0,0 +1,95 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Benchmarks for evaluating animated values at arbitrary frames.
+
+// The `main` function generated by criterion is undocumented.
+#![allow(missing_docs)]
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use interpoli::{Animated, SeekHint, Time};
+
+fn animated(keyframes: usize) -> Animated<f64> {
+    Animated {
+        times: (0..keyframes)
+            .map(|frame| Time::new(frame as f64))
+            .collect(),
+        values: (0..keyframes).map(|value| value as f64).collect(),
+        extrapolation: Default::default(),
+        interpolation: Default::default(),
+    }
+}
+
+/// Returns frames spread pseudo-randomly over the keyframe range.
+fn random_frames(keyframes: usize) -> Vec<f64> {
+    let mut state = 0x2545_f491_u32;
+    (0..1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f64 / u32::MAX as f64 * keyframes as f64
+        })
+        .collect()
+}
+
+fn seek(c: &mut Criterion) {
+    let mut group = c.benchmark_group("seek");
+    for keyframes in [16, 1024, 65536] {
+        let animated = animated(keyframes);
+        let random = random_frames(keyframes);
+        group.bench_with_input(
+            BenchmarkId::new("random", keyframes),
+            &random,
+            |b, frames| {
+                b.iter(|| {
+                    for frame in frames {
+                        black_box(animated.evaluate(*frame));
+                    }
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("random_hinted", keyframes),
+            &random,
+            |b, frames| {
+                let mut hint = SeekHint::default();
+                b.iter(|| {
+                    for frame in frames {
+                        black_box(animated.evaluate_hinted(*frame, &mut hint));
+                    }
+                });
+            },
+        );
+        let playback: Vec<f64> = (0..1024)
+            .map(|frame| frame as f64 * keyframes as f64 / 1024.0)
+            .collect();
+        group.bench_with_input(
+            BenchmarkId::new("playback", keyframes),
+            &playback,
+            |b, frames| {
+                b.iter(|| {
+                    for frame in frames {
+                        black_box(animated.evaluate(*frame));
+                    }
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("playback_hinted", keyframes),
+            &playback,
+            |b, frames| {
+                let mut hint = SeekHint::default();
+                b.iter(|| {
+                    for frame in frames {
+                        black_box(animated.evaluate_hinted(*frame, &mut hint));
+                    }
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, seek);
+criterion_main!(benches);
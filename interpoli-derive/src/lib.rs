@@ -0,0 +1,107 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Derive macros for Interpoli.
+//!
+//! These are re-exported by the `interpoli` crate when its `derive` feature
+//! is enabled, and should be used through that crate.
+
+// LINEBENDER LINT SET - lib.rs - v1
+// See https://linebender.org/wiki/canonical-lints/
+// These lints aren't included in Cargo.toml because they
+// shouldn't apply to examples and tests
+#![warn(unused_crate_dependencies)]
+#![warn(clippy::print_stdout, clippy::print_stderr)]
+// END LINEBENDER LINT SET
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{parse_macro_input, parse_quote, spanned::Spanned, Data, DeriveInput, Fields};
+
+/// Derives `Tween` for a struct by tweening each field.
+///
+/// The struct can be extrapolated if all of its fields can. Every field
+/// must implement `Tween`, and the struct must implement
+/// `Clone` and `Default`, which can usually be derived as well. Type
+/// parameters are required to implement `Tween`.
+#[proc_macro_derive(Tween)]
+pub fn derive_tween(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "Tween can only be derived for structs",
+        ));
+    };
+    let (body, extrapolate) = match &data.fields {
+        Fields::Named(fields) => {
+            let tweens = fields.named.iter().map(|field| {
+                let name = &field.ident;
+                quote_spanned! {field.span()=>
+                    #name: ::interpoli::Tween::tween(&self.#name, &other.#name, t, easing)
+                }
+            });
+            let extrapolations = fields.named.iter().map(|field| {
+                let name = &field.ident;
+                quote_spanned! {field.span()=>
+                    #name: ::interpoli::Tween::extrapolate(&self.#name, &previous.#name, scale)?
+                }
+            });
+            (
+                quote!(Self { #(#tweens,)* }),
+                quote!(::core::option::Option::Some(Self { #(#extrapolations,)* })),
+            )
+        }
+        Fields::Unnamed(fields) => {
+            let tweens = fields.unnamed.iter().enumerate().map(|(index, field)| {
+                let index = syn::Index::from(index);
+                quote_spanned! {field.span()=>
+                    ::interpoli::Tween::tween(&self.#index, &other.#index, t, easing)
+                }
+            });
+            let extrapolations = fields.unnamed.iter().enumerate().map(|(index, field)| {
+                let index = syn::Index::from(index);
+                quote_spanned! {field.span()=>
+                    ::interpoli::Tween::extrapolate(&self.#index, &previous.#index, scale)?
+                }
+            });
+            (
+                quote!(Self(#(#tweens,)*)),
+                quote!(::core::option::Option::Some(Self(#(#extrapolations,)*))),
+            )
+        }
+        Fields::Unit => (
+            quote!({
+                let _ = (other, t, easing);
+                Self
+            }),
+            quote!({
+                let _ = (previous, scale);
+                ::core::option::Option::Some(Self)
+            }),
+        ),
+    };
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::interpoli::Tween));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::interpoli::Tween for #name #ty_generics #where_clause {
+            fn tween(&self, other: &Self, t: f64, easing: &::interpoli::Easing) -> Self {
+                #body
+            }
+
+            fn extrapolate(&self, previous: &Self, scale: f64) -> ::core::option::Option<Self> {
+                #extrapolate
+            }
+        }
+    })
+}
@@ -0,0 +1,477 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+use kurbo::{Affine, PathEl};
+
+use crate::{fixed, Composition, Content, Layer, Limits, Modifier, Shape};
+
+/// Options for [`Composition::find_loop_points`].
+#[derive(Clone, Debug)]
+pub struct LoopSearch {
+    /// Range of frames to search. Defaults to the frames of the
+    /// composition when `None`.
+    pub frames: Option<Range<f64>>,
+    /// Distance between sampled frames.
+    pub step: f64,
+    /// Minimum number of frames between the loop in and out points.
+    pub min_length: f64,
+    /// Maximum difference of any evaluated property for two frames to be
+    /// considered matching.
+    pub tolerance: f64,
+    /// Indices of the top level layers to compare. All layers are compared
+    /// when `None`.
+    pub layers: Option<Vec<usize>>,
+    /// Limits on the structure that is sampled. Instances nested deeper
+    /// than the limit are not sampled.
+    pub limits: Limits,
+}
+
+impl Default for LoopSearch {
+    fn default() -> Self {
+        Self {
+            frames: None,
+            step: 1.0,
+            min_length: 1.0,
+            tolerance: 1e-3,
+            layers: None,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// Candidate pair of frames for looping an animation.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LoopPoint {
+    /// Frame at which the loop begins.
+    pub start: f64,
+    /// Frame at which the loop jumps back to `start`.
+    pub end: f64,
+    /// Largest difference of any evaluated property between the two frames.
+    pub error: f64,
+}
+
+impl Composition {
+    /// Finds pairs of frames at which the evaluated state of the
+    /// composition matches within a tolerance.
+    ///
+    /// Jumping from the end to the start of such a pair produces a loop
+    /// without a visible discontinuity. The result is ordered from the best
+    /// match to the worst, preferring longer loops for equal errors.
+    pub fn find_loop_points(&self, search: &LoopSearch) -> Vec<LoopPoint> {
+        let frames = search.frames.clone().unwrap_or(self.frames.clone());
+        if search.step <= 0.0 || frames.end <= frames.start {
+            return Vec::new();
+        }
+        let mut samples = Vec::new();
+        for frame in sample_frames(frames, search.step) {
+            let mut state = State::new(search.limits);
+            for (index, layer) in self.layers.iter().enumerate() {
+                if search
+                    .layers
+                    .as_ref()
+                    .map_or(true, |layers| layers.contains(&index))
+                {
+                    state.layer(self, layer, frame);
+                }
+            }
+            samples.push((frame, state.values));
+        }
+        // Samples only match if all of their values do, so once they are
+        // sorted by a single value, each sample only needs to be compared
+        // with the following ones until that value differs by more than the
+        // tolerance. Samples of different lengths never match.
+        let key = key_value(&samples);
+        let value = |ix: usize| key.map_or(0.0, |key| samples[ix].1[key]);
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        order.sort_by(|a, b| {
+            (samples[*a].1.len().cmp(&samples[*b].1.len())).then(value(*a).total_cmp(&value(*b)))
+        });
+        let mut points = Vec::new();
+        for (position, &i) in order.iter().enumerate() {
+            for &j in &order[position + 1..] {
+                if samples[j].1.len() != samples[i].1.len()
+                    || value(j) - value(i) > search.tolerance
+                {
+                    break;
+                }
+                let (a, b) = if samples[i].0 < samples[j].0 {
+                    (&samples[i], &samples[j])
+                } else {
+                    (&samples[j], &samples[i])
+                };
+                if b.0 - a.0 < search.min_length {
+                    continue;
+                }
+                let error = difference(&a.1, &b.1);
+                if error <= search.tolerance {
+                    points.push(LoopPoint {
+                        start: a.0,
+                        end: b.0,
+                        error,
+                    });
+                }
+            }
+        }
+        points.sort_by(|a, b| {
+            a.error
+                .total_cmp(&b.error)
+                .then((b.end - b.start).total_cmp(&(a.end - a.start)))
+        });
+        points
+    }
+}
+
+/// Returns the frames sampled within a range.
+pub(crate) fn sample_frames(frames: Range<f64>, step: f64) -> impl Iterator<Item = f64> {
+    let count = if step > 0.0 && frames.end > frames.start {
+        ((frames.end - frames.start) / step).ceil() as usize
+    } else {
+        0
+    };
+    (0..count).map(move |index| frames.start + index as f64 * step)
+}
+
+/// Returns the index of the value that varies the most between samples, or
+/// `None` if no value present in every sample can be used to order them.
+fn key_value(samples: &[(f64, Vec<f64>)]) -> Option<usize> {
+    let len = samples.iter().map(|(_, values)| values.len()).min()?;
+    (0..len)
+        .filter(|&ix| samples.iter().all(|(_, values)| values[ix].is_finite()))
+        .map(|ix| {
+            let (min, max) = samples.iter().fold(
+                (f64::INFINITY, f64::NEG_INFINITY),
+                |(min, max), (_, values)| (min.min(values[ix]), max.max(values[ix])),
+            );
+            (ix, max - min)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(ix, _)| ix)
+}
+
+/// Returns the largest difference between two sampled states.
+fn difference(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return f64::INFINITY;
+    }
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, f64::max)
+}
+
+/// Class of an evaluated property of a composition.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub enum Property {
+    /// Whether a layer is active.
+    #[default]
+    Visibility,
+    /// Layer and group transforms.
+    Transform,
+    /// Layer, group and draw opacity.
+    Opacity,
+    /// Layer mask geometry and opacity.
+    Mask,
+    /// Shape geometry and path modifiers.
+    Geometry,
+    /// Stroke widths.
+    Stroke,
+    /// Fill and stroke brushes.
+    Brush,
+    /// Repeater parameters.
+    Repeater,
+}
+
+/// Flattened evaluated properties of a composition at a single frame.
+#[derive(Default)]
+pub(crate) struct State {
+    pub(crate) values: Vec<f64>,
+    /// Class of property of each value, completed by [`State::finish`].
+    properties: Vec<Property>,
+    /// Class of property of the values currently being appended.
+    property: Property,
+    path: Vec<PathEl>,
+    limits: Limits,
+    /// Addresses of the assets currently being sampled, used to break
+    /// cycles in compositions that haven't been validated.
+    instances: Vec<usize>,
+}
+
+impl State {
+    pub(crate) fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Completes the classes of property of the values appended last. This
+    /// must be called before [`State::properties`].
+    pub(crate) fn finish(&mut self) {
+        self.properties.resize(self.values.len(), self.property);
+    }
+
+    /// Returns the class of property of each value.
+    pub(crate) fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Sets the class of property of the values appended next.
+    fn set(&mut self, property: Property) {
+        self.properties.resize(self.values.len(), self.property);
+        self.property = property;
+    }
+
+    pub(crate) fn layer(&mut self, composition: &Composition, layer: &Layer, frame: f64) {
+        self.set(Property::Visibility);
+        if !layer.is_active(frame) {
+            self.values.push(0.0);
+            return;
+        }
+        self.values.push(1.0);
+        self.set(Property::Transform);
+        self.transform(layer.transform.evaluate(frame).into_owned());
+        self.set(Property::Opacity);
+        self.values.push(layer.opacity.evaluate(frame));
+        self.set(Property::Mask);
+        for mask in &layer.masks {
+            self.values.push(mask.opacity.evaluate(frame));
+            mask.geometry.evaluate(frame, &mut self.path);
+            self.path();
+        }
+        match &layer.content {
+            Content::None | Content::External(_) => {}
+            Content::Instance { name, .. } => {
+                let Some(asset) = composition.assets.get(name) else {
+                    return;
+                };
+                let instance = asset.as_ptr() as usize;
+                // See Composition::validate
+                if self.instances.contains(&instance)
+                    || self.instances.len() >= self.limits.max_instance_depth
+                {
+                    return;
+                }
+                self.instances.push(instance);
+                let frame = layer.instance_frame(frame);
+                for asset_layer in asset {
+                    self.layer(composition, asset_layer, frame);
+                }
+                self.instances.pop();
+            }
+            Content::Shape(shapes) => self.shapes(shapes, frame),
+        }
+    }
+
+    fn shapes(&mut self, shapes: &[Shape], frame: f64) {
+        for shape in shapes {
+            match shape {
+                Shape::Group(group, transform) => {
+                    if let Some(transform) = transform {
+                        self.set(Property::Transform);
+                        self.transform(transform.transform.evaluate(frame).into_owned());
+                        self.set(Property::Opacity);
+                        self.values.push(transform.opacity.evaluate(frame));
+                    }
+                    self.shapes(group, frame);
+                }
+                Shape::Geometry(geometry) => {
+                    self.set(Property::Geometry);
+                    geometry.evaluate(frame, &mut self.path);
+                    self.path();
+                }
+                Shape::Draw(draw) => {
+                    self.set(Property::Opacity);
+                    self.values.push(draw.opacity.evaluate(frame));
+                    if let Some(stroke) = &draw.stroke {
+                        self.set(Property::Stroke);
+                        self.values.push(stroke.evaluate(frame).as_ref().width);
+                    }
+                    self.set(Property::Brush);
+                    self.brush(draw.brush.evaluate(1.0, frame).as_ref());
+                }
+                Shape::Repeater(repeater) => {
+                    self.set(Property::Repeater);
+                    let repeater = repeater.evaluate(frame);
+                    let repeater = repeater.as_ref();
+                    self.values.extend([
+                        repeater.copies as f64,
+                        repeater.offset,
+                        repeater.anchor_point.x,
+                        repeater.anchor_point.y,
+                        repeater.position.x,
+                        repeater.position.y,
+                        repeater.rotation,
+                        repeater.scale.x,
+                        repeater.scale.y,
+                        repeater.start_opacity,
+                        repeater.end_opacity,
+                    ]);
+                }
+                Shape::Modifier(modifier) => {
+                    self.set(Property::Geometry);
+                    match modifier {
+                        Modifier::ZigZag(zig_zag) => self
+                            .values
+                            .extend([zig_zag.size.evaluate(frame), zig_zag.ridges.evaluate(frame)]),
+                        Modifier::PuckerBloat(pucker_bloat) => {
+                            self.values.push(pucker_bloat.amount.evaluate(frame));
+                        }
+                        Modifier::Twist(twist) => {
+                            let center = twist.center.evaluate(frame);
+                            self.values
+                                .extend([twist.angle.evaluate(frame), center.x, center.y]);
+                        }
+                        Modifier::Wave(wave) => self.values.extend([
+                            wave.amplitude.evaluate(frame),
+                            wave.wavelength.evaluate(frame),
+                            wave.phase.evaluate(frame),
+                        ]),
+                    }
+                }
+            }
+        }
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        self.values.extend(transform.as_coeffs());
+    }
+
+    /// Appends the points of the current path and clears it.
+    fn path(&mut self) {
+        for element in self.path.drain(..) {
+            match element {
+                PathEl::MoveTo(p) | PathEl::LineTo(p) => self.values.extend([p.x, p.y]),
+                PathEl::QuadTo(p0, p1) => self.values.extend([p0.x, p0.y, p1.x, p1.y]),
+                PathEl::CurveTo(p0, p1, p2) => {
+                    self.values.extend([p0.x, p0.y, p1.x, p1.y, p2.x, p2.y]);
+                }
+                PathEl::ClosePath => {}
+            }
+        }
+    }
+
+    fn brush(&mut self, brush: &fixed::Brush) {
+        match brush {
+            peniko::Brush::Solid(color) => self.color(*color),
+            peniko::Brush::Gradient(gradient) => {
+                match gradient.kind {
+                    peniko::GradientKind::Linear { start, end } => {
+                        self.values.extend([start.x, start.y, end.x, end.y]);
+                    }
+                    peniko::GradientKind::Radial {
+                        start_center,
+                        start_radius,
+                        end_center,
+                        end_radius,
+                    } => self.values.extend([
+                        start_center.x,
+                        start_center.y,
+                        start_radius as f64,
+                        end_center.x,
+                        end_center.y,
+                        end_radius as f64,
+                    ]),
+                    peniko::GradientKind::Sweep {
+                        center,
+                        start_angle,
+                        end_angle,
+                    } => self.values.extend([
+                        center.x,
+                        center.y,
+                        start_angle as f64,
+                        end_angle as f64,
+                    ]),
+                }
+                for stop in gradient.stops.iter() {
+                    self.values.push(stop.offset as f64);
+                    self.color(stop.color);
+                }
+            }
+            peniko::Brush::Image(_) => {}
+        }
+    }
+
+    fn color(&mut self, color: fixed::Color) {
+        self.values.extend([
+            color.r as f64 / 255.0,
+            color.g as f64 / 255.0,
+            color.b as f64 / 255.0,
+            color.a as f64 / 255.0,
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Animated, Time, Value};
+    use alloc::vec;
+
+    #[test]
+    fn finds_loop_in_periodic_animation() {
+        // Opacity goes 0 -> 100 -> 0 -> 100 over 30 frames.
+        let opacity = Animated {
+            times: vec![
+                Time::new(0.0),
+                Time::new(10.0),
+                Time::new(20.0),
+                Time::new(30.0),
+            ],
+            values: vec![0.0, 100.0, 0.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let composition = Composition {
+            frames: 0.0..31.0,
+            layers: vec![Layer {
+                frames: 0.0..31.0,
+                opacity: Value::Animated(opacity),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let points = composition.find_loop_points(&LoopSearch {
+            min_length: 5.0,
+            ..Default::default()
+        });
+        let best = points.first().expect("a loop should be found");
+        assert_eq!(best.error, 0.0);
+        assert_eq!(best.end - best.start, 20.0);
+        assert!(
+            points.iter().all(|point| point.end - point.start >= 5.0),
+            "loops should respect the minimum length"
+        );
+    }
+
+    #[test]
+    fn instance_cycles_are_not_followed() {
+        let instance = |name: &str| Layer {
+            frames: 0.0..4.0,
+            ..Layer::instance(name)
+        };
+        let mut composition = Composition {
+            frames: 0.0..4.0,
+            layers: vec![instance("a")],
+            ..Default::default()
+        };
+        composition
+            .assets
+            .insert("a".into(), vec![instance("a"), instance("a")]);
+        let points = composition.find_loop_points(&LoopSearch::default());
+        assert_eq!(points.len(), 6, "every pair of static frames matches");
+        let shallow = composition.find_loop_points(&LoopSearch {
+            limits: Limits {
+                max_instance_depth: 0,
+                ..Limits::UNBOUNDED
+            },
+            ..Default::default()
+        });
+        assert_eq!(shallow.len(), 6);
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use crate::{Animated, Easing, Tween};
+
+/// Angle in degrees that tweens along the shorter arc.
+///
+/// Tweening plain numbers between 350 and 10 degrees turns the long way
+/// around through 180 degrees. Angles instead wrap around at 180 degrees
+/// and turn by 20 degrees, plus any full turns requested with the spin
+/// count of the target.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct Angle {
+    /// Angle in degrees.
+    pub degrees: f64,
+    /// Number of additional full turns taken when tweening towards this
+    /// angle. Positive values turn clockwise and negative values turn
+    /// counterclockwise.
+    pub spins: i32,
+}
+
+impl Angle {
+    /// Creates an angle from degrees with no additional turns.
+    pub fn from_degrees(degrees: f64) -> Self {
+        Self { degrees, spins: 0 }
+    }
+
+    /// Creates an angle from radians with no additional turns.
+    pub fn from_radians(radians: f64) -> Self {
+        Self::from_degrees(radians.to_degrees())
+    }
+
+    /// Returns the angle in radians.
+    pub fn radians(self) -> f64 {
+        self.degrees.to_radians()
+    }
+
+    /// Returns the angle with the given number of additional turns.
+    #[must_use]
+    pub fn with_spins(self, spins: i32) -> Self {
+        Self { spins, ..self }
+    }
+}
+
+impl Tween for Angle {
+    fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
+        let delta = shortest_arc(other.degrees - self.degrees) + 360.0 * other.spins as f64;
+        Self::from_degrees(self.degrees + delta * easing.evaluate(t))
+    }
+
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        let delta = shortest_arc(self.degrees - previous.degrees);
+        Some(Self::from_degrees(self.degrees + delta * scale))
+    }
+}
+
+/// Returns the equivalent rotation in the range -180 to 180 degrees.
+fn shortest_arc(delta: f64) -> f64 {
+    let delta = delta % 360.0;
+    if delta > 180.0 {
+        delta - 360.0
+    } else if delta < -180.0 {
+        delta + 360.0
+    } else {
+        delta
+    }
+}
+
+impl Animated<f64> {
+    /// Returns a copy of the animation with values, taken as angles in
+    /// degrees, adjusted so that each segment turns along the shorter arc.
+    ///
+    /// This is useful for rotations imported from formats that tween the
+    /// numbers directly. The first value is kept, and every later value is
+    /// moved by whole turns to within 180 degrees of the one before it.
+    #[must_use]
+    pub fn shortest_arc(&self) -> Self {
+        let mut values = self.values.clone();
+        for ix in 1..values.len() {
+            let previous = values[ix - 1];
+            values[ix] = previous + shortest_arc(values[ix] - previous);
+        }
+        Self {
+            times: self.times.clone(),
+            values,
+            extrapolation: self.extrapolation,
+            interpolation: self.interpolation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Time;
+    use alloc::vec;
+
+    #[test]
+    fn angles_take_shorter_arc() {
+        let from = Angle::from_degrees(350.0);
+        let to = Angle::from_degrees(10.0);
+        let mid = from.tween(&to, 0.5, &Easing::LERP);
+        assert!(
+            (mid.degrees - 360.0).abs() < 1e-9,
+            "should pass through 0 degrees, got {mid:?}"
+        );
+        let spun = from.tween(&to.with_spins(-1), 0.5, &Easing::LERP);
+        assert!(
+            (spun.degrees - 180.0).abs() < 1e-9,
+            "a negative spin should turn counterclockwise, got {spun:?}"
+        );
+        let rotation = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0), Time::new(20.0)],
+            values: vec![170.0, -170.0, 90.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        assert_eq!(rotation.shortest_arc().values, [170.0, 190.0, 90.0]);
+    }
+}
@@ -3,20 +3,135 @@
 
 //! Representations of animated values.
 
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 #[cfg(all(not(feature = "std"), feature = "libm"))]
 #[allow(unused_imports)]
 use kurbo::common::FloatFuncs as _;
-use kurbo::{Affine, PathEl, Point, Size, Vec2};
+use kurbo::{Affine, CubicBez, ParamCurve, ParamCurveArclen, PathEl, Point, Size, Vec2};
 
-use crate::{fixed, spline::SplineToPath, value::Tween, Time, Value};
+use crate::{
+    fixed,
+    spline::{SplineShape, SplineToPath},
+    value::Tween,
+    Animated, ColorSpace, Easing, HueDirection, SeekHint, SeekHints, Time, Value,
+};
 
 #[derive(Clone, Debug)]
 pub enum Position {
     Value(Value<Point>),
     SplitValues((Value<f64>, Value<f64>)),
+    /// Position moving along curved motion paths between keyframes.
+    Spatial(SpatialPosition),
 }
 
+impl Position {
+    /// Returns true if the value contains no animated properties.
+    pub fn is_fixed(&self) -> bool {
+        match self {
+            Self::Value(value) => value.is_fixed(),
+            Self::SplitValues((x_value, y_value)) => x_value.is_fixed() && y_value.is_fixed(),
+            Self::Spatial(spatial) => spatial.value.times.len() <= 1,
+        }
+    }
+
+    /// Evaluates the position at the specified frame.
+    pub fn evaluate(&self, frame: f64) -> Point {
+        match self {
+            Self::Value(value) => value.evaluate(frame),
+            Self::SplitValues((x_value, y_value)) => Point {
+                x: x_value.evaluate(frame),
+                y: y_value.evaluate(frame),
+            },
+            Self::Spatial(spatial) => spatial.evaluate(frame),
+        }
+    }
+
+    /// Evaluates the position at the specified frame, using the hints for
+    /// its values in the set.
+    pub fn evaluate_cached(&self, frame: f64, hints: &mut SeekHints) -> Point {
+        match self {
+            Self::Value(value) => value.evaluate_cached(frame, hints),
+            Self::SplitValues((x_value, y_value)) => Point {
+                x: x_value.evaluate_cached(frame, hints),
+                y: y_value.evaluate_cached(frame, hints),
+            },
+            Self::Spatial(spatial) => spatial.evaluate_hinted(frame, hints.get(&spatial.value)),
+        }
+    }
+}
+
+/// Tangents of the motion path of a position between two keyframes, as
+/// with the `to` and `ti` properties of Lottie position keyframes.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SpatialTangents {
+    /// Outgoing tangent, relative to the value at the start of the segment.
+    pub out_tangent: Vec2,
+    /// Incoming tangent, relative to the value at the end of the segment.
+    pub in_tangent: Vec2,
+}
+
+/// Animated position following cubic bezier motion paths between
+/// keyframes.
+///
+/// The easing of each segment controls the progress along the arc length
+/// of its path, so the position moves at a steady speed along curves when
+/// the easing is linear.
+#[derive(Clone, Debug)]
+pub struct SpatialPosition {
+    /// Keyframed values.
+    pub value: Animated<Point>,
+    /// Tangents of the segment starting at each keyframe. Missing tangents
+    /// are zero, giving a straight path.
+    pub tangents: Vec<SpatialTangents>,
+}
+
+impl SpatialPosition {
+    /// Evaluates the position at the specified frame.
+    pub fn evaluate(&self, frame: f64) -> Point {
+        self.evaluate_hinted(frame, &mut SeekHint::default())
+    }
+
+    /// Evaluates the position at the specified frame, starting the search
+    /// for the surrounding keyframes at the segment recorded in the hint.
+    ///
+    /// Frames outside of the range of the keyframes follow the
+    /// [extrapolation](Animated::extrapolation) of the value.
+    pub fn evaluate_hinted(&self, frame: f64, hint: &mut SeekHint) -> Point {
+        self.value
+            .extrapolation
+            .apply(&self.value.times, frame, |frame| {
+                self.evaluate_inner(frame, hint).unwrap_or_default()
+            })
+    }
+
+    fn evaluate_inner(&self, frame: f64, hint: &mut SeekHint) -> Option<Point> {
+        let ([ix0, ix1], t, easing, hold) = self.value.segment(frame, hint)?;
+        let p0 = *self.value.values.get(ix0)?;
+        let p1 = *self.value.values.get(ix1)?;
+        if hold {
+            return Some(p0);
+        }
+        let tangents = self.tangents.get(ix0).copied().unwrap_or_default();
+        if tangents.out_tangent == Vec2::ZERO && tangents.in_tangent == Vec2::ZERO {
+            // Straight segments follow the interpolation of the value.
+            if ix0 != ix1 && t > 0.0 && t < 1.0 {
+                if let Some(point) = Point::interpolate(&self.value, ix0, t) {
+                    return Some(point);
+                }
+            }
+            return Some(p0.lerp(p1, easing.as_ref().evaluate(t)));
+        }
+        let progress = easing.as_ref().evaluate(t);
+        let path = CubicBez::new(p0, p0 + tangents.out_tangent, p1 + tangents.in_tangent, p1);
+        let length = path.arclen(ARCLEN_ACCURACY);
+        let param = path.inv_arclen(length * progress.clamp(0.0, 1.0), ARCLEN_ACCURACY);
+        Some(path.eval(param))
+    }
+}
+
+/// Accuracy of arc length computations for motion paths.
+const ARCLEN_ACCURACY: f64 = 1e-3;
+
 /// Animated affine transformation.
 #[derive(Clone, Debug)]
 pub struct Transform {
@@ -38,12 +153,7 @@ impl Transform {
     /// Returns true if the transform is fixed.
     pub fn is_fixed(&self) -> bool {
         self.anchor.is_fixed()
-            && match &self.position {
-                Position::Value(value) => value.is_fixed(),
-                Position::SplitValues((x_value, y_value)) => {
-                    x_value.is_fixed() && y_value.is_fixed()
-                }
-            }
+            && self.position.is_fixed()
             && self.rotation.is_fixed()
             && self.scale.is_fixed()
             && self.skew.is_fixed()
@@ -52,32 +162,27 @@ impl Transform {
 
     /// Evaluates the transform at the specified frame.
     pub fn evaluate(&self, frame: f64) -> Affine {
-        let anchor = self.anchor.evaluate(frame);
-        let position = match &self.position {
-            Position::Value(value) => value.evaluate(frame),
-            Position::SplitValues((x_value, y_value)) => Point {
-                x: x_value.evaluate(frame),
-                y: y_value.evaluate(frame),
-            },
-        };
-        let rotation = self.rotation.evaluate(frame);
-        let scale = self.scale.evaluate(frame);
-        let skew = self.skew.evaluate(frame);
-        let skew_angle = self.skew_angle.evaluate(frame);
-        let skew_matrix = if skew != 0.0 {
-            const SKEW_LIMIT: f64 = 85.0;
-            let skew = -skew.clamp(-SKEW_LIMIT, SKEW_LIMIT);
-            let skew = skew.to_radians();
-            let angle = skew_angle.to_radians();
-            Affine::rotate(-angle) * Affine::skew(skew.tan(), 0.0) * Affine::rotate(angle)
-        } else {
-            Affine::IDENTITY
-        };
-        Affine::translate((position.x, position.y))
-            * Affine::rotate(rotation.to_radians())
-            * skew_matrix
-            * Affine::scale_non_uniform(scale.x / 100.0, scale.y / 100.0)
-            * Affine::translate((-anchor.x, -anchor.y))
+        compose_transform(
+            self.anchor.evaluate(frame),
+            self.position.evaluate(frame),
+            self.rotation.evaluate(frame),
+            self.scale.evaluate(frame),
+            self.skew.evaluate(frame),
+            self.skew_angle.evaluate(frame),
+        )
+    }
+
+    /// Evaluates the transform at the specified frame, using the hints for
+    /// each of its properties in the set.
+    pub fn evaluate_cached(&self, frame: f64, hints: &mut SeekHints) -> Affine {
+        compose_transform(
+            self.anchor.evaluate_cached(frame, hints),
+            self.position.evaluate_cached(frame, hints),
+            self.rotation.evaluate_cached(frame, hints),
+            self.scale.evaluate_cached(frame, hints),
+            self.skew.evaluate_cached(frame, hints),
+            self.skew_angle.evaluate_cached(frame, hints),
+        )
     }
 
     /// Converts the animated value to its model representation.
@@ -90,6 +195,31 @@ impl Transform {
     }
 }
 
+/// Combines the evaluated properties of a transform.
+pub(crate) fn compose_transform(
+    anchor: Point,
+    position: Point,
+    rotation: f64,
+    scale: Vec2,
+    skew: f64,
+    skew_angle: f64,
+) -> Affine {
+    let skew_matrix = if skew != 0.0 {
+        const SKEW_LIMIT: f64 = 85.0;
+        let skew = -skew.clamp(-SKEW_LIMIT, SKEW_LIMIT);
+        let skew = skew.to_radians();
+        let angle = skew_angle.to_radians();
+        Affine::rotate(-angle) * Affine::skew(skew.tan(), 0.0) * Affine::rotate(angle)
+    } else {
+        Affine::IDENTITY
+    };
+    Affine::translate((position.x, position.y))
+        * Affine::rotate(rotation.to_radians())
+        * skew_matrix
+        * Affine::scale_non_uniform(scale.x / 100.0, scale.y / 100.0)
+        * Affine::translate((-anchor.x, -anchor.y))
+}
+
 /// Animated ellipse.
 #[derive(Clone, Debug)]
 pub struct Ellipse {
@@ -152,32 +282,110 @@ impl Rect {
     }
 }
 
+/// Former name of [`PolyStar`].
+#[deprecated(note = "use `PolyStar`, which replaces `direction` with `is_ccw`")]
+pub type Star = PolyStar;
+
+/// Maximum number of points of a [`PolyStar`]. This bounds the size of the
+/// evaluated path.
+pub const MAX_POLYSTAR_POINTS: usize = 1000;
+
 /// Animated star or polygon.
 #[derive(Clone, Debug)]
-pub struct Star {
+pub struct PolyStar {
+    /// True if the shape is a regular polygon, which has no inner vertices.
     pub is_polygon: bool,
-    pub direction: f64,
+    /// True if the shape should be drawn in counterclockwise order.
+    pub is_ccw: bool,
+    /// Position of the center of the shape.
     pub position: Value<Point>,
+    /// Distance of the inner vertices of a star from the center.
     pub inner_radius: Value<f64>,
+    /// Roundness of the inner vertices of a star in percent.
     pub inner_roundness: Value<f64>,
+    /// Distance of the outer vertices from the center.
     pub outer_radius: Value<f64>,
+    /// Roundness of the outer vertices in percent.
     pub outer_roundness: Value<f64>,
+    /// Rotation in degrees, with the first outer vertex pointing up at zero.
     pub rotation: Value<f64>,
+    /// Number of points of a star, or sides of a polygon. Fractional values
+    /// are rounded down, and values are clamped to at most
+    /// [`MAX_POLYSTAR_POINTS`].
     pub points: Value<f64>,
 }
 
-// TODO: Use this.
-//impl Star {
-//    pub fn is_fixed(&self) -> bool {
-//        self.position.is_fixed()
-//            && self.inner_radius.is_fixed()
-//            && self.inner_roundness.is_fixed()
-//            && self.outer_radius.is_fixed()
-//            && self.outer_roundness.is_fixed()
-//            && self.rotation.is_fixed()
-//            && self.points.is_fixed()
-//    }
-//}
+impl PolyStar {
+    /// Returns true if the shape is fixed.
+    pub fn is_fixed(&self) -> bool {
+        self.position.is_fixed()
+            && self.inner_radius.is_fixed()
+            && self.inner_roundness.is_fixed()
+            && self.outer_radius.is_fixed()
+            && self.outer_roundness.is_fixed()
+            && self.rotation.is_fixed()
+            && self.points.is_fixed()
+    }
+
+    /// Evaluates the shape at the specified frame.
+    ///
+    /// Rounded vertices are drawn with tangents along the circle through
+    /// them, with lengths proportional to the roundness, as in Lottie.
+    pub fn evaluate(&self, frame: f64) -> kurbo::BezPath {
+        let position = self.position.evaluate(frame);
+        let points =
+            (self.points.evaluate(frame).floor().max(0.0) as usize).min(MAX_POLYSTAR_POINTS);
+        let outer = (
+            self.outer_radius.evaluate(frame),
+            self.outer_roundness.evaluate(frame) / 100.0,
+        );
+        let (count, inner) = if self.is_polygon {
+            (Some(points), outer)
+        } else {
+            let inner = (
+                self.inner_radius.evaluate(frame),
+                self.inner_roundness.evaluate(frame) / 100.0,
+            );
+            (points.checked_mul(2), inner)
+        };
+        let mut path = kurbo::BezPath::new();
+        let (Some(count), Some(quarters)) =
+            (count.filter(|count| *count != 0), points.checked_mul(4))
+        else {
+            return path;
+        };
+        let direction = if self.is_ccw { -1.0 } else { 1.0 };
+        let step = core::f64::consts::TAU / count as f64 * direction;
+        let start = self.rotation.evaluate(frame).to_radians() - core::f64::consts::FRAC_PI_2;
+        // Each vertex with the offsets of its incoming and outgoing handles.
+        let vertices = (0..count).map(|ix| {
+            let (radius, roundness) = if ix % 2 == 0 { outer } else { inner };
+            let tangent = Vec2::from_angle(start + step * ix as f64);
+            let handle = Vec2::new(tangent.y, -tangent.x)
+                * (core::f64::consts::TAU * radius / quarters as f64 * roundness * direction);
+            (position + tangent * radius, handle)
+        });
+        let mut previous: Option<(Point, Vec2)> = None;
+        let mut first = None;
+        for (vertex, handle) in vertices {
+            match previous {
+                Some((from, from_handle)) => {
+                    path.curve_to(from - from_handle, vertex + handle, vertex);
+                }
+                None => {
+                    path.move_to(vertex);
+                    first = Some((vertex, handle));
+                }
+            }
+            previous = Some((vertex, handle));
+        }
+        if let (Some((from, from_handle)), Some((vertex, handle))) = (previous, first) {
+            path.curve_to(from - from_handle, vertex + handle, vertex);
+            path.close_path();
+        }
+        path
+    }
+}
 
 /// Animated cubic spline.
 #[derive(Clone, Debug)]
@@ -195,17 +403,82 @@ impl Spline {
     /// Evaluates the spline at the given frame and appends the elements
     /// to the specified path.
     pub fn evaluate(&self, frame: f64, path: &mut Vec<PathEl>) -> bool {
-        let Some(([ix0, ix1], t, _easing, _hold)) = Time::frames_and_weight(&self.times, frame)
+        let Some(([ix0, ix1], t, _easing, hold)) = Time::frames_and_weight(&self.times, frame)
         else {
-            // TODO: evaluate whether hold frame is needed here
             return false;
         };
+        let t = if hold { 0.0 } else { t };
         let (Some(from), Some(to)) = (self.values.get(ix0), self.values.get(ix1)) else {
             return false;
         };
         (from.as_slice(), to.as_slice(), t).to_path(self.is_closed, path);
         true
     }
+
+    /// Returns the spline at the given frame as a shape that produces its
+    /// path elements lazily.
+    pub fn shape(&self, frame: f64) -> SplineShape<'_> {
+        let Some(([ix0, ix1], t, _easing, hold)) = Time::frames_and_weight(&self.times, frame)
+        else {
+            return SplineShape::new(&[], &[], 0.0, self.is_closed);
+        };
+        let t = if hold { 0.0 } else { t };
+        let (Some(from), Some(to)) = (self.values.get(ix0), self.values.get(ix1)) else {
+            return SplineShape::new(&[], &[], 0.0, self.is_closed);
+        };
+        SplineShape::new(from, to, t, self.is_closed)
+    }
+}
+
+/// Cubic path whose vertices are animated independently.
+///
+/// Unlike a [`Spline`], which interpolates between whole paths, each vertex
+/// and handle has its own keyframes.
+#[derive(Clone, Debug)]
+pub struct Path {
+    /// True if the path is closed.
+    pub is_closed: bool,
+    /// Vertices of the path in drawing order.
+    pub vertices: Vec<PathVertex>,
+}
+
+impl Path {
+    /// Returns true if the path is fixed.
+    pub fn is_fixed(&self) -> bool {
+        self.vertices.iter().all(PathVertex::is_fixed)
+    }
+
+    /// Evaluates the path at the given frame and appends the elements to
+    /// the specified path.
+    pub fn evaluate(&self, frame: f64, path: &mut Vec<PathEl>) {
+        let mut points = Vec::with_capacity(self.vertices.len() * 3);
+        for vertex in &self.vertices {
+            points.extend([
+                vertex.point.evaluate(frame),
+                vertex.in_tangent.evaluate(frame).to_point(),
+                vertex.out_tangent.evaluate(frame).to_point(),
+            ]);
+        }
+        points.as_slice().to_path(self.is_closed, path);
+    }
+}
+
+/// Vertex of an animated [`Path`].
+#[derive(Clone, Debug)]
+pub struct PathVertex {
+    /// Position of the vertex.
+    pub point: Value<Point>,
+    /// Offset of the incoming handle from the vertex.
+    pub in_tangent: Value<Vec2>,
+    /// Offset of the outgoing handle from the vertex.
+    pub out_tangent: Value<Vec2>,
+}
+
+impl PathVertex {
+    /// Returns true if the vertex is fixed.
+    pub fn is_fixed(&self) -> bool {
+        self.point.is_fixed() && self.in_tangent.is_fixed() && self.out_tangent.is_fixed()
+    }
 }
 
 /// Animated repeater effect.
@@ -285,12 +558,23 @@ pub struct Stroke {
     pub miter_limit: Option<f64>,
     /// Cap style.
     pub cap: kurbo::Cap,
+    /// Lengths of dashes in alternating on/off order, or empty for a solid
+    /// stroke.
+    pub dash_pattern: Vec<Value<f64>>,
+    /// Offset of the first dash.
+    pub dash_offset: Value<f64>,
+    /// Variation of the width along the path, or `None` for a constant
+    /// width.
+    pub taper: Option<Box<Taper>>,
 }
 
 impl Stroke {
     /// Returns true if the stroke is fixed.
     pub fn is_fixed(&self) -> bool {
         self.width.is_fixed()
+            && self.dash_pattern.iter().all(Value::is_fixed)
+            && self.dash_offset.is_fixed()
+            && self.taper.is_none()
     }
 
     /// Evaluates the stroke at the specified frame.
@@ -302,10 +586,24 @@ impl Stroke {
         if let Some(miter_limit) = self.miter_limit {
             stroke.miter_limit = miter_limit;
         }
+        if !self.dash_pattern.is_empty() {
+            stroke = stroke.with_dashes(
+                self.dash_offset.evaluate(frame),
+                self.dash_pattern.iter().map(|dash| dash.evaluate(frame)),
+            );
+        }
         stroke
     }
 
+    /// Evaluates the taper of the stroke at the specified frame.
+    pub fn evaluate_taper(&self, frame: f64) -> Option<fixed::Taper> {
+        self.taper.as_ref().map(|taper| taper.evaluate(frame))
+    }
+
     /// Converts the animated value to its model representation.
+    ///
+    /// Tapered strokes remain animated, since the fixed representation
+    /// has no taper.
     pub fn into_model(self) -> super::Stroke {
         if self.is_fixed() {
             super::Stroke::Fixed(self.evaluate(0.0))
@@ -315,6 +613,28 @@ impl Stroke {
     }
 }
 
+/// Animated variation of the width of a stroke along its path.
+#[derive(Clone, Debug)]
+pub struct Taper {
+    /// Width at the start of each contour in percent of the stroke width.
+    pub start_width: Value<f64>,
+    /// Width at the end of each contour in percent of the stroke width.
+    pub end_width: Value<f64>,
+    /// Easing of the width from the start to the end, by arc length.
+    pub ease: Easing,
+}
+
+impl Taper {
+    /// Evaluates the taper at the specified frame.
+    pub fn evaluate(&self, frame: f64) -> fixed::Taper {
+        fixed::Taper {
+            start_width: self.start_width.evaluate(frame),
+            end_width: self.end_width.evaluate(frame),
+            ease: self.ease.clone(),
+        }
+    }
+}
+
 /// Animated linear or radial gradient.
 #[derive(Clone, Debug)]
 pub struct Gradient {
@@ -326,22 +646,81 @@ pub struct Gradient {
     pub end_point: Value<Point>,
     /// Stop offsets and color values.
     pub stops: super::ColorStops,
+    /// Opacity stops, stored separately from the color stops as in Lottie.
+    /// When present, the colors are multiplied by the opacity ramp.
+    pub opacity_stops: Option<OpacityStops>,
+    /// Distance of the focal point of a radial gradient from its center, in
+    /// percent of the radius. Clamped to 99% in either direction.
+    pub highlight_length: Value<f64>,
+    /// Angle of the focal point of a radial gradient in degrees, relative to
+    /// the direction from the start point to the end point.
+    pub highlight_angle: Value<f64>,
+    /// Rotation of the gradient about the start point in degrees.
+    pub rotation: Value<f64>,
+    /// Uniform scale of the gradient about the start point in percent.
+    pub scale: Value<f64>,
 }
 
 impl Gradient {
     /// Returns true if the value contains no animated properties.
     pub fn is_fixed(&self) -> bool {
-        self.start_point.is_fixed() && self.end_point.is_fixed() && self.stops.is_fixed()
+        self.start_point.is_fixed()
+            && self.end_point.is_fixed()
+            && self.highlight_length.is_fixed()
+            && self.highlight_angle.is_fixed()
+            && self.rotation.is_fixed()
+            && self.scale.is_fixed()
+            && self.stops.is_fixed()
+            && self
+                .opacity_stops
+                .as_ref()
+                .map_or(true, |opacity| opacity.frames.len() <= 1)
+    }
+
+    /// Returns true if the gradient is known to evaluate identically at both
+    /// frames.
+    pub fn is_constant_between(&self, a: f64, b: f64) -> bool {
+        self.start_point.is_constant_between(a, b)
+            && self.end_point.is_constant_between(a, b)
+            && self.highlight_length.is_constant_between(a, b)
+            && self.highlight_angle.is_constant_between(a, b)
+            && self.rotation.is_constant_between(a, b)
+            && self.scale.is_constant_between(a, b)
+            && match &self.stops {
+                super::ColorStops::Fixed(_) => true,
+                super::ColorStops::Animated(stops) => {
+                    Time::is_constant_between(&stops.frames, a, b)
+                }
+            }
+            && self.opacity_stops.as_ref().map_or(true, |opacity| {
+                Time::is_constant_between(&opacity.frames, a, b)
+            })
     }
 
     /// Evaluates the animated value at the given frame.
     pub fn evaluate(&self, frame: f64) -> peniko::Brush {
         let start = self.start_point.evaluate(frame);
-        let end = self.end_point.evaluate(frame);
-        let stops = self.stops.evaluate(frame).into_owned();
+        // Rotation and uniform scale about the start point only move the
+        // end point, and preserve the shape of both kinds of gradient.
+        let scale = self.scale.evaluate(frame) / 100.0;
+        let rotation = self.rotation.evaluate(frame).to_radians();
+        let end = start + (self.end_point.evaluate(frame) - start) * scale;
+        let end = Affine::rotate_about(rotation, start) * end;
+        let mut stops = self.stops.evaluate(frame).into_owned();
+        if let Some(opacity_stops) = &self.opacity_stops {
+            stops = opacity_stops.apply(&stops, frame);
+        }
         if self.is_radial {
-            let radius = (end.to_vec2() - start.to_vec2()).hypot();
-            let mut grad = peniko::Gradient::new_radial(start, radius as f32);
+            let axis = end - start;
+            let radius = axis.hypot();
+            let length = (self.highlight_length.evaluate(frame) / 100.0).clamp(-0.99, 0.99);
+            let angle = self.highlight_angle.evaluate(frame).to_radians() + axis.atan2();
+            let focal = start + Vec2::from_angle(angle) * (length * radius);
+            let mut grad = if focal == start {
+                peniko::Gradient::new_radial(start, radius as f32)
+            } else {
+                peniko::Gradient::new_two_point_radial(focal, 0.0, start, radius as f32)
+            };
             grad.stops = stops;
             grad.into()
         } else {
@@ -352,11 +731,111 @@ impl Gradient {
     }
 }
 
+/// Animated opacity stops of a gradient.
+#[derive(Clone, Debug)]
+pub struct OpacityStops {
+    pub frames: Vec<Time>,
+    /// Interleaved offset and opacity pairs for each keyframe, with opacity
+    /// in the range 0 to 1.
+    pub values: Vec<Vec<f64>>,
+    pub count: usize,
+}
+
+impl OpacityStops {
+    /// Returns the offset and opacity pairs at the given frame.
+    pub fn evaluate(&self, frame: f64) -> Vec<(f64, f64)> {
+        self.evaluate_inner(frame).unwrap_or_default()
+    }
+
+    fn evaluate_inner(&self, frame: f64) -> Option<Vec<(f64, f64)>> {
+        let ([ix0, ix1], t, easing, hold) = Time::frames_and_weight(&self.frames, frame)?;
+        let v0 = self.values.get(ix0)?;
+        let v1 = self.values.get(ix1)?;
+        let t = if hold { 0.0 } else { t };
+        let mut stops = Vec::with_capacity(self.count);
+        for i in 0..self.count {
+            let j = i * 2;
+            let offset = v0.get(j)?.tween(v1.get(j)?, t, easing.as_ref());
+            let opacity = v0.get(j + 1)?.tween(v1.get(j + 1)?, t, easing.as_ref());
+            stops.push((offset, opacity));
+        }
+        Some(stops)
+    }
+
+    /// Returns the color stops multiplied by the opacity ramp at the given
+    /// frame.
+    ///
+    /// The result has a stop at each color and opacity offset, so the
+    /// ramps are preserved exactly when they don't line up.
+    pub fn apply(&self, stops: &fixed::ColorStops, frame: f64) -> fixed::ColorStops {
+        let opacity = self.evaluate(frame);
+        if opacity.is_empty() || stops.is_empty() {
+            return stops.clone();
+        }
+        let mut offsets: Vec<f64> = stops
+            .iter()
+            .map(|stop| stop.offset as f64)
+            .chain(opacity.iter().map(|(offset, _)| *offset))
+            .collect();
+        offsets.sort_by(f64::total_cmp);
+        offsets.dedup();
+        let mut result = fixed::ColorStops::new();
+        for offset in offsets {
+            let color = color_at(stops, offset);
+            let alpha = ramp_at(&opacity, offset).clamp(0.0, 1.0);
+            let stop = peniko::ColorStop::from((offset as f32, color.multiply_alpha(alpha as f32)));
+            result.push(stop);
+        }
+        result
+    }
+}
+
+/// Returns the color of a gradient at the given offset.
+fn color_at(stops: &fixed::ColorStops, offset: f64) -> fixed::Color {
+    let ix = stops.partition_point(|stop| (stop.offset as f64) < offset);
+    let (Some(prev), Some(next)) = (stops.get(ix.saturating_sub(1)), stops.get(ix)) else {
+        return stops.last().map(|stop| stop.color).unwrap_or_default();
+    };
+    let span = next.offset as f64 - prev.offset as f64;
+    let t = if span > 0.0 {
+        (offset - prev.offset as f64) / span
+    } else {
+        0.0
+    };
+    prev.color.tween(&next.color, t, &Easing::LERP)
+}
+
+/// Returns the value of a piecewise linear ramp at the given offset.
+fn ramp_at(ramp: &[(f64, f64)], offset: f64) -> f64 {
+    let ix = ramp.partition_point(|(stop, _)| *stop < offset);
+    let (Some(prev), Some(next)) = (ramp.get(ix.saturating_sub(1)), ramp.get(ix)) else {
+        return ramp.last().map_or(1.0, |(_, value)| *value);
+    };
+    let span = next.0 - prev.0;
+    if span > 0.0 {
+        prev.1 + (next.1 - prev.1) * (offset - prev.0) / span
+    } else {
+        next.1
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ColorStops {
     pub frames: Vec<Time>,
+    /// Interleaved offset and sRGB components, with alpha last, of each
+    /// stop for each keyframe, in the range 0 to 1.
+    ///
+    /// Keyframes may have different numbers of stops, up to `count`. The
+    /// stops of the keyframe with fewer stops are resampled at the offsets
+    /// of the other keyframe when interpolating between them.
     pub values: Vec<Vec<f64>>,
     pub count: usize,
+    /// Color space in which the colors of corresponding stops are
+    /// interpolated between keyframes.
+    pub color_space: ColorSpace,
+    /// Direction in which hue is interpolated when the color space is
+    /// cylindrical.
+    pub hue_direction: HueDirection,
 }
 
 impl ColorStops {
@@ -370,36 +849,79 @@ impl ColorStops {
         let v0 = self.values.get(ix0)?;
         let v1 = self.values.get(ix1)?;
 
+        let t = if hold { 0.0 } else { t };
+
+        let t = easing.as_ref().evaluate(t);
+        let mut stops0 = self.keyframe_stops(v0);
+        let mut stops1 = self.keyframe_stops(v1);
+        if stops0.len() < stops1.len() {
+            stops0 = resample(&stops0, &stops1);
+        } else if stops1.len() < stops0.len() {
+            stops1 = resample(&stops1, &stops0);
+        }
         let mut stops: fixed::ColorStops = Default::default();
-        for i in 0..self.count {
-            let j = i * 5;
-            let offset = v0.get(j)?.tween(v1.get(j)?, t, &easing);
-            let t = if hold { 0.0 } else { t };
-            let r = v0.get(j + 1)?.tween(v1.get(j + 1)?, t, &easing);
-            let g = v0.get(j + 2)?.tween(v1.get(j + 2)?, t, &easing);
-            let b = v0.get(j + 3)?.tween(v1.get(j + 3)?, t, &easing);
-            let a = v0.get(j + 4)?.tween(v1.get(j + 4)?, t, &easing);
+        for ((offset0, color0), (offset1, color1)) in stops0.into_iter().zip(stops1) {
+            let offset = offset0 + (offset1 - offset0) * t;
+            let [r, g, b, a] =
+                self.color_space
+                    .mix_components(color0, color1, t, self.hue_direction);
             let stop = peniko::ColorStop::from((offset as f32, peniko::Color::rgba(r, g, b, a)));
             stops.push(stop);
         }
         Some(stops)
     }
+
+    /// Returns the offset and color components of the stops of a keyframe.
+    fn keyframe_stops(&self, values: &[f64]) -> Vec<(f64, [f64; 4])> {
+        values
+            .chunks_exact(5)
+            .take(self.count)
+            .map(|stop| (stop[0], [stop[1], stop[2], stop[3], stop[4]]))
+            .collect()
+    }
+}
+
+/// Samples the gradient described by `stops` at the offsets of `target`.
+///
+/// Returns `target` if there are no stops to sample.
+fn resample(stops: &[(f64, [f64; 4])], target: &[(f64, [f64; 4])]) -> Vec<(f64, [f64; 4])> {
+    if stops.is_empty() {
+        return target.to_vec();
+    }
+    target
+        .iter()
+        .map(|(offset, _)| {
+            let ix = stops.partition_point(|(stop, _)| stop < offset);
+            let color = match (stops.get(ix.saturating_sub(1)), stops.get(ix)) {
+                (Some((offset0, color0)), Some((offset1, color1))) if offset1 > offset0 => {
+                    let t = (offset - offset0) / (offset1 - offset0);
+                    // Gradients are rendered with sRGB interpolation between
+                    // stops, so sample them the same way.
+                    ColorSpace::Srgb.mix_components(*color0, *color1, t, HueDirection::Shorter)
+                }
+                (_, Some((_, color))) | (Some((_, color)), None) => *color,
+                (None, None) => unreachable!("stops are not empty"),
+            };
+            (*offset, color)
+        })
+        .collect()
 }
 
 /// Animated brush.
 #[derive(Clone, Debug)]
 pub enum Brush {
-    /// Solid color.
-    Solid(Value<peniko::Color>),
+    /// Solid color, interpolated between keyframes in the given color
+    /// space.
+    Solid(Value<peniko::Color>, ColorSpace),
     /// Gradient color.
-    Gradient(Gradient),
+    Gradient(Box<Gradient>),
 }
 
 impl Brush {
     /// Returns true if the value contains no animated properties.
     pub fn is_fixed(&self) -> bool {
         match self {
-            Self::Solid(value) => value.is_fixed(),
+            Self::Solid(value, _) => value.is_fixed(),
             Self::Gradient(value) => value.is_fixed(),
         }
     }
@@ -407,7 +929,13 @@ impl Brush {
     /// Evaluates the animation at the specified time.
     pub fn evaluate(&self, alpha: f64, frame: f64) -> fixed::Brush {
         match self {
-            Self::Solid(value) => value.evaluate(frame).multiply_alpha(alpha as f32).into(),
+            Self::Solid(value, color_space) => {
+                let color = match value {
+                    Value::Fixed(color) => *color,
+                    Value::Animated(animated) => animated.evaluate_with(frame, color_space),
+                };
+                color.multiply_alpha(alpha as f32).into()
+            }
             Self::Gradient(value) => value.evaluate(frame),
         }
     }
@@ -421,3 +949,244 @@ impl Brush {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Extrapolation, Interpolation};
+    use alloc::vec;
+
+    #[test]
+    fn opacity_stops_merge_with_color_stops() {
+        let opacity = OpacityStops {
+            frames: vec![Time::new(0.0), Time::new(10.0)],
+            values: vec![vec![0.0, 1.0, 0.5, 1.0], vec![0.0, 1.0, 0.5, 0.0]],
+            count: 2,
+        };
+        let mut stops = fixed::ColorStops::new();
+        stops.push((0.0, fixed::Color::rgb8(255, 0, 0)).into());
+        stops.push((1.0, fixed::Color::rgb8(0, 0, 255)).into());
+        let merged = opacity.apply(&stops, 5.0);
+        let offsets: Vec<f32> = merged.iter().map(|stop| stop.offset).collect();
+        assert_eq!(offsets, [0.0, 0.5, 1.0]);
+        let alphas: Vec<u8> = merged.iter().map(|stop| stop.color.a).collect();
+        assert_eq!(alphas, [255, 128, 128]);
+        assert_eq!(merged[1].color.r, 128);
+    }
+
+    #[test]
+    fn spatial_position_follows_motion_path() {
+        let mut position = SpatialPosition {
+            value: Animated {
+                times: vec![Time::new(0.0), Time::new(10.0)],
+                values: vec![Point::new(0.0, 0.0), Point::new(100.0, 0.0)],
+                extrapolation: Default::default(),
+                interpolation: Default::default(),
+            },
+            tangents: vec![],
+        };
+        assert_eq!(position.evaluate(5.0), Point::new(50.0, 0.0));
+        position.tangents.push(SpatialTangents {
+            out_tangent: Vec2::new(0.0, 100.0),
+            in_tangent: Vec2::new(0.0, 100.0),
+        });
+        let mid = position.evaluate(5.0);
+        assert!(
+            (mid.x - 50.0).abs() < 1e-3 && (mid.y - 75.0).abs() < 1e-3,
+            "a symmetric arc should peak halfway along its length, got {mid:?}"
+        );
+        assert_eq!(position.evaluate(10.0), Point::new(100.0, 0.0));
+
+        // Frames past the keyframes follow the extrapolation of the value.
+        assert_eq!(position.evaluate(15.0), Point::new(100.0, 0.0));
+        position.value.extrapolation = Extrapolation::Loop;
+        assert_eq!(position.evaluate(15.0), mid);
+        assert_eq!(position.evaluate(-5.0), mid);
+        position.value.extrapolation = Extrapolation::PingPong;
+        assert_eq!(position.evaluate(15.0), mid);
+        let back = position.evaluate(12.0);
+        let forward = position.evaluate(8.0);
+        assert!((back - forward).hypot() < 1e-9, "{back:?} {forward:?}");
+        position.value.extrapolation = Extrapolation::Hold;
+        position.value.interpolation = Interpolation::Hold;
+        assert_eq!(position.evaluate(5.0), Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn color_stops_resample_mismatched_counts() {
+        let stops = ColorStops {
+            frames: vec![Time::new(0.0), Time::new(10.0)],
+            values: vec![
+                vec![0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0],
+                vec![
+                    0.0, 0.0, 0.0, 1.0, 1.0, 0.5, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0,
+                ],
+            ],
+            count: 3,
+            color_space: ColorSpace::Srgb,
+            hue_direction: HueDirection::Shorter,
+        };
+        let start = stops.evaluate(0.0);
+        let offsets: Vec<f32> = start.iter().map(|stop| stop.offset).collect();
+        assert_eq!(offsets, [0.0, 0.5, 1.0]);
+        assert_eq!(start[1].color, fixed::Color::rgb8(128, 0, 128));
+        let mid = stops.evaluate(5.0);
+        assert_eq!(mid.len(), 3);
+        assert_eq!(mid[1].color, fixed::Color::rgb8(64, 128, 64));
+        assert_eq!(stops.evaluate(10.0)[2].color, fixed::Color::rgb8(0, 0, 255));
+    }
+
+    #[test]
+    fn solid_brush_interpolates_in_color_space() {
+        let red = fixed::Color::rgb8(255, 0, 0);
+        let blue = fixed::Color::rgb8(0, 0, 255);
+        let brush = |color_space| {
+            Brush::Solid(
+                Value::Animated(Animated {
+                    times: vec![Time::new(0.0), Time::new(10.0)],
+                    values: vec![red, blue],
+                    extrapolation: Default::default(),
+                    interpolation: Default::default(),
+                }),
+                color_space,
+            )
+        };
+        let solid = |evaluated: fixed::Brush| match evaluated {
+            fixed::Brush::Solid(color) => color,
+            _ => panic!("a solid brush should evaluate to a solid color"),
+        };
+        let srgb = solid(brush(ColorSpace::Srgb).evaluate(1.0, 5.0));
+        let oklab = solid(brush(ColorSpace::Oklab).evaluate(1.0, 5.0));
+        assert_eq!(srgb, ColorSpace::Srgb.mix(red, blue, 0.5));
+        assert_eq!(oklab, ColorSpace::Oklab.mix(red, blue, 0.5));
+        assert_ne!(srgb, oklab);
+    }
+
+    #[test]
+    fn radial_highlight_moves_focal_point() {
+        let gradient = Gradient {
+            is_radial: true,
+            start_point: Value::Fixed(Point::new(0.0, 0.0)),
+            end_point: Value::Fixed(Point::new(10.0, 0.0)),
+            stops: crate::ColorStops::Fixed(fixed::ColorStops::new()),
+            opacity_stops: None,
+            highlight_length: Value::Animated(Animated {
+                times: vec![Time::new(0.0), Time::new(10.0)],
+                values: vec![0.0, 50.0],
+                extrapolation: Default::default(),
+                interpolation: Default::default(),
+            }),
+            highlight_angle: Value::Fixed(90.0),
+            rotation: Value::Fixed(0.0),
+            scale: Value::Fixed(200.0),
+        };
+        assert!(!gradient.is_fixed());
+        let circles = |frame| match gradient.evaluate(frame) {
+            peniko::Brush::Gradient(peniko::Gradient {
+                kind:
+                    peniko::GradientKind::Radial {
+                        start_center,
+                        end_center,
+                        end_radius,
+                        ..
+                    },
+                ..
+            }) => (start_center, end_center, end_radius),
+            brush => panic!("expected a radial gradient, got {brush:?}"),
+        };
+        assert_eq!(circles(0.0), (Point::ORIGIN, Point::ORIGIN, 20.0));
+        let (focal, center, _) = circles(10.0);
+        assert!((focal - Point::new(0.0, 10.0)).hypot() < 1e-9);
+        assert_eq!(center, Point::ORIGIN);
+    }
+
+    #[test]
+    fn poly_star_alternates_radii() {
+        use kurbo::Shape as _;
+
+        let mut star = PolyStar {
+            is_polygon: false,
+            is_ccw: false,
+            position: Value::Fixed(Point::new(10.0, 10.0)),
+            inner_radius: Value::Fixed(2.0),
+            inner_roundness: Value::Fixed(0.0),
+            outer_radius: Value::Fixed(5.0),
+            outer_roundness: Value::Fixed(0.0),
+            rotation: Value::Fixed(0.0),
+            points: Value::Fixed(5.5),
+        };
+        let path = star.evaluate(0.0);
+        let vertices: Vec<Point> = path
+            .elements()
+            .iter()
+            .filter_map(|el| el.end_point())
+            .collect();
+        // Ten vertices, and the first one again to close the outline.
+        assert_eq!(vertices.len(), 11);
+        assert!((vertices[0] - Point::new(10.0, 5.0)).hypot() < 1e-9);
+        for (ix, vertex) in vertices.iter().enumerate() {
+            let radius = if ix % 2 == 0 { 5.0 } else { 2.0 };
+            assert!(((*vertex - Point::new(10.0, 10.0)).hypot() - radius).abs() < 1e-9);
+        }
+        // Clockwise in a y-down coordinate system.
+        assert!(path.area() > 0.0);
+
+        star.is_polygon = true;
+        star.points = Value::Fixed(4.0);
+        star.outer_roundness = Value::Fixed(100.0);
+        let square = star.evaluate(0.0);
+        assert_eq!(square.elements().len(), 6);
+        // Rounded sides bulge out of the square towards the circle through
+        // its vertices.
+        let area = square.area();
+        assert!(area > 50.0 && area < core::f64::consts::PI * 25.0, "{area}");
+
+        // Huge point counts are clamped rather than overflowing.
+        for points in [1e30, f64::INFINITY] {
+            star.points = Value::Fixed(points);
+            assert_eq!(star.evaluate(0.0).elements().len(), MAX_POLYSTAR_POINTS + 2);
+        }
+        star.points = Value::Fixed(f64::NAN);
+        assert!(star.evaluate(0.0).elements().is_empty());
+    }
+
+    #[test]
+    fn path_vertices_animate_independently() {
+        let corner = |point| PathVertex {
+            point: Value::Fixed(point),
+            in_tangent: Value::Fixed(Vec2::ZERO),
+            out_tangent: Value::Fixed(Vec2::ZERO),
+        };
+        let mut path = Path {
+            is_closed: true,
+            vertices: vec![corner(Point::new(0.0, 0.0)), corner(Point::new(10.0, 0.0))],
+        };
+        path.vertices.push(PathVertex {
+            point: Value::Animated(Animated {
+                times: vec![Time::new(0.0), Time::new(10.0)],
+                values: vec![Point::new(10.0, 10.0), Point::new(0.0, 10.0)],
+                extrapolation: Default::default(),
+                interpolation: Default::default(),
+            }),
+            in_tangent: Value::Fixed(Vec2::ZERO),
+            out_tangent: Value::Fixed(Vec2::new(0.0, -5.0)),
+        });
+        assert!(!path.is_fixed());
+        let mut elements = Vec::new();
+        path.evaluate(5.0, &mut elements);
+        assert_eq!(
+            elements,
+            [
+                PathEl::MoveTo(Point::new(0.0, 0.0)),
+                PathEl::LineTo(Point::new(10.0, 0.0)),
+                PathEl::LineTo(Point::new(5.0, 10.0)),
+                PathEl::CurveTo(
+                    Point::new(5.0, 5.0),
+                    Point::new(0.0, 0.0),
+                    Point::new(0.0, 0.0)
+                ),
+                PathEl::ClosePath,
+            ]
+        );
+    }
+}
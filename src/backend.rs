@@ -0,0 +1,265 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use kurbo::{Affine, Shape};
+use peniko::{BlendMode, Fill};
+
+use crate::fixed;
+
+/// Target of the drawing operations produced by a [`Renderer`].
+///
+/// The renderer traverses a composition, resolving layers, masks, shapes
+/// and repeaters, and issues the resulting operations to a backend in
+/// order. Implement this trait to draw compositions with a renderer other
+/// than vello, whose [`Scene`] implements it when the `vello` feature is
+/// enabled.
+///
+/// A backend records operations so they can be replayed: the renderer
+/// keeps backends holding previously encoded content and appends them to
+/// the target when the content is unchanged.
+///
+/// [`Renderer`]: crate::Renderer
+/// [`Scene`]: https://docs.rs/vello/latest/vello/struct.Scene.html
+pub trait RenderBackend: Default {
+    /// Starts a layer clipped to `clip`, which is composited with `blend`
+    /// and `alpha` when popped.
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    );
+
+    /// Starts a layer clipped to `clip` whose content is converted to
+    /// coverage by its luminance when popped: each pixel becomes white with
+    /// an opacity of its luminance multiplied by its alpha. The converted
+    /// layer is then composited normally with `alpha`.
+    ///
+    /// This draws the matte layers of luma mattes. Returns false without
+    /// starting a layer if the backend can't convert luminance, which is the
+    /// default. The renderer then converts the color of each brush of the
+    /// matte instead, which is only exact for solid and gradient brushes of
+    /// shapes that don't overlap. Images and the content of instance brushes
+    /// are drawn unconverted.
+    fn push_luminance_layer(
+        &mut self,
+        _alpha: f32,
+        _transform: Affine,
+        _clip: &impl Shape,
+    ) -> bool {
+        false
+    }
+
+    /// Ends the most recently pushed layer.
+    fn pop_layer(&mut self);
+
+    /// Fills a shape with a brush.
+    fn fill(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: &fixed::Brush,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    );
+
+    /// Strokes a shape with a brush.
+    fn stroke(
+        &mut self,
+        style: &fixed::Stroke,
+        transform: Affine,
+        brush: &fixed::Brush,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    );
+
+    /// Appends the operations recorded by another backend, with an
+    /// additional transform.
+    fn append(&mut self, other: &Self, transform: Option<Affine>);
+
+    /// Removes all recorded operations, keeping any allocations for reuse.
+    fn reset(&mut self);
+}
+
+#[cfg(feature = "vello")]
+impl RenderBackend for vello::Scene {
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        Self::push_layer(self, blend, alpha, transform, clip);
+    }
+
+    fn pop_layer(&mut self) {
+        Self::pop_layer(self);
+    }
+
+    fn fill(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: &fixed::Brush,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        Self::fill(self, style, transform, brush, brush_transform, shape);
+    }
+
+    fn stroke(
+        &mut self,
+        style: &fixed::Stroke,
+        transform: Affine,
+        brush: &fixed::Brush,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        Self::stroke(self, style, transform, brush, brush_transform, shape);
+    }
+
+    fn append(&mut self, other: &Self, transform: Option<Affine>) {
+        Self::append(self, other, transform);
+    }
+
+    fn reset(&mut self) {
+        Self::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use kurbo::Rect;
+
+    use super::*;
+    use crate::{
+        composition::square_layer, Composition, Content, ExternalId, Layer, LayerInfo, Renderer,
+    };
+
+    /// Backend that records the name of each operation.
+    #[derive(Default)]
+    struct Recorder(Vec<&'static str>);
+
+    impl RenderBackend for Recorder {
+        fn push_layer(
+            &mut self,
+            _blend: impl Into<BlendMode>,
+            _alpha: f32,
+            _transform: Affine,
+            _clip: &impl Shape,
+        ) {
+            self.0.push("push");
+        }
+
+        fn pop_layer(&mut self) {
+            self.0.push("pop");
+        }
+
+        fn fill(
+            &mut self,
+            _style: Fill,
+            _transform: Affine,
+            _brush: &fixed::Brush,
+            _brush_transform: Option<Affine>,
+            _shape: &impl Shape,
+        ) {
+            self.0.push("fill");
+        }
+
+        fn stroke(
+            &mut self,
+            _style: &fixed::Stroke,
+            _transform: Affine,
+            _brush: &fixed::Brush,
+            _brush_transform: Option<Affine>,
+            _shape: &impl Shape,
+        ) {
+            self.0.push("stroke");
+        }
+
+        fn append(&mut self, other: &Self, _transform: Option<Affine>) {
+            self.0.extend_from_slice(&other.0);
+        }
+
+        fn reset(&mut self) {
+            self.0.clear();
+        }
+    }
+
+    #[test]
+    fn renderer_draws_to_custom_backend() {
+        let composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![square_layer(fixed::Color::BLACK)],
+            ..Default::default()
+        };
+        let mut renderer = Renderer::<Recorder>::new();
+        for frame in [0.0, 1.0] {
+            let recorder = renderer.render(&composition, frame, Affine::IDENTITY, 1.0);
+            assert_eq!(recorder.0, ["push", "fill", "pop"], "at frame {frame}");
+        }
+    }
+
+    #[test]
+    fn callbacks_draw_around_layers() {
+        fn assert_send<T: Send>() {}
+        // Renderers with callbacks can be moved to a render thread.
+        assert_send::<Renderer<Recorder>>();
+
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![
+                square_layer(fixed::Color::BLACK),
+                Layer {
+                    frames: 0.0..10.0,
+                    width: 10.0,
+                    height: 10.0,
+                    content: Content::External(ExternalId(7)),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut renderer = Renderer::<Recorder>::new();
+        // External layers are left empty without a callback.
+        assert_eq!(
+            renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0).0,
+            ["push", "fill", "pop"]
+        );
+
+        renderer.set_pre_layer_callback(Some(|_: &LayerInfo<'_>, scene: &mut Recorder| {
+            scene.0.push("pre");
+        }));
+        renderer.set_post_layer_callback(Some(|_: &LayerInfo<'_>, scene: &mut Recorder| {
+            scene.0.push("post");
+        }));
+        renderer.set_external_content_callback(Some(
+            |id: ExternalId, info: &LayerInfo<'_>, scene: &mut Recorder| {
+                assert_eq!(id, ExternalId(7));
+                assert_eq!(info.bounds, Rect::new(0.0, 0.0, 10.0, 10.0));
+                scene.0.push("external");
+            },
+        ));
+        // Layers are drawn from the bottom up, and external content is
+        // clipped to the layer bounds.
+        assert_eq!(
+            renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0).0,
+            ["push", "pre", "push", "external", "pop", "post", "pre", "fill", "post", "pop"]
+        );
+
+        composition.layers.truncate(1);
+        renderer.set_pre_layer_callback(None::<fn(&LayerInfo<'_>, &mut Recorder)>);
+        assert_eq!(
+            renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0).0,
+            ["push", "fill", "post", "pop"]
+        );
+    }
+}
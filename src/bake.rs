@@ -0,0 +1,164 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::{fmt, ops::Range};
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+use kurbo::Affine;
+
+use crate::{Composition, DisplayList, Renderer};
+
+/// Maximum number of frames sampled by [`Composition::bake`].
+pub const MAX_BAKED_FRAMES: usize = 1 << 16;
+
+/// Composition sampled into display lists at a fixed frame step.
+///
+/// Playing a baked composition only looks up the display list of a frame,
+/// with no keyframes to evaluate, which suits embedded and web clients that
+/// trade memory for CPU time. Consecutive samples that draw the same
+/// content share a display list.
+///
+/// With the `serde` feature, baked compositions can be serialized, such as
+/// with a compact binary format that clients load without parsing or
+/// evaluating the original animation.
+#[derive(Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BakedComposition {
+    /// Frames in which the animation is active.
+    pub frames: Range<f64>,
+    /// Frames per second.
+    pub frame_rate: f64,
+    /// Width of the animation.
+    pub width: usize,
+    /// Height of the animation.
+    pub height: usize,
+    /// Distance in frames between consecutive samples.
+    pub frame_step: f64,
+    /// Distinct display lists drawn by the samples.
+    pub lists: Vec<DisplayList>,
+    /// Index into `lists` of each sample, in frame order.
+    pub samples: Vec<usize>,
+}
+
+impl BakedComposition {
+    /// Returns the display list of the last sample at or before the frame.
+    ///
+    /// Frames outside of the sampled range use the first or last sample.
+    /// Returns `None` if nothing was sampled.
+    pub fn display_list(&self, frame: f64) -> Option<&DisplayList> {
+        let last = self.samples.len().checked_sub(1)?;
+        let index = ((frame - self.frames.start) / self.frame_step).floor();
+        // NaN frames use the first sample.
+        let index = (index.max(0.0) as usize).min(last);
+        self.lists.get(self.samples[index])
+    }
+}
+
+/// Error produced when baking a composition.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BakeError {
+    /// The frame step is not positive and finite.
+    InvalidFrameStep,
+    /// Sampling would produce more than [`MAX_BAKED_FRAMES`] frames.
+    TooManyFrames,
+}
+
+impl fmt::Display for BakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFrameStep => write!(f, "frame step is not positive and finite"),
+            Self::TooManyFrames => write!(f, "baking would sample too many frames"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BakeError {}
+
+impl Composition {
+    /// Samples the composition every `frame_step` frames, starting at the
+    /// first frame, into display lists that can be played back without
+    /// evaluating the animation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BakeError::InvalidFrameStep`] if the frame step is not
+    /// positive and finite, and [`BakeError::TooManyFrames`] if more than
+    /// [`MAX_BAKED_FRAMES`] frames would be sampled.
+    pub fn bake(&self, frame_step: f64) -> Result<BakedComposition, BakeError> {
+        if !(frame_step > 0.0 && frame_step.is_finite()) {
+            return Err(BakeError::InvalidFrameStep);
+        }
+        // Empty or NaN ranges sample no frames.
+        let count = ((self.frames.end - self.frames.start) / frame_step)
+            .ceil()
+            .max(0.0);
+        if count > MAX_BAKED_FRAMES as f64 {
+            return Err(BakeError::TooManyFrames);
+        }
+        let mut renderer = Renderer::<DisplayList>::new();
+        let mut baked = BakedComposition {
+            frames: self.frames.clone(),
+            frame_rate: self.frame_rate,
+            width: self.width,
+            height: self.height,
+            frame_step,
+            lists: Vec::new(),
+            samples: Vec::with_capacity(count as usize),
+        };
+        for index in 0..count as usize {
+            let frame = self.frames.start + index as f64 * frame_step;
+            let list = renderer.render(self, frame, Affine::IDENTITY, 1.0);
+            if baked.lists.last() != Some(&list) {
+                baked.lists.push(list);
+            }
+            baked.samples.push(baked.lists.len() - 1);
+        }
+        Ok(baked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::square_composition as square;
+
+    #[test]
+    fn baked_frames_match_rendered_frames() {
+        let composition = square();
+        let baked = composition.bake(1.0).unwrap();
+        assert_eq!(baked.samples, [0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+        for frame in [-1.0, 0.0, 4.5, 5.0, 9.9, 20.0, f64::NAN] {
+            let sampled = if frame.is_nan() {
+                0.0
+            } else {
+                frame.clamp(0.0, 9.0).floor()
+            };
+            assert_eq!(
+                baked.display_list(frame),
+                Some(&composition.display_list(sampled)),
+                "at frame {frame}"
+            );
+        }
+
+        assert_eq!(composition.bake(0.0), Err(BakeError::InvalidFrameStep));
+        assert_eq!(composition.bake(f64::NAN), Err(BakeError::InvalidFrameStep));
+        assert_eq!(composition.bake(1e-9), Err(BakeError::TooManyFrames));
+        let empty = Composition {
+            frames: 0.0..0.0,
+            ..square()
+        };
+        assert_eq!(empty.bake(1.0).unwrap().display_list(0.0), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn baked_compositions_round_trip_through_serde() {
+        let baked = square().bake(2.5).unwrap();
+        let json = serde_json::to_string(&baked).unwrap();
+        let parsed: BakedComposition = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, baked);
+    }
+}
@@ -0,0 +1,507 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+use kurbo::{Affine, Point, Vec2};
+
+use crate::{
+    animated::{self, Position},
+    Extrapolation, Layer, SeekHint, SeekHints, Transform, Tween, Value,
+};
+
+/// Number of lanes interpolated together by the inner loop. This is chosen
+/// to fill the vector registers of common targets, so that the loop is
+/// vectorized by the compiler.
+const LANES: usize = 4;
+
+/// Evaluates many animated channels at the same frame.
+///
+/// Keyframe search and easing are done for each channel in turn, after
+/// which the interpolation of all channels is done in a single pass that
+/// the compiler vectorizes. This suits evaluating the properties of many
+/// layers at once: the renderer evaluates every transform channel of a set
+/// of layers with a batch.
+///
+/// The batch owns scratch buffers and a seek hint for each channel, so
+/// reusing it with the same channels in the same order on every frame
+/// avoids allocation and finds keyframes in constant time during playback.
+#[derive(Clone, Default, Debug)]
+pub struct ChannelBatch {
+    from: Vec<f64>,
+    to: Vec<f64>,
+    progress: Vec<f64>,
+    /// Interpolated lanes, once the batch is finished.
+    values: Vec<f64>,
+    hints: Vec<SeekHint>,
+    /// Number of channels pushed since the batch was started.
+    channels: usize,
+}
+
+impl ChannelBatch {
+    /// Creates a new batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the values of the channels at the specified frame to `out`.
+    ///
+    /// The results are identical to evaluating each channel separately.
+    pub fn evaluate<'a>(
+        &mut self,
+        channels: impl IntoIterator<Item = &'a Value<f64>>,
+        frame: f64,
+        out: &mut Vec<f64>,
+    ) {
+        self.begin();
+        for channel in channels {
+            self.push(channel, frame);
+        }
+        self.finish();
+        out.extend_from_slice(&self.values);
+    }
+
+    /// Starts a new batch of channels.
+    pub(crate) fn begin(&mut self) {
+        self.from.clear();
+        self.to.clear();
+        self.progress.clear();
+        self.values.clear();
+        self.channels = 0;
+    }
+
+    /// Adds a channel to evaluate at the specified frame, and returns the
+    /// offset at which to [get](Self::get) its value once the batch is
+    /// finished.
+    pub(crate) fn push<T: Channel>(&mut self, channel: &Value<T>, frame: f64) -> usize {
+        if self.channels == self.hints.len() {
+            self.hints.push(SeekHint::default());
+        }
+        let (from, to, progress) = segment(channel, frame, &mut self.hints[self.channels]);
+        self.channels += 1;
+        let offset = self.from.len();
+        from.write_lanes(&mut self.from);
+        to.write_lanes(&mut self.to);
+        self.progress.resize(self.from.len(), progress);
+        offset
+    }
+
+    /// Interpolates every channel pushed since the batch was started.
+    pub(crate) fn finish(&mut self) {
+        self.values.clear();
+        self.values.resize(self.from.len(), 0.0);
+        lerp(&self.from, &self.to, &self.progress, &mut self.values);
+    }
+
+    /// Returns the value of the channel pushed at `offset`.
+    pub(crate) fn get<T: Channel>(&self, offset: usize) -> T {
+        T::read_lanes(&self.values[offset..offset + T::LANES])
+    }
+}
+
+/// Value made of numeric components that are interpolated independently,
+/// so that a [`ChannelBatch`] can interpolate them together.
+pub(crate) trait Channel: Tween + Copy {
+    /// Number of components.
+    const LANES: usize;
+
+    /// Appends the components of the value to `lanes`.
+    fn write_lanes(&self, lanes: &mut Vec<f64>);
+
+    /// Returns the value with the given components.
+    fn read_lanes(lanes: &[f64]) -> Self;
+}
+
+impl Channel for f64 {
+    const LANES: usize = 1;
+
+    fn write_lanes(&self, lanes: &mut Vec<f64>) {
+        lanes.push(*self);
+    }
+
+    fn read_lanes(lanes: &[f64]) -> Self {
+        lanes[0]
+    }
+}
+
+impl Channel for Point {
+    const LANES: usize = 2;
+
+    fn write_lanes(&self, lanes: &mut Vec<f64>) {
+        lanes.extend_from_slice(&[self.x, self.y]);
+    }
+
+    fn read_lanes(lanes: &[f64]) -> Self {
+        Self::new(lanes[0], lanes[1])
+    }
+}
+
+impl Channel for Vec2 {
+    const LANES: usize = 2;
+
+    fn write_lanes(&self, lanes: &mut Vec<f64>) {
+        lanes.extend_from_slice(&[self.x, self.y]);
+    }
+
+    fn read_lanes(lanes: &[f64]) -> Self {
+        Self::new(lanes[0], lanes[1])
+    }
+}
+
+/// Returns the values surrounding the frame and the eased progress between
+/// them.
+///
+/// Values that aren't interpolated with keyframe easing, such as frames
+/// that are extrapolated, are evaluated here and returned with no progress.
+fn segment<T: Channel>(channel: &Value<T>, frame: f64, hint: &mut SeekHint) -> (T, T, f64) {
+    let animated = match channel {
+        Value::Fixed(value) => return (*value, *value, 0.0),
+        Value::Animated(animated) => animated,
+    };
+    let in_range = match (animated.times.first(), animated.times.last()) {
+        (Some(first), Some(last)) => (first.frame..=last.frame).contains(&frame),
+        _ => true,
+    };
+    if !in_range && animated.extrapolation != Extrapolation::Hold {
+        let value = animated.evaluate_hinted(frame, hint);
+        return (value, value, 0.0);
+    }
+    let Some(([ix0, ix1], t, easing, hold)) = animated.segment(frame, hint) else {
+        return (T::default(), T::default(), 0.0);
+    };
+    let t = if hold { 0.0 } else { t };
+    if !hold && ix0 != ix1 && t > 0.0 && t < 1.0 {
+        if let Some(value) = T::interpolate(animated, ix0, t) {
+            return (value, value, 0.0);
+        }
+    }
+    match (animated.values.get(ix0), animated.values.get(ix1)) {
+        (Some(from), Some(to)) => (*from, *to, easing.as_ref().evaluate(t)),
+        _ => (T::default(), T::default(), 0.0),
+    }
+}
+
+/// Interpolates each lane, matching the [`Tween`] implementation for `f64`.
+///
+/// Lanes with no progress keep their starting value exactly, even when it
+/// isn't finite.
+fn lerp(from: &[f64], to: &[f64], progress: &[f64], out: &mut [f64]) {
+    let mut from_chunks = from.chunks_exact(LANES);
+    let mut to_chunks = to.chunks_exact(LANES);
+    let mut progress_chunks = progress.chunks_exact(LANES);
+    let mut out_chunks = out.chunks_exact_mut(LANES);
+    for (((a, b), t), c) in (&mut from_chunks)
+        .zip(&mut to_chunks)
+        .zip(&mut progress_chunks)
+        .zip(&mut out_chunks)
+    {
+        for lane in 0..LANES {
+            c[lane] = if t[lane] == 0.0 {
+                a[lane]
+            } else {
+                a[lane] + (b[lane] - a[lane]) * t[lane]
+            };
+        }
+    }
+    let remainder = from_chunks
+        .remainder()
+        .iter()
+        .zip(to_chunks.remainder())
+        .zip(progress_chunks.remainder());
+    for (c, ((a, b), t)) in out_chunks.into_remainder().iter_mut().zip(remainder) {
+        *c = if *t == 0.0 { *a } else { a + (b - a) * t };
+    }
+}
+
+/// Local transforms of sets of layers, evaluated in a batch for each set.
+///
+/// Entries are keyed by the address of the set of layers, and only reused
+/// within a single [pass](Self::begin), during which the layers can't
+/// change. Each entry keeps its batch across passes, so that steady
+/// playback doesn't allocate and keeps its seek hints.
+#[derive(Default)]
+pub(crate) struct LayerTransforms {
+    sets: HashMap<usize, SetTransforms>,
+    pass: u64,
+}
+
+#[derive(Default)]
+struct SetTransforms {
+    /// Pass and frame at which the transforms were evaluated.
+    evaluated: Option<(u64, u64)>,
+    batch: ChannelBatch,
+    /// Local transform of each layer, with the offsets in the batch of the
+    /// channels of animated transforms.
+    offsets: Vec<LocalOffsets>,
+    /// Local transform of each layer.
+    locals: Vec<Affine>,
+}
+
+enum LocalOffsets {
+    Fixed(Affine),
+    Animated(TransformOffsets),
+}
+
+/// Offsets in a [`ChannelBatch`] of the channels of an animated transform.
+struct TransformOffsets {
+    anchor: usize,
+    position: PositionOffsets,
+    rotation: usize,
+    scale: usize,
+    skew: usize,
+    skew_angle: usize,
+}
+
+enum PositionOffsets {
+    Value(usize),
+    Split(usize, usize),
+    /// Motion paths aren't interpolated linearly, so these are evaluated
+    /// separately.
+    Evaluated(Point),
+}
+
+impl LayerTransforms {
+    /// Starts a new pass, after which transforms are evaluated again.
+    pub(crate) fn begin(&mut self) {
+        self.pass += 1;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.sets.clear();
+    }
+
+    /// Returns the local transform of each layer in the set at the given
+    /// frame, evaluating them all at once unless that was already done
+    /// during this pass.
+    pub(crate) fn evaluate(
+        &mut self,
+        layers: &[Layer],
+        frame: f64,
+        hints: &mut SeekHints,
+    ) -> &[Affine] {
+        let set = self.sets.entry(layers.as_ptr() as usize).or_default();
+        let evaluated = Some((self.pass, frame.to_bits()));
+        if set.evaluated != evaluated || set.locals.len() != layers.len() {
+            set.evaluate(layers, frame, hints);
+            set.evaluated = evaluated;
+        }
+        &set.locals
+    }
+}
+
+impl SetTransforms {
+    fn evaluate(&mut self, layers: &[Layer], frame: f64, hints: &mut SeekHints) {
+        let batch = &mut self.batch;
+        batch.begin();
+        self.offsets.clear();
+        for layer in layers {
+            let transform = match &layer.transform {
+                Transform::Fixed(transform) => {
+                    self.offsets.push(LocalOffsets::Fixed(*transform));
+                    continue;
+                }
+                Transform::Animated(transform) => transform,
+            };
+            let position = match &transform.position {
+                Position::Value(value) => PositionOffsets::Value(batch.push(value, frame)),
+                Position::SplitValues((x, y)) => {
+                    PositionOffsets::Split(batch.push(x, frame), batch.push(y, frame))
+                }
+                Position::Spatial(spatial) => PositionOffsets::Evaluated(
+                    spatial.evaluate_hinted(frame, hints.get(&spatial.value)),
+                ),
+            };
+            self.offsets.push(LocalOffsets::Animated(TransformOffsets {
+                anchor: batch.push(&transform.anchor, frame),
+                position,
+                rotation: batch.push(&transform.rotation, frame),
+                scale: batch.push(&transform.scale, frame),
+                skew: batch.push(&transform.skew, frame),
+                skew_angle: batch.push(&transform.skew_angle, frame),
+            }));
+        }
+        batch.finish();
+        self.locals.clear();
+        self.locals.extend(self.offsets.iter().map(|offsets| {
+            let offsets = match offsets {
+                LocalOffsets::Fixed(transform) => return *transform,
+                LocalOffsets::Animated(offsets) => offsets,
+            };
+            let position = match offsets.position {
+                PositionOffsets::Value(offset) => batch.get(offset),
+                PositionOffsets::Split(x, y) => Point::new(batch.get(x), batch.get(y)),
+                PositionOffsets::Evaluated(position) => position,
+            };
+            animated::compose_transform(
+                batch.get(offsets.anchor),
+                position,
+                batch.get(offsets.rotation),
+                batch.get(offsets.scale),
+                batch.get(offsets.skew),
+                batch.get(offsets.skew_angle),
+            )
+        }));
+    }
+}
+
+/// Returns the transform of the layer at `index` combined with those of its
+/// chain of parents, given the local transform of each layer.
+///
+/// `parents` yields the indices of the parents, nearest first.
+pub(crate) fn parented_transform(
+    locals: &[Affine],
+    index: usize,
+    parents: impl IntoIterator<Item = usize>,
+) -> Affine {
+    let Some(local) = locals.get(index) else {
+        return Affine::IDENTITY;
+    };
+    parents
+        .into_iter()
+        .fold(*local, |transform, parent| locals[parent] * transform)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::{Animated, Easing, Interpolation, Time};
+
+    fn animated<T: Tween>(times: Vec<Time>, values: Vec<T>) -> Value<T> {
+        Value::Animated(Animated {
+            times,
+            values,
+            extrapolation: Extrapolation::Hold,
+            interpolation: Interpolation::Eased,
+        })
+    }
+
+    #[test]
+    fn batch_matches_separate_evaluation() {
+        let time = |frame, easing| Time::new(frame).with_easing(easing);
+        let channels: Vec<Value<f64>> = (0..11)
+            .map(|ix| match ix % 4 {
+                0 => Value::Fixed(ix as f64),
+                1 => {
+                    let mut value = animated(
+                        vec![time(0.0, Easing::LERP), time(20.0, Easing::LERP)],
+                        vec![0.0, ix as f64 * 10.0],
+                    );
+                    if let Value::Animated(animated) = &mut value {
+                        animated.extrapolation = Extrapolation::Linear;
+                    }
+                    value
+                }
+                2 => animated(
+                    vec![
+                        time(ix as f64, Easing::cubic_bezier(0.4, 0.0, 0.2, 1.0)),
+                        time(15.0, Easing::Hold),
+                        time(18.0, Easing::LERP),
+                    ],
+                    vec![-5.0, 5.0, 1.0],
+                ),
+                _ => {
+                    let mut value = animated(
+                        vec![Time::new(0.0), Time::new(8.0), Time::new(16.0)],
+                        vec![0.0, 10.0, 5.0],
+                    );
+                    if let Value::Animated(animated) = &mut value {
+                        animated.interpolation = Interpolation::MonotoneCubic;
+                    }
+                    value
+                }
+            })
+            .collect();
+        let mut batch = ChannelBatch::new();
+        let mut out = vec![];
+        for frame in [-3.0, 0.0, 4.5, 10.0, 16.0, 19.0, 25.0] {
+            out.clear();
+            batch.evaluate(&channels, frame, &mut out);
+            let expected: Vec<f64> = channels.iter().map(|c| c.evaluate(frame)).collect();
+            assert_eq!(out, expected, "batch should match at frame {frame}");
+        }
+    }
+
+    #[test]
+    fn layer_transforms_match_separate_evaluation() {
+        let eased = |times: [f64; 2]| {
+            vec![
+                Time::new(times[0]).with_easing(Easing::cubic_bezier(0.3, 0.0, 0.6, 1.0)),
+                Time::new(times[1]),
+            ]
+        };
+        let transform = |ix: usize, position| {
+            let offset = ix as f64;
+            Transform::Animated(animated::Transform {
+                anchor: animated(
+                    eased([0.0, 10.0]),
+                    vec![Point::ZERO, Point::new(offset, 5.0)],
+                ),
+                position,
+                rotation: animated(eased([2.0, 8.0]), vec![0.0, 90.0 + offset]),
+                scale: animated(
+                    eased([0.0, 10.0]),
+                    vec![Vec2::new(100.0, 100.0), Vec2::new(50.0, 200.0)],
+                ),
+                skew: Value::Fixed(offset),
+                skew_angle: animated(eased([0.0, 10.0]), vec![0.0, 45.0]),
+            })
+        };
+        let layers: Vec<Layer> = (0..7)
+            .map(|ix| {
+                let position = match ix % 3 {
+                    0 => Position::Value(animated(
+                        eased([0.0, 10.0]),
+                        vec![Point::ZERO, Point::new(20.0, ix as f64)],
+                    )),
+                    1 => Position::SplitValues((
+                        animated(eased([0.0, 5.0]), vec![0.0, 20.0]),
+                        Value::Fixed(ix as f64),
+                    )),
+                    _ => Position::Spatial(animated::SpatialPosition {
+                        value: Animated {
+                            times: eased([0.0, 10.0]),
+                            values: vec![Point::ZERO, Point::new(20.0, 0.0)],
+                            extrapolation: Extrapolation::Loop,
+                            interpolation: Interpolation::Eased,
+                        },
+                        tangents: vec![animated::SpatialTangents {
+                            out_tangent: Vec2::new(0.0, 10.0),
+                            in_tangent: Vec2::new(0.0, 10.0),
+                        }],
+                    }),
+                };
+                Layer {
+                    transform: if ix == 3 {
+                        Transform::Fixed(Affine::translate((1.0, 2.0)))
+                    } else {
+                        transform(ix, position)
+                    },
+                    parent: ix.checked_sub(1),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let mut transforms = LayerTransforms::default();
+        let mut hints = SeekHints::default();
+        for frame in [-1.0, 0.0, 3.5, 7.25, 10.0, 12.0] {
+            transforms.begin();
+            let locals = transforms.evaluate(&layers, frame, &mut hints);
+            for (index, layer) in layers.iter().enumerate() {
+                assert_eq!(
+                    locals[index],
+                    layer.transform.evaluate(frame).into_owned(),
+                    "layer {index} at frame {frame}"
+                );
+                let parents = (0..index).rev();
+                assert_eq!(
+                    parented_transform(locals, index, parents),
+                    layer.parented_transform(&layers, frame),
+                    "parented layer {index} at frame {frame}"
+                );
+            }
+        }
+    }
+}
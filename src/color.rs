@@ -0,0 +1,310 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use core::f64::consts::{PI, TAU};
+
+use crate::{fixed, Animated, Interpolator};
+
+/// Color space in which colors are interpolated.
+///
+/// Interpolating the components of sRGB colors directly, as the [`Tween`]
+/// implementation for colors does, tends to produce dark or desaturated
+/// colors midway between saturated colors. The perceptual spaces avoid
+/// this.
+///
+/// Use a color space with [`Animated::evaluate_with`] to interpolate
+/// animated colors, or set it on gradient color stops.
+///
+/// [`Tween`]: crate::Tween
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum ColorSpace {
+    /// Gamma encoded sRGB components.
+    #[default]
+    Srgb,
+    /// Linear light sRGB components.
+    LinearSrgb,
+    /// Oklab perceptual color space.
+    Oklab,
+    /// Cylindrical form of Oklab.
+    Oklch,
+    /// Cylindrical form of CIELAB with a D65 white point.
+    Lch,
+}
+
+/// Direction in which hue is interpolated in cylindrical color spaces.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum HueDirection {
+    /// Take the shorter arc between the hues.
+    #[default]
+    Shorter,
+    /// Take the longer arc between the hues, as in CSS. Equal hues go all
+    /// the way around the color wheel.
+    Longer,
+}
+
+impl ColorSpace {
+    /// Returns the color at progress `t` between two colors. The progress
+    /// should already be eased.
+    ///
+    /// Hue is interpolated along the shorter arc. Alpha is always
+    /// interpolated linearly and without premultiplication.
+    pub fn mix(self, a: fixed::Color, b: fixed::Color, t: f64) -> fixed::Color {
+        self.mix_hue(a, b, t, HueDirection::Shorter)
+    }
+
+    /// Same as [`ColorSpace::mix`], but interpolates hue in the given
+    /// direction. The direction has no effect on spaces without hue.
+    pub fn mix_hue(
+        self,
+        a: fixed::Color,
+        b: fixed::Color,
+        t: f64,
+        hue: HueDirection,
+    ) -> fixed::Color {
+        let [r, g, b, a] = self.mix_components(components(a), components(b), t, hue);
+        fixed::Color::rgba(r, g, b, a)
+    }
+
+    /// Interpolates sRGB components in the range 0 to 1, with alpha last.
+    pub(crate) fn mix_components(
+        self,
+        a: [f64; 4],
+        b: [f64; 4],
+        t: f64,
+        direction: HueDirection,
+    ) -> [f64; 4] {
+        let lerp = |from: f64, to: f64| from + (to - from) * t;
+        if self == Self::Srgb {
+            return [0, 1, 2, 3].map(|i| lerp(a[i], b[i]));
+        }
+        let [a0, a1, a2] = self.srgb_to_space([a[0], a[1], a[2]]);
+        let [b0, b1, b2] = self.srgb_to_space([b[0], b[1], b[2]]);
+        let hue = match self {
+            Self::Oklch | Self::Lch => {
+                // Achromatic colors have no meaningful hue, so take the hue
+                // of the other color.
+                let achromatic = (a1 <= ACHROMATIC, b1 <= ACHROMATIC);
+                let (a2, b2) = match achromatic {
+                    (true, false) => (b2, b2),
+                    (false, true) => (a2, a2),
+                    _ => (a2, b2),
+                };
+                let mut delta = (b2 - a2) % TAU;
+                if delta > PI {
+                    delta -= TAU;
+                } else if delta < -PI {
+                    delta += TAU;
+                }
+                if direction == HueDirection::Longer && achromatic == (false, false) {
+                    if delta > 0.0 {
+                        delta -= TAU;
+                    } else {
+                        delta += TAU;
+                    }
+                }
+                a2 + delta * t
+            }
+            _ => lerp(a2, b2),
+        };
+        let [c0, c1, c2] = self.space_to_srgb([lerp(a0, b0), lerp(a1, b1), hue]);
+        [c0, c1, c2, lerp(a[3], b[3])]
+    }
+
+    /// Converts sRGB components to components in this space.
+    fn srgb_to_space(self, srgb: [f64; 3]) -> [f64; 3] {
+        let linear = srgb.map(to_linear);
+        match self {
+            Self::Srgb => srgb,
+            Self::LinearSrgb => linear,
+            Self::Oklab => oklab_from_linear(linear),
+            Self::Oklch => polar(oklab_from_linear(linear)),
+            Self::Lch => polar(lab_from_linear(linear)),
+        }
+    }
+
+    /// Converts components in this space to sRGB components.
+    fn space_to_srgb(self, color: [f64; 3]) -> [f64; 3] {
+        let linear = match self {
+            Self::Srgb => return color,
+            Self::LinearSrgb => color,
+            Self::Oklab => linear_from_oklab(color),
+            Self::Oklch => linear_from_oklab(rectangular(color)),
+            Self::Lch => linear_from_lab(rectangular(color)),
+        };
+        linear.map(from_linear)
+    }
+}
+
+impl Interpolator<fixed::Color> for ColorSpace {
+    fn interpolate(
+        &self,
+        animated: &Animated<fixed::Color>,
+        segment: usize,
+        t: f64,
+    ) -> fixed::Color {
+        let (Some(v0), Some(v1)) = (
+            animated.values.get(segment),
+            animated.values.get(segment + 1),
+        ) else {
+            return fixed::Color::default();
+        };
+        let t = animated.times[segment].easing().as_ref().evaluate(t);
+        self.mix(*v0, *v1, t)
+    }
+}
+
+/// Returns the components of a color in the range 0 to 1, with alpha last.
+fn components(color: fixed::Color) -> [f64; 4] {
+    [color.r, color.g, color.b, color.a].map(|c| c as f64 / 255.0)
+}
+
+/// Chroma below which a color is considered achromatic.
+const ACHROMATIC: f64 = 1e-4;
+
+fn to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f64) -> f64 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn oklab_from_linear([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+    let m = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+    let s = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+    [
+        0.210_454_255_3 * l + 0.793_617_785_0 * m - 0.004_072_046_8 * s,
+        1.977_998_495_1 * l - 2.428_592_205_0 * m + 0.450_593_709_9 * s,
+        0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766_0 * s,
+    ]
+}
+
+fn linear_from_oklab([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = l + 0.396_337_777_4 * a + 0.215_803_757_3 * b;
+    let m_ = l - 0.105_561_345_8 * a - 0.063_854_172_8 * b;
+    let s_ = l - 0.089_484_177_5 * a - 1.291_485_548_0 * b;
+    let [l3, m3, s3] = [l_, m_, s_].map(|c| c * c * c);
+    [
+        4.076_741_662_1 * l3 - 3.307_711_591_3 * m3 + 0.230_969_929_2 * s3,
+        -1.268_438_004_6 * l3 + 2.609_757_401_1 * m3 - 0.341_319_396_5 * s3,
+        -0.004_196_086_3 * l3 - 0.703_418_614_7 * m3 + 1.707_614_701_0 * s3,
+    ]
+}
+
+/// D65 reference white in XYZ.
+const WHITE: [f64; 3] = [0.950_47, 1.0, 1.088_83];
+
+fn lab_from_linear([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let xyz = [
+        0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b,
+        0.212_672_9 * r + 0.715_152_2 * g + 0.072_175_0 * b,
+        0.019_333_9 * r + 0.119_192_0 * g + 0.950_304_1 * b,
+    ];
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let [fx, fy, fz] = [0, 1, 2].map(|i| f(xyz[i] / WHITE[i]));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn linear_from_lab([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let f_inv = |f: f64| {
+        let t = f * f * f;
+        if t > 216.0 / 24389.0 {
+            t
+        } else {
+            (116.0 * f - 16.0) * 27.0 / 24389.0
+        }
+    };
+    let [x, y, z] = [fy + a / 500.0, fy, fy - b / 200.0].map(f_inv);
+    let [x, y, z] = [x * WHITE[0], y * WHITE[1], z * WHITE[2]];
+    [
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266_0 * x + 1.876_010_8 * y + 0.041_556_0 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+    ]
+}
+
+/// Converts rectangular lightness and chroma axes to lightness, chroma and
+/// hue.
+fn polar([l, a, b]: [f64; 3]) -> [f64; 3] {
+    [l, a.hypot(b), b.atan2(a)]
+}
+
+/// Converts lightness, chroma and hue to rectangular axes.
+fn rectangular([l, c, h]: [f64; 3]) -> [f64; 3] {
+    let (sin, cos) = h.sin_cos();
+    [l, c * cos, c * sin]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perceptual_spaces_avoid_dark_midpoints() {
+        let red = fixed::Color::rgb8(255, 0, 0);
+        let green = fixed::Color::rgb8(0, 255, 0);
+        let spaces = [
+            ColorSpace::Srgb,
+            ColorSpace::LinearSrgb,
+            ColorSpace::Oklab,
+            ColorSpace::Oklch,
+            ColorSpace::Lch,
+        ];
+        for space in spaces {
+            assert_eq!(
+                space.mix(red, green, 0.0),
+                red,
+                "{space:?} should start at red"
+            );
+            assert_eq!(
+                space.mix(red, green, 1.0),
+                green,
+                "{space:?} should end at green"
+            );
+        }
+        let lightness = |color: fixed::Color| {
+            let [r, g, b, _] = components(color);
+            ColorSpace::Oklab.srgb_to_space([r, g, b])[0]
+        };
+        let srgb = lightness(ColorSpace::Srgb.mix(red, green, 0.5));
+        let oklab = lightness(ColorSpace::Oklab.mix(red, green, 0.5));
+        assert!(
+            oklab > srgb,
+            "Oklab should avoid the dark midpoint of sRGB interpolation"
+        );
+        // The shorter arc from red to blue passes through magenta rather
+        // than green.
+        let blue = fixed::Color::rgb8(0, 0, 255);
+        let shorter = ColorSpace::Oklch.mix(red, blue, 0.5);
+        assert!(
+            shorter.g < shorter.r.min(shorter.b),
+            "hue should take the shorter arc, got {shorter:?}"
+        );
+        let longer = ColorSpace::Oklch.mix_hue(red, blue, 0.5, HueDirection::Longer);
+        assert!(
+            longer.g > longer.r.max(longer.b),
+            "hue should take the longer arc, got {longer:?}"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use crate::{
+    analysis::{sample_frames, State},
+    Animated, Composition, Limits, Linear, Property,
+};
+
+/// Options for comparing two versions of an animation.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    /// Range of frames to compare. Defaults to the combined frames of both
+    /// versions when `None`.
+    pub frames: Option<Range<f64>>,
+    /// Distance between sampled frames.
+    pub step: f64,
+    /// Maximum difference for values to be considered equal.
+    pub tolerance: f64,
+    /// Limits on the structure that is compared. Instances nested deeper
+    /// than the limit are not compared.
+    pub limits: Limits,
+}
+
+impl Default for Comparison {
+    fn default() -> Self {
+        Self {
+            frames: None,
+            step: 1.0,
+            tolerance: 1e-3,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// Difference between two versions of an animated value over a range of
+/// frames.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Divergence {
+    /// Largest difference at any sampled frame. This is infinite if the
+    /// versions differ in structure, such as the number of path elements.
+    pub max_delta: f64,
+    /// Frame at which the largest difference occurs.
+    pub max_delta_frame: f64,
+    /// First sampled frame at which the difference exceeds the tolerance,
+    /// or `None` if the versions match everywhere.
+    pub first_frame: Option<f64>,
+}
+
+impl Divergence {
+    fn new() -> Self {
+        Self {
+            max_delta: 0.0,
+            max_delta_frame: 0.0,
+            first_frame: None,
+        }
+    }
+
+    fn sample(&mut self, frame: f64, delta: f64, tolerance: f64) {
+        if delta > self.max_delta {
+            self.max_delta = delta;
+            self.max_delta_frame = frame;
+        }
+        if delta > tolerance && self.first_frame.is_none() {
+            self.first_frame = Some(frame);
+        }
+    }
+}
+
+/// Divergence of one class of property of a top level layer.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PropertyDivergence {
+    /// Index of the top level layer, including the content of any assets it
+    /// instances.
+    pub layer: usize,
+    /// Class of property.
+    pub property: Property,
+    /// Difference between the two versions.
+    pub divergence: Divergence,
+}
+
+impl Composition {
+    /// Evaluates this composition and another version of it at the same
+    /// frames and reports the properties that differ by more than the
+    /// tolerance.
+    ///
+    /// This is useful to check that an optimization, such as keyframe
+    /// reduction or baking, didn't visibly change the animation. Layers are
+    /// matched by index. The result is ordered by layer and then by class
+    /// of property.
+    pub fn compare(&self, other: &Self, comparison: &Comparison) -> Vec<PropertyDivergence> {
+        let frames = comparison.frames.clone().unwrap_or(
+            self.frames.start.min(other.frames.start)..self.frames.end.max(other.frames.end),
+        );
+        let mut result: Vec<PropertyDivergence> = Vec::new();
+        let layer_count = self.layers.len().max(other.layers.len());
+        for frame in sample_frames(frames, comparison.step) {
+            for index in 0..layer_count {
+                let mut a = State::new(comparison.limits);
+                let mut b = State::new(comparison.limits);
+                if let Some(layer) = self.layers.get(index) {
+                    a.layer(self, layer, frame);
+                }
+                if let Some(layer) = other.layers.get(index) {
+                    b.layer(other, layer, frame);
+                }
+                a.finish();
+                b.finish();
+                for (property, delta) in property_deltas(&a, &b) {
+                    let position = result
+                        .iter()
+                        .position(|entry| entry.layer == index && entry.property == property)
+                        .unwrap_or_else(|| {
+                            result.push(PropertyDivergence {
+                                layer: index,
+                                property,
+                                divergence: Divergence::new(),
+                            });
+                            result.len() - 1
+                        });
+                    result[position]
+                        .divergence
+                        .sample(frame, delta, comparison.tolerance);
+                }
+            }
+        }
+        result.retain(|entry| entry.divergence.first_frame.is_some());
+        result.sort_by_key(|entry| (entry.layer, entry.property as u8));
+        result
+    }
+}
+
+impl<T: Linear> Animated<T> {
+    /// Evaluates this animation and another version of it at the same
+    /// frames and reports how far apart they are.
+    ///
+    /// The difference at each frame is the Euclidean distance between the
+    /// values.
+    pub fn compare(&self, other: &Self, comparison: &Comparison) -> Divergence {
+        let frames = comparison.frames.clone().unwrap_or_else(|| {
+            let range = |times: &[crate::Time]| {
+                times.first().map_or(f64::INFINITY, |time| time.frame)
+                    ..times.last().map_or(f64::NEG_INFINITY, |time| time.frame)
+            };
+            let (a, b) = (range(&self.times), range(&other.times));
+            // Include the last keyframe.
+            a.start.min(b.start)..a.end.max(b.end) + comparison.step
+        });
+        let mut divergence = Divergence::new();
+        for frame in sample_frames(frames, comparison.step) {
+            let delta = self.evaluate(frame).add_scaled(other.evaluate(frame), -1.0);
+            divergence.sample(frame, delta.dot(delta).sqrt(), comparison.tolerance);
+        }
+        divergence
+    }
+}
+
+/// Returns the largest difference for each class of property of two
+/// sampled states.
+fn property_deltas(a: &State, b: &State) -> Vec<(Property, f64)> {
+    let mut deltas: Vec<(Property, f64)> = Vec::new();
+    let mut record = |property: Property, delta: f64| match deltas
+        .iter_mut()
+        .find(|(entry, _)| *entry == property)
+    {
+        Some((_, max)) => *max = max.max(delta),
+        None => deltas.push((property, delta)),
+    };
+    if a.properties() != b.properties() {
+        // The versions differ in structure, so values can't be matched.
+        for property in a.properties().iter().chain(b.properties()) {
+            record(*property, f64::INFINITY);
+        }
+        return deltas;
+    }
+    for ((value_a, value_b), property) in a.values.iter().zip(&b.values).zip(a.properties()) {
+        record(*property, (value_a - value_b).abs());
+    }
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Layer, Time, Value};
+    use alloc::vec;
+
+    #[test]
+    fn compare_reports_first_differing_frame() {
+        let original = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0), Time::new(20.0)],
+            values: vec![0.0, 50.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let reduced = Animated {
+            times: vec![Time::new(0.0), Time::new(20.0)],
+            values: vec![0.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let divergence = original.compare(&reduced, &Comparison::default());
+        assert!(
+            divergence.max_delta < 1e-6,
+            "removing a redundant keyframe should not change the animation"
+        );
+        assert_eq!(divergence.first_frame, None);
+
+        let changed = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0), Time::new(20.0)],
+            values: vec![0.0, 60.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let composition = |opacity: &Animated<f64>| Composition {
+            frames: 0.0..20.0,
+            layers: vec![Layer {
+                frames: 0.0..20.0,
+                opacity: Value::Animated(opacity.clone()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let divergences =
+            composition(&original).compare(&composition(&changed), &Comparison::default());
+        assert_eq!(divergences.len(), 1);
+        let entry = divergences[0];
+        assert_eq!((entry.layer, entry.property), (0, Property::Opacity));
+        assert_eq!(entry.divergence.first_frame, Some(1.0));
+        assert!(
+            (entry.divergence.max_delta - 10.0).abs() < 1e-6,
+            "the largest difference should be at the changed keyframe"
+        );
+        assert_eq!(entry.divergence.max_delta_frame, 10.0);
+    }
+}
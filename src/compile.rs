@@ -0,0 +1,169 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+use hashbrown::HashMap;
+
+use crate::{Composition, Content, Layer};
+
+/// Composition prepared for evaluation at many frames.
+///
+/// Compiling resolves the structure of a composition that doesn't change
+/// from frame to frame: the chain of parents of each layer is flattened
+/// into a list, and the asset of each precomposition instance is looked up
+/// once. Pass the result to
+/// [`Renderer::append_compiled`](crate::Renderer::append_compiled) to
+/// avoid resolving this structure again for every frame.
+///
+/// The compiled composition borrows the composition, so it must be compiled
+/// again after the composition is modified.
+#[derive(Clone, Debug)]
+pub struct CompiledComposition<'a> {
+    composition: &'a Composition,
+    /// Top level layers followed by the layers of each asset.
+    sets: Vec<CompiledLayers<'a>>,
+}
+
+/// Resolved structure of a set of layers.
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledLayers<'a> {
+    pub(crate) layers: &'a [Layer],
+    /// Range of `parents` holding the chain of parents of each layer.
+    chains: Vec<Range<usize>>,
+    /// Indices of the parents of each layer, nearest first.
+    parents: Vec<usize>,
+    /// Index of the compiled set of the asset of each instance layer.
+    pub(crate) instances: Vec<Option<usize>>,
+}
+
+impl Composition {
+    /// Returns the composition with its structure resolved for evaluation
+    /// at many frames.
+    ///
+    /// See [`CompiledComposition`].
+    pub fn compile(&self) -> CompiledComposition<'_> {
+        let mut sets = vec![self.layers.as_slice()];
+        let mut assets = HashMap::new();
+        for (name, layers) in &self.assets {
+            assets.insert(name.as_str(), sets.len());
+            sets.push(layers);
+        }
+        CompiledComposition {
+            composition: self,
+            sets: sets
+                .into_iter()
+                .map(|layers| CompiledLayers::new(layers, &assets))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> CompiledComposition<'a> {
+    /// Returns the composition that was compiled.
+    pub fn composition(&self) -> &'a Composition {
+        self.composition
+    }
+
+    /// Returns the top level layers when `index` is zero, and the layers of
+    /// an asset otherwise.
+    pub(crate) fn set(&self, index: usize) -> Option<&CompiledLayers<'a>> {
+        self.sets.get(index)
+    }
+}
+
+impl<'a> CompiledLayers<'a> {
+    fn new(layers: &'a [Layer], assets: &HashMap<&str, usize>) -> Self {
+        let mut chains = Vec::with_capacity(layers.len());
+        let mut parents = Vec::new();
+        for layer in layers {
+            let start = parents.len();
+            parents.extend(layer.parents(layers));
+            chains.push(start..parents.len());
+        }
+        let instances = layers
+            .iter()
+            .map(|layer| match &layer.content {
+                Content::Instance { name, .. } => assets.get(name.as_str()).copied(),
+                _ => None,
+            })
+            .collect();
+        Self {
+            layers,
+            chains,
+            parents,
+            instances,
+        }
+    }
+
+    /// Returns the indices of the chain of parents of the layer at `index`,
+    /// nearest first.
+    pub(crate) fn parents(&self, index: usize) -> &[usize] {
+        self.chains
+            .get(index)
+            .map_or(&[], |chain| &self.parents[chain.clone()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+    use kurbo::Affine;
+
+    use super::*;
+    use crate::{batch, Transform};
+
+    #[test]
+    fn compile_resolves_parents_and_instances() {
+        let translate = |x| Transform::Fixed(Affine::translate((x, 0.0)));
+        let mut composition = Composition {
+            layers: vec![
+                Layer {
+                    transform: translate(1.0),
+                    parent: Some(1),
+                    content: Content::Instance {
+                        name: "asset".to_string(),
+                        time_remap: None,
+                    },
+                    ..Default::default()
+                },
+                Layer {
+                    transform: translate(10.0),
+                    parent: Some(2),
+                    ..Default::default()
+                },
+                Layer {
+                    transform: translate(100.0),
+                    // A cycle, which must not loop forever.
+                    parent: Some(1),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        composition
+            .assets
+            .insert("asset".to_string(), vec![Layer::default()]);
+        let compiled = composition.compile();
+        let top = compiled.set(0).unwrap();
+        let locals: Vec<Affine> = composition
+            .layers
+            .iter()
+            .map(|layer| layer.transform.evaluate(0.0).into_owned())
+            .collect();
+        for (index, layer) in composition.layers.iter().enumerate() {
+            assert_eq!(
+                batch::parented_transform(&locals, index, top.parents(index).iter().copied()),
+                layer.parented_transform(&composition.layers, 0.0),
+                "compiled transform of layer {index} should match"
+            );
+        }
+        let asset = top.instances[0].expect("instance should be resolved");
+        assert_eq!(
+            compiled.set(asset).unwrap().layers.as_ptr(),
+            composition.assets["asset"].as_ptr()
+        );
+        assert_eq!(top.instances[1], None);
+    }
+}
@@ -1,16 +1,20 @@
 // Copyright 2024 the Interpoli Authors
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
-use alloc::{string::String, vec::Vec};
+use alloc::{boxed::Box, string::String, vec::Vec};
 use core::ops::Range;
-use kurbo::{PathEl, Shape as _};
+use core::sync::atomic::{AtomicU64, Ordering};
+use kurbo::{Affine, PathEl, Shape as _};
 
 use hashbrown::HashMap;
 
-use crate::{animated, Brush, Repeater, Stroke, Transform, Value};
+use crate::{
+    animated, keyframes::Keyframed, spline::SplineShape, Animated, Brush, Modifier, Repeater,
+    Stroke, TimeVariation, Transform, Value,
+};
 
 /// Model of a Lottie file.
-#[derive(Clone, Default, Debug)]
+#[derive(Default, Debug)]
 pub struct Composition {
     /// Frames in which the animation is active.
     pub frames: Range<f64>,
@@ -24,6 +28,47 @@ pub struct Composition {
     pub assets: HashMap<String, Vec<Layer>>,
     /// Collection of layers.
     pub layers: Vec<Layer>,
+    /// Identity of the composition, which keys the state that renderers
+    /// cache across frames.
+    ///
+    /// Clones are given a new identity. Editing keyframes with methods such
+    /// as [`Animated::insert_keyframe`](crate::Animated::insert_keyframe)
+    /// discards the cached state on its own. Assign a new identity after
+    /// modifying the fields of a composition in place by other means.
+    pub id: CompositionId,
+}
+
+impl Clone for Composition {
+    fn clone(&self) -> Self {
+        Self {
+            frames: self.frames.clone(),
+            frame_rate: self.frame_rate,
+            width: self.width,
+            height: self.height,
+            assets: self.assets.clone(),
+            layers: self.layers.clone(),
+            id: CompositionId::new(),
+        }
+    }
+}
+
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Unique identity of a [`Composition`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CompositionId(u64);
+
+impl CompositionId {
+    /// Creates a new identity, distinct from all others.
+    pub fn new() -> Self {
+        Self(ID_COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for CompositionId {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -31,7 +76,13 @@ pub enum Geometry {
     Fixed(Vec<PathEl>),
     Rect(animated::Rect),
     Ellipse(animated::Ellipse),
+    /// Star or regular polygon.
+    PolyStar(Box<animated::PolyStar>),
     Spline(animated::Spline),
+    /// Path with independently keyframed vertices.
+    Path(animated::Path),
+    /// Path morphing between arbitrary keyframed paths.
+    Morph(Animated<kurbo::BezPath>),
 }
 
 impl Geometry {
@@ -46,9 +97,174 @@ impl Geometry {
             Self::Ellipse(value) => {
                 path.extend(value.evaluate(frame).path_elements(0.1));
             }
+            Self::PolyStar(value) => {
+                path.extend_from_slice(value.evaluate(frame).elements());
+            }
             Self::Spline(value) => {
                 value.evaluate(frame, path);
             }
+            Self::Path(value) => {
+                value.evaluate(frame, path);
+            }
+            Self::Morph(value) => {
+                path.extend_from_slice(value.evaluate(frame).elements());
+            }
+        }
+    }
+
+    /// Evaluates the geometry at the given frame and appends a simplified
+    /// version of the path to `path`.
+    ///
+    /// The path is refit with as few curves as possible while staying within
+    /// `tolerance` of the original, which helps keep element counts down for
+    /// densely sampled imported splines. The original path is appended if
+    /// simplification doesn't reduce the number of elements.
+    pub fn evaluate_simplified(&self, frame: f64, tolerance: f64, path: &mut Vec<PathEl>) {
+        let start = path.len();
+        self.evaluate(frame, path);
+        let simplified = kurbo::simplify::simplify_bezpath(
+            path[start..].iter().copied(),
+            tolerance,
+            &kurbo::simplify::SimplifyOptions::default(),
+        );
+        if simplified.elements().len() < path.len() - start {
+            path.truncate(start);
+            path.extend_from_slice(simplified.elements());
+        }
+    }
+
+    /// Returns true if the geometry is known to evaluate identically at
+    /// both frames.
+    pub fn is_constant_between(&self, a: f64, b: f64) -> bool {
+        <Self as Keyframed>::is_constant_between(self, a, b)
+    }
+
+    /// Returns the geometry at the given frame as a shape that can be used
+    /// without collecting its path elements first.
+    pub fn shape(&self, frame: f64) -> GeometryShape<'_> {
+        match self {
+            Self::Fixed(value) => GeometryShape::Path(value),
+            Self::Rect(value) => GeometryShape::Rect(value.evaluate(frame)),
+            Self::Ellipse(value) => GeometryShape::Ellipse(value.evaluate(frame)),
+            Self::PolyStar(value) => GeometryShape::Owned(value.evaluate(frame)),
+            Self::Spline(value) => GeometryShape::Spline(value.shape(frame)),
+            Self::Path(value) => {
+                let mut path = Vec::new();
+                value.evaluate(frame, &mut path);
+                GeometryShape::Owned(kurbo::BezPath::from_vec(path))
+            }
+            Self::Morph(value) => GeometryShape::Owned(value.evaluate(frame)),
+        }
+    }
+}
+
+/// Geometry evaluated at a single frame.
+#[derive(Clone, Debug)]
+pub enum GeometryShape<'a> {
+    /// Fixed path.
+    Path(&'a [PathEl]),
+    /// Rounded rectangle.
+    Rect(kurbo::RoundedRect),
+    /// Ellipse.
+    Ellipse(kurbo::Ellipse),
+    /// Spline.
+    Spline(SplineShape<'a>),
+    /// Path evaluated from a star, a path with animated vertices or a morph.
+    Owned(kurbo::BezPath),
+}
+
+/// Iterator over the path elements of a [`GeometryShape`].
+#[allow(missing_debug_implementations)]
+// Boxing the larger iterators would defeat the purpose of avoiding
+// allocations.
+#[allow(clippy::large_enum_variant)]
+pub enum GeometryPathElements<'a> {
+    /// Elements of a fixed path.
+    Path(core::iter::Copied<core::slice::Iter<'a, PathEl>>),
+    /// Elements of a rounded rectangle.
+    Rect(<kurbo::RoundedRect as kurbo::Shape>::PathElementsIter<'a>),
+    /// Elements of an ellipse.
+    Ellipse(<kurbo::Ellipse as kurbo::Shape>::PathElementsIter<'a>),
+    /// Elements of a spline.
+    Spline(<SplineShape<'a> as kurbo::Shape>::PathElementsIter<'a>),
+}
+
+impl Iterator for GeometryPathElements<'_> {
+    type Item = PathEl;
+
+    fn next(&mut self) -> Option<PathEl> {
+        match self {
+            Self::Path(iter) => iter.next(),
+            Self::Rect(iter) => iter.next(),
+            Self::Ellipse(iter) => iter.next(),
+            Self::Spline(iter) => iter.next(),
+        }
+    }
+}
+
+impl<'a> kurbo::Shape for GeometryShape<'a> {
+    type PathElementsIter<'iter>
+        = GeometryPathElements<'iter>
+    where
+        Self: 'iter;
+
+    fn path_elements(&self, tolerance: f64) -> Self::PathElementsIter<'_> {
+        match self {
+            Self::Path(path) => GeometryPathElements::Path(path.path_elements(tolerance)),
+            Self::Rect(rect) => GeometryPathElements::Rect(rect.path_elements(tolerance)),
+            Self::Ellipse(ellipse) => {
+                GeometryPathElements::Ellipse(ellipse.path_elements(tolerance))
+            }
+            Self::Spline(spline) => GeometryPathElements::Spline(spline.path_elements(tolerance)),
+            Self::Owned(path) => GeometryPathElements::Path(path.path_elements(tolerance)),
+        }
+    }
+
+    fn area(&self) -> f64 {
+        match self {
+            Self::Path(path) => path.area(),
+            Self::Rect(rect) => rect.area(),
+            Self::Ellipse(ellipse) => ellipse.area(),
+            Self::Spline(spline) => spline.area(),
+            Self::Owned(path) => path.area(),
+        }
+    }
+
+    fn perimeter(&self, accuracy: f64) -> f64 {
+        match self {
+            Self::Path(path) => path.perimeter(accuracy),
+            Self::Rect(rect) => rect.perimeter(accuracy),
+            Self::Ellipse(ellipse) => ellipse.perimeter(accuracy),
+            Self::Spline(spline) => spline.perimeter(accuracy),
+            Self::Owned(path) => path.perimeter(accuracy),
+        }
+    }
+
+    fn winding(&self, pt: kurbo::Point) -> i32 {
+        match self {
+            Self::Path(path) => path.winding(pt),
+            Self::Rect(rect) => rect.winding(pt),
+            Self::Ellipse(ellipse) => ellipse.winding(pt),
+            Self::Spline(spline) => spline.winding(pt),
+            Self::Owned(path) => path.winding(pt),
+        }
+    }
+
+    fn bounding_box(&self) -> kurbo::Rect {
+        match self {
+            Self::Path(path) => path.bounding_box(),
+            Self::Rect(rect) => rect.bounding_box(),
+            Self::Ellipse(ellipse) => ellipse.bounding_box(),
+            Self::Spline(spline) => spline.bounding_box(),
+            Self::Owned(path) => path.bounding_box(),
+        }
+    }
+
+    fn as_path_slice(&self) -> Option<&[PathEl]> {
+        match self {
+            Self::Path(path) => Some(path),
+            Self::Owned(path) => Some(path.elements()),
+            _ => None,
         }
     }
 }
@@ -59,10 +275,40 @@ pub struct Draw {
     pub stroke: Option<Stroke>,
     /// Brush for the draw operation.
     pub brush: Brush,
-    /// Opacity of the draw operation.
+    /// Opacity of the draw operation in percent.
     pub opacity: Value<f64>,
 }
 
+/// Brush that fills shapes with the content of an asset, such as an
+/// animated pattern inside the outline of some text.
+#[derive(Clone, Debug)]
+pub struct InstanceBrush {
+    /// Name of the asset.
+    pub name: String,
+    /// Transform from the coordinate space of the asset to that of the
+    /// shape.
+    pub transform: Transform,
+}
+
+/// Brush that fills shapes with a repeating pattern.
+#[derive(Clone, Debug)]
+pub struct PatternBrush {
+    /// Content of a single tile.
+    pub source: PatternSource,
+    /// Transform from the coordinate space of the pattern to that of the
+    /// shape. Animating this scrolls, rotates or scales the pattern.
+    pub transform: Transform,
+}
+
+/// Content of a single tile of a [`PatternBrush`].
+#[derive(Clone, Debug)]
+pub enum PatternSource {
+    /// Image tiled from its top left corner.
+    Image(peniko::Image),
+    /// Content of the named asset within the given tile rectangle.
+    Instance { name: String, tile: kurbo::Rect },
+}
+
 /// Elements of a shape layer.
 #[derive(Clone, Debug)]
 pub enum Shape {
@@ -74,6 +320,8 @@ pub enum Shape {
     Draw(Draw),
     /// Repeater element.
     Repeater(Repeater),
+    /// Path modifier element.
+    Modifier(Modifier),
 }
 
 /// Transform and opacity for a shape group.
@@ -92,7 +340,7 @@ pub struct Layer {
     pub parent: Option<usize>,
     /// Transform for the entire layer.
     pub transform: Transform,
-    /// Opacity for the entire layer.
+    /// Opacity for the entire layer in percent.
     pub opacity: Value<f64>,
     /// Width of the layer.
     pub width: f64,
@@ -110,32 +358,138 @@ pub struct Layer {
     pub masks: Vec<Mask>,
     /// True if the layer is used as a mask.
     pub is_mask: bool,
-    /// Mask blend mode and layer.
-    pub mask_layer: Option<(peniko::BlendMode, usize)>,
+    /// Track matte mode and the index of the matte layer.
+    pub mask_layer: Option<(Matte, usize)>,
     /// Content of the layer.
     pub content: Content,
+    /// Variation of the timing of instanced content.
+    pub time_variation: Option<TimeVariation>,
+}
+
+impl Layer {
+    /// Returns true if the layer is active at the specified frame.
+    pub fn is_active(&self, frame: f64) -> bool {
+        self.frames.contains(&frame)
+    }
+
+    /// Returns the frame at which instanced content should be evaluated
+    /// for the specified frame of the containing layer set.
+    ///
+    /// This applies time remapping if present, and otherwise the start
+    /// frame and stretch factor of the layer, followed by any time
+    /// variation.
+    pub fn instance_frame(&self, frame: f64) -> f64 {
+        let frame = match &self.content {
+            Content::Instance {
+                time_remap: Some(time_remap),
+                ..
+            } => time_remap.evaluate(frame),
+            _ => (frame - self.start_frame) / self.stretch,
+        };
+        match &self.time_variation {
+            Some(variation) => variation.apply(frame),
+            None => frame,
+        }
+    }
+
+    /// Returns the transform of the layer combined with those of its chain
+    /// of parents in the given layer set.
+    pub(crate) fn parented_transform(&self, layer_set: &[Layer], frame: f64) -> Affine {
+        self.parents(layer_set).fold(
+            self.transform.evaluate(frame).into_owned(),
+            |transform, index| layer_set[index].transform.evaluate(frame).into_owned() * transform,
+        )
+    }
+
+    /// Returns the indices of the chain of parents of the layer in the given
+    /// layer set, nearest first.
+    pub(crate) fn parents<'a>(&self, layer_set: &'a [Layer]) -> impl Iterator<Item = usize> + 'a {
+        let mut parent_index = self.parent;
+        core::iter::from_fn(move || {
+            let index = parent_index.filter(|&index| index < layer_set.len())?;
+            parent_index = layer_set[index].parent;
+            Some(index)
+        })
+        // We don't check for cycles at import time, so this heuristic
+        // prevents infinite loops.
+        .take(layer_set.len())
+    }
 }
 
 /// Matte layer mode.
+///
+/// A track matte limits the visible content of a layer to the areas covered
+/// by another layer, the matte layer, which is not drawn itself.
 #[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
 pub enum Matte {
+    /// Content is visible where the matte is opaque.
+    #[default]
+    Alpha,
+    /// Content is visible where the matte is transparent.
+    InvertAlpha,
+    /// Content is visible where the matte is bright.
+    Luma,
+    /// Content is visible where the matte is dark.
+    InvertLuma,
+}
+
+impl Matte {
+    /// Returns true if the matte is derived from the luminance of the matte
+    /// layer rather than its opacity.
+    pub fn is_luma(self) -> bool {
+        matches!(self, Self::Luma | Self::InvertLuma)
+    }
+
+    /// Returns the blend mode that composites content onto the coverage of
+    /// the matte layer.
+    pub fn blend_mode(self) -> peniko::BlendMode {
+        match self {
+            Self::Alpha | Self::Luma => peniko::Compose::SrcIn.into(),
+            Self::InvertAlpha | Self::InvertLuma => peniko::Compose::SrcOut.into(),
+        }
+    }
+}
+
+/// Mode in which a mask is combined with the masks that precede it.
+///
+/// Masks of a layer are combined in order. The coverage starts out empty if
+/// the first mask adds to it, and full otherwise.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum MaskMode {
+    /// Adds the mask to the coverage.
     #[default]
-    Normal,
-    // TODO: Use these
-    // Alpha,
-    // InvertAlpha,
-    // Luma,
-    // InvertLuma,
+    Add,
+    /// Removes the mask from the coverage.
+    Subtract,
+    /// Limits the coverage to the mask.
+    Intersect,
+    /// Inverts the coverage where the mask is present.
+    Difference,
+}
+
+impl MaskMode {
+    /// Returns the blend mode that combines a mask with the coverage of
+    /// the masks that precede it.
+    pub fn blend_mode(self) -> peniko::BlendMode {
+        match self {
+            Self::Add => peniko::Compose::SrcOver.into(),
+            Self::Subtract => peniko::Compose::DestOut.into(),
+            Self::Intersect => peniko::Compose::DestIn.into(),
+            Self::Difference => peniko::Compose::Xor.into(),
+        }
+    }
 }
 
 /// Mask for a layer.
 #[derive(Clone, Debug)]
 pub struct Mask {
-    /// Blend mode for the mask.
-    pub mode: peniko::BlendMode,
+    /// Mode in which the mask is combined with preceding masks.
+    pub mode: MaskMode,
+    /// True if the mask covers the area outside of its geometry.
+    pub inverted: bool,
     /// Geometry that defines the shape of the mask.
     pub geometry: Geometry,
-    /// Opacity of the mask.
+    /// Opacity of the mask in percent.
     pub opacity: Value<f64>,
 }
 
@@ -152,4 +506,139 @@ pub enum Content {
     },
     /// Collection of shapes.
     Shape(Vec<Shape>),
+    /// Slot for content supplied by the host at render time, such as a
+    /// video frame or a snapshot of a native view.
+    ///
+    /// The slot is transformed, masked and composited with the opacity of
+    /// the layer like any other content.
+    External(ExternalId),
+}
+
+/// Host defined identifier of external layer content.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ExternalId(pub u64);
+
+#[cfg(test)]
+impl Layer {
+    /// Returns a layer instancing the named asset.
+    pub(crate) fn instance(name: &str) -> Self {
+        Self {
+            content: Content::Instance {
+                name: name.into(),
+                time_remap: None,
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns a layer that fills the square from the origin to (10, 10) with
+/// a solid color, shared by the rendering tests.
+#[cfg(test)]
+pub(crate) fn square_layer(color: crate::fixed::Color) -> Layer {
+    let shapes = alloc::vec![
+        Shape::Geometry(Geometry::Fixed(
+            kurbo::Rect::new(0.0, 0.0, 10.0, 10.0)
+                .path_elements(0.1)
+                .collect(),
+        )),
+        Shape::Draw(Draw {
+            stroke: None,
+            brush: Brush::Fixed(color.into()),
+            opacity: Value::Fixed(100.0),
+        }),
+    ];
+    Layer {
+        frames: 0.0..10.0,
+        opacity: Value::Fixed(100.0),
+        content: Content::Shape(shapes),
+        ..Default::default()
+    }
+}
+
+/// Returns a composition with a black [square layer](square_layer) that is
+/// active until frame 5.
+#[cfg(test)]
+pub(crate) fn square_composition() -> Composition {
+    Composition {
+        frames: 0.0..10.0,
+        width: 10,
+        height: 10,
+        layers: alloc::vec![Layer {
+            frames: 0.0..5.0,
+            ..square_layer(crate::fixed::Color::BLACK)
+        }],
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Animated, Time};
+
+    fn instance(time_remap: Option<Value<f64>>) -> Layer {
+        Layer {
+            frames: 10.0..20.0,
+            stretch: 2.0,
+            start_frame: 10.0,
+            content: Content::Instance {
+                name: "asset".into(),
+                time_remap,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn composition_is_send_and_sync() {
+        // Evaluation only needs shared references, so compositions can be
+        // shared between threads.
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Composition>();
+    }
+
+    #[test]
+    fn simplification_merges_collinear_segments() {
+        let mut elements = vec![PathEl::MoveTo((0.0, 0.0).into())];
+        elements.extend((1..=10).map(|i| PathEl::LineTo((i as f64, 0.0).into())));
+        let geometry = Geometry::Fixed(elements);
+        let mut path = vec![];
+        geometry.evaluate_simplified(0.0, 0.1, &mut path);
+        assert!(path.len() <= 2, "collinear segments should be merged");
+        assert_eq!(
+            path.last().and_then(|el| el.end_point()),
+            Some((10.0, 0.0).into())
+        );
+    }
+
+    #[test]
+    fn layer_activity_excludes_end_frame() {
+        let layer = instance(None);
+        assert!(!layer.is_active(9.0), "before the frame range");
+        assert!(layer.is_active(10.0), "at the first frame");
+        assert!(!layer.is_active(20.0), "at the end of the frame range");
+    }
+
+    #[test]
+    fn instance_frame_applies_offset_and_stretch() {
+        let layer = instance(None);
+        assert_eq!(layer.instance_frame(10.0), 0.0);
+        assert_eq!(layer.instance_frame(14.0), 2.0);
+    }
+
+    #[test]
+    fn instance_frame_prefers_time_remap() {
+        let remap = Value::Animated(Animated {
+            times: vec![Time::new(10.0), Time::new(20.0)],
+            values: vec![100.0, 0.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        });
+        let layer = instance(Some(remap));
+        assert!(
+            (layer.instance_frame(15.0) - 50.0).abs() < 1e-6,
+            "remapped time should run backwards"
+        );
+    }
 }
@@ -0,0 +1,175 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::{fmt, str::FromStr};
+
+use crate::{Easing, JumpTerm};
+
+/// Error produced when parsing an easing from a CSS string.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ParseEasingError {
+    /// The string is not a supported easing keyword or function.
+    Unknown,
+    /// An easing function has the wrong number of arguments, or an argument
+    /// is malformed or out of range.
+    InvalidArguments,
+}
+
+impl fmt::Display for ParseEasingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown => write!(f, "unsupported easing function"),
+            Self::InvalidArguments => write!(f, "invalid easing function arguments"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseEasingError {}
+
+impl Easing {
+    /// Parses an easing from a CSS `<easing-function>`, such as
+    /// `ease-in-out`, `cubic-bezier(0.4, 0, 0.2, 1)` or `steps(4, end)`.
+    ///
+    /// The `linear()` function with control points is not supported.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParseEasingError::Unknown`] for unsupported keywords and
+    /// functions, and [`ParseEasingError::InvalidArguments`] if the
+    /// arguments of a function are invalid.
+    pub fn from_css(css: &str) -> Result<Self, ParseEasingError> {
+        let css = css.trim();
+        let keyword = |name: &str| css.eq_ignore_ascii_case(name);
+        if keyword("linear") {
+            return Ok(Self::LERP);
+        }
+        if keyword("ease") {
+            return Ok(Self::cubic_bezier(0.25, 0.1, 0.25, 1.0));
+        }
+        if keyword("ease-in") {
+            return Ok(Self::cubic_bezier(0.42, 0.0, 1.0, 1.0));
+        }
+        if keyword("ease-out") {
+            return Ok(Self::cubic_bezier(0.0, 0.0, 0.58, 1.0));
+        }
+        if keyword("ease-in-out") {
+            return Ok(Self::cubic_bezier(0.42, 0.0, 0.58, 1.0));
+        }
+        if keyword("step-start") {
+            return Ok(Self::Steps(1, JumpTerm::JumpStart));
+        }
+        if keyword("step-end") {
+            return Ok(Self::Steps(1, JumpTerm::JumpEnd));
+        }
+        let (name, args) = css
+            .strip_suffix(')')
+            .and_then(|css| css.split_once('('))
+            .ok_or(ParseEasingError::Unknown)?;
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        let name = name.trim_end();
+        if name.eq_ignore_ascii_case("cubic-bezier") {
+            let [x1, y1, x2, y2] = args[..] else {
+                return Err(ParseEasingError::InvalidArguments);
+            };
+            let [x1, y1, x2, y2] = [x1, y1, x2, y2].map(parse_number);
+            let (Some(x1), Some(y1), Some(x2), Some(y2)) = (x1, y1, x2, y2) else {
+                return Err(ParseEasingError::InvalidArguments);
+            };
+            if !(0.0..=1.0).contains(&x1) || !(0.0..=1.0).contains(&x2) {
+                return Err(ParseEasingError::InvalidArguments);
+            }
+            Ok(Self::cubic_bezier(x1, y1, x2, y2))
+        } else if name.eq_ignore_ascii_case("steps") {
+            let (steps, position) = match args[..] {
+                [steps] => (steps, None),
+                [steps, position] => (steps, Some(position)),
+                _ => return Err(ParseEasingError::InvalidArguments),
+            };
+            let steps: u32 = steps
+                .parse()
+                .map_err(|_| ParseEasingError::InvalidArguments)?;
+            let jump = match position {
+                None => JumpTerm::JumpEnd,
+                Some(position) => parse_jump_term(position)?,
+            };
+            let min_steps = if jump == JumpTerm::JumpNone { 2 } else { 1 };
+            if steps < min_steps {
+                return Err(ParseEasingError::InvalidArguments);
+            }
+            Ok(Self::Steps(steps, jump))
+        } else {
+            Err(ParseEasingError::Unknown)
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = ParseEasingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_css(s)
+    }
+}
+
+/// Parses a finite CSS number.
+fn parse_number(number: &str) -> Option<f64> {
+    number
+        .parse()
+        .ok()
+        .filter(|number: &f64| number.is_finite())
+}
+
+/// Parses a CSS `<step-position>` keyword.
+fn parse_jump_term(position: &str) -> Result<JumpTerm, ParseEasingError> {
+    let keyword = |name: &str| position.eq_ignore_ascii_case(name);
+    if keyword("jump-start") || keyword("start") {
+        Ok(JumpTerm::JumpStart)
+    } else if keyword("jump-end") || keyword("end") {
+        Ok(JumpTerm::JumpEnd)
+    } else if keyword("jump-none") {
+        Ok(JumpTerm::JumpNone)
+    } else if keyword("jump-both") {
+        Ok(JumpTerm::JumpBoth)
+    } else {
+        Err(ParseEasingError::InvalidArguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_css_easing() {
+        let material = Easing::from_css("cubic-bezier(0.4, 0, 0.2, 1)").unwrap();
+        let expected = Easing::cubic_bezier(0.4, 0.0, 0.2, 1.0);
+        for t in [0.1, 0.5, 0.9] {
+            assert_eq!(material.evaluate(t), expected.evaluate(t));
+        }
+        let ease = Easing::from_css(" Ease-In-Out ").unwrap();
+        assert!(
+            (ease.evaluate(0.5) - 0.5).abs() < 1e-12,
+            "ease-in-out should be symmetric"
+        );
+        let steps: Easing = "steps(4, end)".parse().unwrap();
+        assert_eq!(steps.evaluate(0.3), 0.25);
+        assert!(matches!(
+            Easing::from_css("step-start"),
+            Ok(Easing::Steps(1, JumpTerm::JumpStart))
+        ));
+        assert_eq!(
+            Easing::from_css("cubic-bezier(1.5, 0, 0, 1)").unwrap_err(),
+            ParseEasingError::InvalidArguments
+        );
+        assert_eq!(
+            Easing::from_css("steps(1, jump-none)").unwrap_err(),
+            ParseEasingError::InvalidArguments
+        );
+        assert_eq!(
+            Easing::from_css("bounce").unwrap_err(),
+            ParseEasingError::Unknown
+        );
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+
+use kurbo::{Affine, BezPath, Shape};
+use peniko::{BlendMode, Fill};
+
+use crate::{fixed, Composition, RenderBackend, Renderer};
+
+/// Tolerance used when converting shapes to paths, matching the tolerance
+/// used to evaluate geometries.
+const PATH_TOLERANCE: f64 = 0.1;
+
+/// Drawing operation of a [`DisplayList`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisplayCommand {
+    /// Starts a layer clipped to `clip`, which is composited with `blend`
+    /// and `alpha` when popped.
+    PushLayer {
+        blend: BlendMode,
+        alpha: f32,
+        transform: Affine,
+        clip: BezPath,
+    },
+    /// Starts a layer clipped to `clip` whose content is converted to
+    /// coverage by its luminance, and composited with `alpha`, when popped.
+    ///
+    /// See [`RenderBackend::push_luminance_layer`].
+    PushLuminanceLayer {
+        alpha: f32,
+        transform: Affine,
+        clip: BezPath,
+    },
+    /// Ends the most recently pushed layer.
+    PopLayer,
+    /// Fills a path with a brush.
+    Fill {
+        style: Fill,
+        transform: Affine,
+        brush: fixed::Brush,
+        brush_transform: Option<Affine>,
+        path: BezPath,
+    },
+    /// Strokes a path with a brush.
+    Stroke {
+        style: fixed::Stroke,
+        transform: Affine,
+        brush: fixed::Brush,
+        brush_transform: Option<Affine>,
+        path: BezPath,
+    },
+}
+
+impl DisplayCommand {
+    /// Returns the transform of the command, if it has one.
+    pub fn transform(&self) -> Option<Affine> {
+        match self {
+            Self::PushLayer { transform, .. }
+            | Self::PushLuminanceLayer { transform, .. }
+            | Self::Fill { transform, .. }
+            | Self::Stroke { transform, .. } => Some(*transform),
+            Self::PopLayer => None,
+        }
+    }
+
+    fn transform_mut(&mut self) -> Option<&mut Affine> {
+        match self {
+            Self::PushLayer { transform, .. }
+            | Self::PushLuminanceLayer { transform, .. }
+            | Self::Fill { transform, .. }
+            | Self::Stroke { transform, .. } => Some(transform),
+            Self::PopLayer => None,
+        }
+    }
+}
+
+impl PartialEq for DisplayCommand {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::PushLayer {
+                    blend,
+                    alpha,
+                    transform,
+                    clip,
+                },
+                Self::PushLayer {
+                    blend: other_blend,
+                    alpha: other_alpha,
+                    transform: other_transform,
+                    clip: other_clip,
+                },
+            ) => {
+                blend == other_blend
+                    && alpha == other_alpha
+                    && transform == other_transform
+                    && clip == other_clip
+            }
+            (
+                Self::PushLuminanceLayer {
+                    alpha,
+                    transform,
+                    clip,
+                },
+                Self::PushLuminanceLayer {
+                    alpha: other_alpha,
+                    transform: other_transform,
+                    clip: other_clip,
+                },
+            ) => alpha == other_alpha && transform == other_transform && clip == other_clip,
+            (Self::PopLayer, Self::PopLayer) => true,
+            (
+                Self::Fill {
+                    style,
+                    transform,
+                    brush,
+                    brush_transform,
+                    path,
+                },
+                Self::Fill {
+                    style: other_style,
+                    transform: other_transform,
+                    brush: other_brush,
+                    brush_transform: other_brush_transform,
+                    path: other_path,
+                },
+            ) => {
+                style == other_style
+                    && transform == other_transform
+                    && brush == other_brush
+                    && brush_transform == other_brush_transform
+                    && path == other_path
+            }
+            (
+                Self::Stroke {
+                    style,
+                    transform,
+                    brush,
+                    brush_transform,
+                    path,
+                },
+                Self::Stroke {
+                    style: other_style,
+                    transform: other_transform,
+                    brush: other_brush,
+                    brush_transform: other_brush_transform,
+                    path: other_path,
+                },
+            ) => {
+                strokes_eq(style, other_style)
+                    && transform == other_transform
+                    && brush == other_brush
+                    && brush_transform == other_brush_transform
+                    && path == other_path
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compares stroke styles, which don't implement `PartialEq`.
+fn strokes_eq(a: &fixed::Stroke, b: &fixed::Stroke) -> bool {
+    a.width == b.width
+        && a.join == b.join
+        && a.miter_limit == b.miter_limit
+        && a.start_cap == b.start_cap
+        && a.end_cap == b.end_cap
+        && a.dash_pattern == b.dash_pattern
+        && a.dash_offset == b.dash_offset
+}
+
+/// Sequence of resolved drawing operations of a composition at a single
+/// frame.
+///
+/// Unlike a vello scene, a display list can be inspected: lists produced at
+/// different frames can be compared, converted to another format, or drawn
+/// by a renderer that isn't written in Rust. A display list is a
+/// [`RenderBackend`], so it can be produced by any [`Renderer`], and it is
+/// the default backend of the renderer.
+///
+/// With the `serde` feature, display lists can be serialized, such as to
+/// hand them to a renderer in another process.
+#[derive(Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisplayList {
+    /// Operations in the order in which they are drawn.
+    pub commands: Vec<DisplayCommand>,
+}
+
+impl DisplayList {
+    /// Creates a new empty display list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of the first command that differs between the two
+    /// lists, or `None` if they are equal.
+    pub fn first_difference(&self, other: &Self) -> Option<usize> {
+        let common = self.commands.len().min(other.commands.len());
+        (0..common)
+            .find(|&ix| self.commands[ix] != other.commands[ix])
+            .or_else(|| (self.commands.len() != other.commands.len()).then_some(common))
+    }
+}
+
+impl RenderBackend for DisplayList {
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        self.commands.push(DisplayCommand::PushLayer {
+            blend: blend.into(),
+            alpha,
+            transform,
+            clip: clip.to_path(PATH_TOLERANCE),
+        });
+    }
+
+    fn push_luminance_layer(&mut self, alpha: f32, transform: Affine, clip: &impl Shape) -> bool {
+        self.commands.push(DisplayCommand::PushLuminanceLayer {
+            alpha,
+            transform,
+            clip: clip.to_path(PATH_TOLERANCE),
+        });
+        true
+    }
+
+    fn pop_layer(&mut self) {
+        self.commands.push(DisplayCommand::PopLayer);
+    }
+
+    fn fill(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: &fixed::Brush,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.commands.push(DisplayCommand::Fill {
+            style,
+            transform,
+            brush: brush.clone(),
+            brush_transform,
+            path: shape.to_path(PATH_TOLERANCE),
+        });
+    }
+
+    fn stroke(
+        &mut self,
+        style: &fixed::Stroke,
+        transform: Affine,
+        brush: &fixed::Brush,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.commands.push(DisplayCommand::Stroke {
+            style: style.clone(),
+            transform,
+            brush: brush.clone(),
+            brush_transform,
+            path: shape.to_path(PATH_TOLERANCE),
+        });
+    }
+
+    fn append(&mut self, other: &Self, transform: Option<Affine>) {
+        let start = self.commands.len();
+        self.commands.extend_from_slice(&other.commands);
+        if let Some(append_transform) = transform {
+            for command in &mut self.commands[start..] {
+                if let Some(command_transform) = command.transform_mut() {
+                    *command_transform = append_transform * *command_transform;
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.commands.clear();
+    }
+}
+
+impl Composition {
+    /// Evaluates the composition at the given frame into a display list.
+    ///
+    /// This renders with a new [`Renderer`] and the default settings; use a
+    /// renderer with a [`DisplayList`] backend directly to configure it or
+    /// to reuse its caches across frames.
+    pub fn display_list(&self, frame: f64) -> DisplayList {
+        Renderer::<DisplayList>::new().render(self, frame, Affine::IDENTITY, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::square_composition as square;
+
+    #[test]
+    fn display_lists_resolve_and_compare_frames() {
+        let composition = square();
+        let first = composition.display_list(0.0);
+        assert!(matches!(
+            first.commands.as_slice(),
+            [
+                DisplayCommand::PushLayer { .. },
+                DisplayCommand::Fill { .. },
+                DisplayCommand::PopLayer
+            ]
+        ));
+        assert_eq!(first.first_difference(&composition.display_list(1.0)), None);
+        // The layer is inactive at frame 5.
+        assert_eq!(
+            first.first_difference(&composition.display_list(5.0)),
+            Some(1)
+        );
+
+        let mut moved = DisplayList::new();
+        moved.append(&first, Some(Affine::translate((5.0, 0.0))));
+        assert_eq!(
+            moved.commands[1].transform(),
+            Some(Affine::translate((5.0, 0.0)))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn display_lists_round_trip_through_serde() {
+        let list = square().display_list(0.0);
+        let json = serde_json::to_string(&list).unwrap();
+        let parsed: DisplayList = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, list);
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{Animated, Easing, Time, Tween};
+
+/// Number of edits made in place through the editing methods of the crate.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of edits made in place so far.
+///
+/// Renderers compare this between frames to discard state cached for
+/// values that were edited without changing the identity of their
+/// composition.
+pub(crate) fn generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// Records an edit made in place.
+pub(crate) fn bump_generation() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Error produced when editing the keyframes of an animation.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeyframeError {
+    /// The frame of a keyframe is infinite or NaN.
+    NonFiniteFrame,
+    /// The number of times doesn't match the number of values.
+    LengthMismatch,
+}
+
+impl fmt::Display for KeyframeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFiniteFrame => write!(f, "keyframe frame is not finite"),
+            Self::LengthMismatch => write!(f, "keyframe times and values differ in length"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeyframeError {}
+
+impl<T: Tween> Animated<T> {
+    /// Sets the value at the specified frame and returns the index of the
+    /// keyframe.
+    ///
+    /// When a keyframe already exists at the frame, its value is replaced
+    /// and its easing kept. Otherwise a new keyframe with linear easing is
+    /// inserted, keeping the keyframes sorted by frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KeyframeError::NonFiniteFrame`] if the frame is infinite
+    /// or NaN, leaving the animation unchanged.
+    pub fn insert_keyframe(&mut self, frame: f64, value: T) -> Result<usize, KeyframeError> {
+        if !frame.is_finite() {
+            return Err(KeyframeError::NonFiniteFrame);
+        }
+        let ix = self.times.partition_point(|time| time.frame < frame);
+        if self.times.get(ix).is_some_and(|time| time.frame == frame) {
+            if let Some(existing) = self.values.get_mut(ix) {
+                *existing = value;
+                bump_generation();
+                return Ok(ix);
+            }
+        }
+        bump_generation();
+        self.times.insert(ix, Time::new(frame));
+        self.values.insert(ix, value);
+        Ok(ix)
+    }
+
+    /// Removes the keyframe at `index` and returns its time and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove_keyframe(&mut self, index: usize) -> (Time, T) {
+        bump_generation();
+        (self.times.remove(index), self.values.remove(index))
+    }
+
+    /// Moves the keyframe at `index` to `frame` and returns its new index.
+    ///
+    /// The keyframe keeps its value and easing, and is reordered among the
+    /// other keyframes as needed. When other keyframes share the frame, it
+    /// is placed after them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KeyframeError::NonFiniteFrame`] if the frame is infinite
+    /// or NaN, leaving the animation unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn move_keyframe(&mut self, index: usize, frame: f64) -> Result<usize, KeyframeError> {
+        if !frame.is_finite() {
+            return Err(KeyframeError::NonFiniteFrame);
+        }
+        let (mut time, value) = self.remove_keyframe(index);
+        time.frame = frame;
+        let ix = self.times.partition_point(|other| other.frame <= frame);
+        self.times.insert(ix, time);
+        self.values.insert(ix, value);
+        Ok(ix)
+    }
+
+    /// Sets the easing for the segment starting at the keyframe at `index`.
+    ///
+    /// Cubic bezier easing is stored as the keyframe tangents, as Lottie
+    /// does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set_easing(&mut self, index: usize, easing: Easing) {
+        self.times[index].set_easing(easing);
+        bump_generation();
+    }
+
+    /// Sorts the keyframes by frame, keeping keyframes that share a frame
+    /// in their current order.
+    ///
+    /// Keyframes are expected to be sorted for evaluation. This repairs
+    /// animations that were built or edited by other means.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KeyframeError::LengthMismatch`] if there isn't a value for
+    /// each time, and [`KeyframeError::NonFiniteFrame`] if any frame is
+    /// infinite or NaN. The animation is left unchanged in either case.
+    pub fn sort_keyframes(&mut self) -> Result<(), KeyframeError> {
+        if self.times.len() != self.values.len() {
+            return Err(KeyframeError::LengthMismatch);
+        }
+        if self.times.iter().any(|time| !time.frame.is_finite()) {
+            return Err(KeyframeError::NonFiniteFrame);
+        }
+        if self.is_sorted() {
+            return Ok(());
+        }
+        let mut keyframes: Vec<_> = self.times.drain(..).zip(self.values.drain(..)).collect();
+        keyframes.sort_by(|(a, _), (b, _)| a.frame.total_cmp(&b.frame));
+        bump_generation();
+        (self.times, self.values) = keyframes.into_iter().unzip();
+        Ok(())
+    }
+
+    /// Returns true if the keyframes are sorted by frame and there is a
+    /// value for each time.
+    pub fn is_sorted(&self) -> bool {
+        self.times.len() == self.values.len()
+            && self.times.windows(2).all(|w| w[0].frame <= w[1].frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn editing_keeps_keyframes_sorted() {
+        let mut animated = Animated {
+            times: vec![],
+            values: vec![],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        assert_eq!(animated.insert_keyframe(10.0, 100.0), Ok(0));
+        assert_eq!(animated.insert_keyframe(0.0, 0.0), Ok(0));
+        assert_eq!(animated.insert_keyframe(20.0, 50.0), Ok(2));
+        assert_eq!(animated.evaluate(5.0), 50.0);
+
+        // Replacing a value keeps the keyframe count.
+        assert_eq!(animated.insert_keyframe(10.0, 80.0), Ok(1));
+        assert_eq!(animated.times.len(), 3);
+
+        animated.set_easing(0, Easing::Hold);
+        assert_eq!(animated.evaluate(5.0), 0.0);
+
+        assert_eq!(animated.move_keyframe(0, 30.0), Ok(2));
+        assert!(animated.is_sorted(), "moving should keep keyframes sorted");
+        assert_eq!(animated.evaluate(30.0), 0.0);
+
+        let (removed, value) = animated.remove_keyframe(2);
+        assert_eq!((removed.frame, value), (30.0, 0.0));
+
+        animated.times[0].frame = 40.0;
+        assert!(!animated.is_sorted(), "keyframes should be out of order");
+        assert_eq!(animated.sort_keyframes(), Ok(()));
+        let frames: Vec<_> = animated.times.iter().map(|time| time.frame).collect();
+        assert_eq!(frames, [20.0, 40.0]);
+        assert_eq!(animated.values, [50.0, 80.0]);
+    }
+
+    #[test]
+    fn editing_rejects_invalid_keyframes() {
+        let mut animated = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0)],
+            values: vec![0.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        for frame in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(
+                animated.insert_keyframe(frame, 50.0),
+                Err(KeyframeError::NonFiniteFrame)
+            );
+            assert_eq!(
+                animated.move_keyframe(0, frame),
+                Err(KeyframeError::NonFiniteFrame)
+            );
+        }
+        assert_eq!(animated.times.len(), 2, "rejected edits change nothing");
+        assert_eq!(animated.times[0].frame, 0.0);
+
+        animated.times.push(Time::new(5.0));
+        assert_eq!(
+            animated.sort_keyframes(),
+            Err(KeyframeError::LengthMismatch)
+        );
+        animated.values.push(50.0);
+        animated.times[0].frame = f64::NAN;
+        assert_eq!(
+            animated.sort_keyframes(),
+            Err(KeyframeError::NonFiniteFrame)
+        );
+        animated.times[0].frame = 0.0;
+        assert_eq!(animated.sort_keyframes(), Ok(()));
+        assert_eq!(animated.values, [0.0, 50.0, 100.0]);
+    }
+}
@@ -0,0 +1,423 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use kurbo::{Affine, PathEl};
+
+use crate::{fixed, Composition, Content, Layer, Limits, Modifier, Shape};
+
+/// Identifier of an [`Entity`] that is stable across frames.
+///
+/// The identifier is derived from the position of the geometry and draw
+/// that produce the entity in the structure of the composition, so it stays
+/// the same from frame to frame for as long as the composition isn't
+/// modified.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct EntityId(pub u64);
+
+/// Single filled or stroked shape of an evaluated composition.
+#[derive(Clone, Debug)]
+pub struct Entity {
+    /// Identifier that is stable across frames.
+    pub id: EntityId,
+    /// Transform from the geometry to the composition.
+    pub transform: Affine,
+    /// Range of path elements in [`EntityList::elements`].
+    pub geometry: Range<usize>,
+    /// Brush used to paint the geometry.
+    pub brush: fixed::Brush,
+    /// Stroke style, or `None` if the geometry is filled.
+    pub stroke: Option<fixed::Stroke>,
+    /// Variation of the stroke width along the geometry, or `None` if the
+    /// width is constant.
+    pub taper: Option<fixed::Taper>,
+    /// Opacity to apply to the brush, from 0 to 1.
+    pub alpha: f64,
+}
+
+/// Flat list of the shapes of a composition evaluated at a single frame.
+#[derive(Clone, Default, Debug)]
+pub struct EntityList {
+    /// Entities in paint order, from bottom to top.
+    pub entities: Vec<Entity>,
+    /// Path elements shared by the entities. Entities painting the same
+    /// geometry refer to the same range.
+    pub elements: Vec<PathEl>,
+}
+
+impl Composition {
+    /// Evaluates the composition at the given frame into a flat list of
+    /// entities, for consumption by an entity component system or a custom
+    /// renderer.
+    ///
+    /// Masks, mattes and blend modes are not represented in the result.
+    pub fn evaluate_entities(&self, frame: f64) -> EntityList {
+        self.evaluate_entities_with_limits(frame, &Limits::UNBOUNDED)
+    }
+
+    /// Same as [`Composition::evaluate_entities`], but skips any structure
+    /// exceeding the given limits, as with [`Renderer::with_limits`].
+    ///
+    /// [`Renderer::with_limits`]: crate::Renderer::with_limits
+    pub fn evaluate_entities_with_limits(&self, frame: f64, limits: &Limits) -> EntityList {
+        let mut list = EntityList::default();
+        let mut evaluator = Evaluator {
+            composition: self,
+            limits,
+            list: &mut list,
+            instances: Vec::new(),
+            geometries: Vec::new(),
+            draws: Vec::new(),
+        };
+        evaluator.layers(&self.layers, Affine::IDENTITY, 1.0, frame, 0);
+        list
+    }
+}
+
+/// Combines a parent identifier with the index of a child.
+fn mix(id: u64, index: u64) -> u64 {
+    (id.rotate_left(5) ^ index).wrapping_mul(0x517c_c1b7_2722_0a95)
+}
+
+struct GeometryData {
+    id: u64,
+    transform: Affine,
+    elements: Range<usize>,
+}
+
+struct DrawData {
+    id: u64,
+    brush: fixed::Brush,
+    stroke: Option<fixed::Stroke>,
+    taper: Option<fixed::Taper>,
+    alpha: f64,
+    /// Range into `Evaluator::geometries`.
+    geometries: Range<usize>,
+}
+
+struct Evaluator<'a> {
+    composition: &'a Composition,
+    limits: &'a Limits,
+    list: &'a mut EntityList,
+    /// Addresses of the assets currently being instanced, used to break
+    /// cycles in compositions that haven't been validated.
+    instances: Vec<usize>,
+    geometries: Vec<GeometryData>,
+    draws: Vec<DrawData>,
+}
+
+impl Evaluator<'_> {
+    fn layers(&mut self, layers: &[Layer], transform: Affine, alpha: f64, frame: f64, id: u64) {
+        for (index, layer) in layers.iter().enumerate().rev() {
+            if layer.is_mask || !layer.is_active(frame) {
+                continue;
+            }
+            let id = mix(id, index as u64);
+            let transform = transform * layer.parented_transform(layers, frame);
+            let alpha = alpha * layer.opacity_at(frame).fraction();
+            match &layer.content {
+                Content::None | Content::External(_) => {}
+                Content::Instance { name, .. } => {
+                    let Some(asset) = self.composition.assets.get(name) else {
+                        continue;
+                    };
+                    let instance = asset.as_ptr() as usize;
+                    // See Composition::validate
+                    if self.instances.contains(&instance)
+                        || self.instances.len() >= self.limits.max_instance_depth
+                    {
+                        continue;
+                    }
+                    self.instances.push(instance);
+                    let frame = layer.instance_frame(frame);
+                    self.layers(asset, transform, alpha, frame, id);
+                    self.instances.pop();
+                }
+                Content::Shape(shapes) => {
+                    self.shapes(shapes, transform, alpha, frame, id, 1);
+                    self.flush();
+                }
+            }
+        }
+    }
+
+    fn shapes(
+        &mut self,
+        shapes: &[Shape],
+        transform: Affine,
+        alpha: f64,
+        frame: f64,
+        id: u64,
+        depth: usize,
+    ) {
+        if depth > self.limits.max_shape_depth {
+            return;
+        }
+        // Draws apply to all geometries that precede them in the same group,
+        // including those of nested groups.
+        let geometry_start = self.geometries.len();
+        let draw_start = self.draws.len();
+        for (index, shape) in shapes.iter().enumerate() {
+            let id = mix(id, index as u64);
+            match shape {
+                Shape::Group(group, properties) => {
+                    let (group_transform, group_alpha) = match properties {
+                        Some(properties) => (
+                            properties.transform.evaluate(frame).into_owned(),
+                            properties.opacity_at(frame).fraction(),
+                        ),
+                        None => (Affine::IDENTITY, 1.0),
+                    };
+                    self.shapes(
+                        group,
+                        transform * group_transform,
+                        alpha * group_alpha,
+                        frame,
+                        id,
+                        depth + 1,
+                    );
+                }
+                Shape::Geometry(geometry) => {
+                    let start = self.list.elements.len();
+                    geometry.evaluate(frame, &mut self.list.elements);
+                    self.geometries.push(GeometryData {
+                        id,
+                        transform,
+                        elements: start..self.list.elements.len(),
+                    });
+                }
+                Shape::Draw(draw) => {
+                    self.draws.push(DrawData {
+                        id,
+                        brush: draw.brush.evaluate(1.0, frame).into_owned(),
+                        stroke: draw
+                            .stroke
+                            .as_ref()
+                            .map(|stroke| stroke.evaluate(frame).into_owned()),
+                        taper: draw.stroke.as_ref().and_then(|stroke| stroke.taper(frame)),
+                        alpha: alpha * draw.opacity_at(frame).fraction(),
+                        geometries: geometry_start..self.geometries.len(),
+                    });
+                }
+                Shape::Repeater(repeater) => {
+                    let mut repeater = repeater.evaluate(frame).into_owned();
+                    repeater.copies = repeater.copies.min(self.limits.max_repeater_copies);
+                    self.repeat(&repeater, geometry_start, draw_start);
+                }
+                Shape::Modifier(modifier) => {
+                    self.modify(modifier, frame, transform, geometry_start);
+                }
+            }
+        }
+    }
+
+    /// Replaces the geometries and draws following the given starts with
+    /// copies produced by a repeater.
+    fn repeat(&mut self, repeater: &fixed::Repeater, geometry_start: usize, draw_start: usize) {
+        let geometries: Vec<GeometryData> = self.geometries.drain(geometry_start..).collect();
+        let draws: Vec<DrawData> = self.draws.drain(draw_start..).collect();
+        let copies = repeater.copies;
+        let start_alpha = repeater.start_opacity / 100.0;
+        let end_alpha = repeater.end_opacity / 100.0;
+        let delta_alpha = if copies > 1 {
+            // See note in Skottie: AE does not cover the full opacity range
+            (end_alpha - start_alpha) / copies as f64
+        } else {
+            0.0
+        };
+        for copy in 0..copies {
+            let copy_start = self.geometries.len();
+            let copy_transform = repeater.transform(copy);
+            for geometry in &geometries {
+                self.geometries.push(GeometryData {
+                    id: mix(geometry.id, copy as u64),
+                    transform: geometry.transform * copy_transform,
+                    elements: geometry.elements.clone(),
+                });
+            }
+            let copy_alpha = start_alpha + delta_alpha * copy as f64;
+            if copy_alpha <= 0.0 {
+                continue;
+            }
+            for draw in &draws {
+                let offset = copy_start - geometry_start;
+                self.draws.push(DrawData {
+                    id: mix(draw.id, copy as u64),
+                    brush: draw.brush.clone(),
+                    stroke: draw.stroke.clone(),
+                    taper: draw.taper.clone(),
+                    alpha: draw.alpha * copy_alpha,
+                    geometries: draw.geometries.start + offset..draw.geometries.end + offset,
+                });
+            }
+        }
+    }
+
+    /// Replaces the geometries following the given start with their
+    /// modified paths, in the coordinate space given by `transform`.
+    fn modify(
+        &mut self,
+        modifier: &Modifier,
+        frame: f64,
+        transform: Affine,
+        geometry_start: usize,
+    ) {
+        if transform.determinant() == 0.0 {
+            return;
+        }
+        let inverse = transform.inverse();
+        let mut path = Vec::new();
+        for geometry in &mut self.geometries[geometry_start..] {
+            let relative = inverse * geometry.transform;
+            path.clear();
+            path.extend(
+                self.list.elements[geometry.elements.clone()]
+                    .iter()
+                    .map(|element| relative * *element),
+            );
+            let start = self.list.elements.len();
+            modifier.apply(frame, &path, &mut self.list.elements);
+            geometry.elements = start..self.list.elements.len();
+            geometry.transform = transform;
+        }
+    }
+
+    /// Emits entities for the pending draws and clears them.
+    fn flush(&mut self) {
+        // Earlier draws are painted on top of later ones.
+        for draw in self.draws.drain(..).rev() {
+            for geometry in &self.geometries[draw.geometries.clone()] {
+                self.list.entities.push(Entity {
+                    id: EntityId(mix(geometry.id, draw.id)),
+                    transform: geometry.transform,
+                    geometry: geometry.elements.clone(),
+                    brush: draw.brush.clone(),
+                    stroke: draw.stroke.clone(),
+                    taper: draw.taper.clone(),
+                    alpha: draw.alpha,
+                });
+            }
+        }
+        self.geometries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brush, Draw, Geometry, Repeater, Value};
+    use alloc::vec;
+    use kurbo::{Point, Shape as _, Vec2};
+
+    fn repeated_square(copies: usize) -> Layer {
+        let rect = kurbo::Rect::new(0.0, 0.0, 10.0, 10.0);
+        let shapes = vec![
+            Shape::Geometry(Geometry::Fixed(rect.path_elements(0.1).collect())),
+            Shape::Draw(Draw {
+                stroke: None,
+                brush: Brush::Fixed(fixed::Color::WHITE.into()),
+                opacity: Value::Fixed(50.0),
+            }),
+            Shape::Repeater(Repeater::Fixed(fixed::Repeater {
+                copies,
+                offset: 0.0,
+                anchor_point: Point::ZERO,
+                position: Point::new(20.0, 0.0),
+                rotation: 0.0,
+                scale: Vec2::new(100.0, 100.0),
+                start_opacity: 100.0,
+                end_opacity: 100.0,
+            })),
+        ];
+        Layer {
+            frames: 0.0..10.0,
+            opacity: Value::Fixed(100.0),
+            content: Content::Shape(shapes),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn entities_are_repeated_with_stable_ids() {
+        let composition = Composition {
+            frames: 0.0..10.0,
+            layers: vec![repeated_square(3)],
+            ..Default::default()
+        };
+        let list = composition.evaluate_entities(0.0);
+        assert_eq!(list.entities.len(), 3);
+        let offsets: Vec<f64> = list
+            .entities
+            .iter()
+            .map(|entity| entity.transform.translation().x)
+            .collect();
+        // The first copy is painted on top.
+        assert_eq!(offsets, [40.0, 20.0, 0.0]);
+        assert!(
+            list.entities.iter().all(|entity| entity.alpha == 0.5),
+            "draw opacity should be applied"
+        );
+        let ids: Vec<EntityId> = list.entities.iter().map(|entity| entity.id).collect();
+        assert!(
+            ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2],
+            "entities should have distinct ids"
+        );
+        let next: Vec<EntityId> = composition
+            .evaluate_entities(5.0)
+            .entities
+            .iter()
+            .map(|entity| entity.id)
+            .collect();
+        assert_eq!(ids, next);
+    }
+
+    #[test]
+    fn evaluation_is_bounded() {
+        let instance = Layer {
+            frames: 0.0..10.0,
+            stretch: 1.0,
+            opacity: Value::Fixed(100.0),
+            ..Layer::instance("a")
+        };
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            layers: vec![instance.clone()],
+            ..Default::default()
+        };
+        composition.assets.insert(
+            "a".into(),
+            vec![instance.clone(), instance, repeated_square(3)],
+        );
+        let list = composition.evaluate_entities(0.0);
+        assert_eq!(list.entities.len(), 3, "cycles should not be followed");
+        let limited = |limits: Limits| {
+            composition
+                .evaluate_entities_with_limits(0.0, &limits)
+                .entities
+                .len()
+        };
+        assert_eq!(
+            limited(Limits {
+                max_repeater_copies: 2,
+                ..Limits::UNBOUNDED
+            }),
+            2
+        );
+        assert_eq!(
+            limited(Limits {
+                max_instance_depth: 0,
+                ..Limits::UNBOUNDED
+            }),
+            0
+        );
+        assert_eq!(
+            limited(Limits {
+                max_shape_depth: 0,
+                ..Limits::UNBOUNDED
+            }),
+            0
+        );
+    }
+}
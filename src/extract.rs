@@ -0,0 +1,253 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+
+use hashbrown::{HashMap, HashSet};
+
+use crate::{validate::instance_names, Composition, CompositionId, Content, Layer};
+
+impl Composition {
+    /// Returns a standalone composition containing only the layer at the
+    /// given index.
+    ///
+    /// Transform parents and the matte layer of the extracted layer are
+    /// carried along so that it is positioned and masked as before, but the
+    /// content of transform parents is dropped. Only the assets instanced
+    /// (directly or transitively) by the result are retained and the frame
+    /// range is limited to the frames in which the layer is active.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    pub fn extract_layer(&self, index: usize) -> Option<Self> {
+        let layer = self.layers.get(index)?;
+        let layers = select_layers(&self.layers, &[index]);
+        Some(Self {
+            frames: intersect(&self.frames, &layer.frames),
+            assets: self.referenced_assets(&layers),
+            layers,
+            ..self.empty_copy()
+        })
+    }
+
+    /// Returns a standalone composition whose layers are those of the named
+    /// asset.
+    ///
+    /// Only the assets instanced (directly or transitively) by the asset are
+    /// retained and the frame range covers the frames in which any of its
+    /// layers are active.
+    ///
+    /// Returns `None` if there is no asset with the given name.
+    pub fn extract_asset(&self, name: &str) -> Option<Self> {
+        let layers = self.assets.get(name)?.clone();
+        let frames = layers
+            .iter()
+            .map(|layer| layer.frames.clone())
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .unwrap_or(0.0..0.0);
+        Some(Self {
+            frames,
+            assets: self.referenced_assets(&layers),
+            layers,
+            ..self.empty_copy()
+        })
+    }
+
+    /// Returns the assets instanced by the given layers, including those
+    /// instanced by other assets.
+    pub(crate) fn referenced_assets(&self, layers: &[Layer]) -> HashMap<String, Vec<Layer>> {
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut pending: Vec<&Layer> = layers.iter().collect();
+        while let Some(layer) = pending.pop() {
+            for name in instance_names(layer) {
+                if let Some((name, asset)) = self.assets.get_key_value(name) {
+                    if visited.insert(name) {
+                        pending.extend(asset);
+                    }
+                }
+            }
+        }
+        visited
+            .into_iter()
+            .map(|name| (name.into(), self.assets[name].clone()))
+            .collect()
+    }
+
+    /// Returns a composition with the same metadata and no content.
+    pub(crate) fn empty_copy(&self) -> Self {
+        Self {
+            frames: self.frames.clone(),
+            frame_rate: self.frame_rate,
+            width: self.width,
+            height: self.height,
+            assets: HashMap::new(),
+            layers: Vec::new(),
+            id: CompositionId::new(),
+        }
+    }
+}
+
+/// Returns copies of the layers at the given indices along with the layers
+/// they depend on, in their original stacking order.
+///
+/// Matte layers are retained as is, while transform parents are retained
+/// with their content dropped. Parent and matte indices are remapped to the
+/// new layer list.
+pub(crate) fn select_layers(layers: &[Layer], indices: &[usize]) -> Vec<Layer> {
+    // Indices of the layers to keep, and whether their content is needed.
+    let mut keep: HashMap<usize, bool> = HashMap::new();
+    for &index in indices {
+        let Some(layer) = layers.get(index) else {
+            continue;
+        };
+        keep.insert(index, true);
+        if let Some((_, matte)) = layer.mask_layer {
+            if matte < layers.len() {
+                keep.insert(matte, true);
+            }
+        }
+    }
+    let drawn: Vec<usize> = keep.keys().copied().collect();
+    for ix in drawn {
+        let mut parent = layers[ix].parent;
+        while let Some(parent_ix) = parent {
+            if parent_ix >= layers.len() || keep.contains_key(&parent_ix) {
+                break;
+            }
+            keep.insert(parent_ix, false);
+            parent = layers[parent_ix].parent;
+        }
+    }
+    // Preserve the original stacking order.
+    let mut order: Vec<usize> = keep.keys().copied().collect();
+    order.sort_unstable();
+    let remap: HashMap<usize, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(new, old)| (*old, new))
+        .collect();
+    order
+        .iter()
+        .map(|ix| {
+            let mut copy = layers[*ix].clone();
+            copy.parent = copy.parent.and_then(|parent| remap.get(&parent).copied());
+            copy.mask_layer = copy
+                .mask_layer
+                .and_then(|(mode, matte)| Some((mode, *remap.get(&matte)?)));
+            if !keep[ix] {
+                copy.content = Content::None;
+            }
+            copy
+        })
+        .collect()
+}
+
+pub(crate) fn intersect(a: &Range<f64>, b: &Range<f64>) -> Range<f64> {
+    let start = a.start.max(b.start);
+    start..a.end.min(b.end).max(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brush, Draw, InstanceBrush, PatternBrush, PatternSource, Shape, Value};
+    use alloc::vec;
+
+    fn layer(name: &str, content: Content) -> Layer {
+        Layer {
+            name: name.into(),
+            frames: 0.0..100.0,
+            content,
+            ..Default::default()
+        }
+    }
+
+    fn instance(name: &str) -> Content {
+        Content::Instance {
+            name: name.into(),
+            time_remap: None,
+        }
+    }
+
+    #[test]
+    fn extract_layer_keeps_parents_and_assets() {
+        let mut composition = Composition {
+            frames: 0.0..60.0,
+            ..Default::default()
+        };
+        composition
+            .assets
+            .insert("outer".into(), vec![layer("inner", instance("inner"))]);
+        composition.assets.insert("inner".into(), vec![]);
+        composition.assets.insert("unused".into(), vec![]);
+        composition.layers = vec![
+            layer("other", Content::None),
+            Layer {
+                parent: Some(3),
+                ..layer("child", instance("outer"))
+            },
+            layer("other", Content::None),
+            layer("parent", instance("unused")),
+        ];
+        let extracted = composition.extract_layer(1).unwrap();
+        assert_eq!(extracted.frames, 0.0..60.0);
+        assert_eq!(extracted.layers.len(), 2);
+        assert_eq!(extracted.layers[0].name, "child");
+        assert_eq!(extracted.layers[0].parent, Some(1));
+        assert!(
+            matches!(extracted.layers[1].content, Content::None),
+            "parent content should be dropped"
+        );
+        let mut assets: Vec<_> = extracted.assets.keys().cloned().collect();
+        assets.sort();
+        assert_eq!(assets, ["inner", "outer"]);
+    }
+
+    #[test]
+    fn extract_layer_keeps_assets_of_instance_brushes() {
+        let draw = Shape::Draw(Draw {
+            stroke: None,
+            brush: Brush::Instance(InstanceBrush {
+                name: "fill".into(),
+                transform: Default::default(),
+            }),
+            opacity: Value::Fixed(100.0),
+        });
+        let mut composition = Composition::default();
+        composition
+            .assets
+            .insert("fill".into(), vec![layer("nested", instance("nested"))]);
+        composition.assets.insert("nested".into(), vec![]);
+        composition.assets.insert("unused".into(), vec![]);
+        composition.layers = vec![layer(
+            "shapes",
+            Content::Shape(vec![Shape::Group(vec![draw], None)]),
+        )];
+        let extracted = composition.extract_layer(0).unwrap();
+        let mut assets: Vec<_> = extracted.assets.keys().cloned().collect();
+        assets.sort();
+        assert_eq!(assets, ["fill", "nested"]);
+    }
+
+    #[test]
+    fn extract_layer_keeps_assets_of_instance_patterns() {
+        let draw = Shape::Draw(Draw {
+            stroke: None,
+            brush: Brush::Pattern(PatternBrush {
+                source: PatternSource::Instance {
+                    name: "tile".into(),
+                    tile: kurbo::Rect::new(0.0, 0.0, 10.0, 10.0),
+                },
+                transform: Default::default(),
+            }),
+            opacity: Value::Fixed(100.0),
+        });
+        let mut composition = Composition::default();
+        composition.assets.insert("tile".into(), vec![]);
+        composition.assets.insert("unused".into(), vec![]);
+        composition.layers = vec![layer("shapes", Content::Shape(vec![draw]))];
+        let extracted = composition.extract_layer(0).unwrap();
+        let assets: Vec<_> = extracted.assets.keys().cloned().collect();
+        assert_eq!(assets, ["tile"]);
+    }
+}
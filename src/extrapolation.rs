@@ -0,0 +1,136 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use crate::{Time, Tween};
+
+/// Behavior of an animated value outside of the range of its keyframes.
+///
+/// Infinite frames are never extrapolated: they evaluate to the first or
+/// last keyframe, whatever the behavior.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum Extrapolation {
+    /// Hold the value of the first or last keyframe.
+    #[default]
+    Hold,
+    /// Repeat the keyframes from the start.
+    Loop,
+    /// Repeat the keyframes, alternating between forwards and backwards.
+    PingPong,
+    /// Continue with the velocity at the start or end of the keyframes.
+    ///
+    /// This requires the value to support [`Tween::extrapolate`]. Other
+    /// values are held.
+    Linear,
+}
+
+impl Extrapolation {
+    /// Returns the value at a frame, using `evaluate` for frames within the
+    /// range of the keyframes.
+    pub(crate) fn apply<T: Tween>(
+        self,
+        times: &[Time],
+        frame: f64,
+        mut evaluate: impl FnMut(f64) -> T,
+    ) -> T {
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return evaluate(frame);
+        };
+        let (start, end) = (first.frame, last.frame);
+        let duration = end - start;
+        if (start..=end).contains(&frame)
+            || !frame.is_finite()
+            || duration <= 0.0
+            || self == Self::Hold
+        {
+            return evaluate(frame);
+        }
+        match self {
+            Self::Hold => evaluate(frame),
+            Self::Loop => evaluate(start + wrap(frame - start, duration)),
+            Self::PingPong => {
+                let phase = wrap(frame - start, duration * 2.0);
+                if phase > duration {
+                    evaluate(start + duration * 2.0 - phase)
+                } else {
+                    evaluate(start + phase)
+                }
+            }
+            Self::Linear => {
+                // Estimate the velocity over a small step into the outer
+                // segment.
+                let (edge, segment, inward) = if frame > end {
+                    (end, &times[times.len() - 2], -1.0)
+                } else {
+                    (start, first, 1.0)
+                };
+                let value = evaluate(edge);
+                if segment.hold {
+                    return value;
+                }
+                let length = if frame > end {
+                    end - segment.frame
+                } else {
+                    times[1].frame - start
+                };
+                let step = length * STEP;
+                let inner = evaluate(edge + step * inward);
+                value
+                    .extrapolate(&inner, (frame - edge).abs() / step)
+                    .unwrap_or(value)
+            }
+        }
+    }
+}
+
+/// Fraction of the outer segment over which velocity is estimated.
+const STEP: f64 = 1e-4;
+
+/// Returns `x` wrapped into the range 0 to `period`.
+pub(crate) fn wrap(x: f64, period: f64) -> f64 {
+    let x = x % period;
+    if x < 0.0 {
+        x + period
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Animated, Easing};
+    use alloc::vec;
+
+    #[test]
+    fn extrapolation_modes() {
+        let mut animated = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0), Time::new(20.0)],
+            values: vec![0.0, 10.0, 30.0],
+            extrapolation: Extrapolation::Hold,
+            interpolation: Default::default(),
+        };
+        assert_eq!(animated.evaluate(25.0), 30.0);
+        animated.extrapolation = Extrapolation::Loop;
+        assert_eq!(animated.evaluate(25.0), 5.0);
+        assert_eq!(animated.evaluate(-5.0), 20.0);
+        animated.extrapolation = Extrapolation::PingPong;
+        assert_eq!(animated.evaluate(25.0), 20.0);
+        assert_eq!(animated.evaluate(45.0), 5.0);
+        animated.extrapolation = Extrapolation::Linear;
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+        assert!(
+            close(animated.evaluate(25.0), 40.0),
+            "should continue with the velocity of the last segment"
+        );
+        assert!(
+            close(animated.evaluate(-5.0), -5.0),
+            "should continue with the velocity of the first segment"
+        );
+        // An ease-in-out comes to rest, so continuing holds the value.
+        animated.times[1].easing = Some(Easing::cubic_bezier(0.42, 0.0, 0.58, 1.0));
+        assert!(
+            (animated.evaluate(25.0) - 30.0).abs() < 1e-2,
+            "should continue with the eased velocity"
+        );
+    }
+}
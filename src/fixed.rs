@@ -8,6 +8,8 @@
 use kurbo::common::FloatFuncs as _;
 use kurbo::{Affine, Point, Vec2};
 
+use crate::Easing;
+
 /// Fixed affine transformation.
 pub type Transform = Affine;
 
@@ -23,6 +25,17 @@ pub type Brush = peniko::Brush;
 /// Fixed stroke style.
 pub type Stroke = kurbo::Stroke;
 
+/// Fixed variation of the width of a stroke along its path.
+#[derive(Clone, Debug)]
+pub struct Taper {
+    /// Width at the start of each contour in percent of the stroke width.
+    pub start_width: f64,
+    /// Width at the end of each contour in percent of the stroke width.
+    pub end_width: f64,
+    /// Easing of the width from the start to the end, by arc length.
+    pub ease: Easing,
+}
+
 /// Fixed repeater effect.
 #[derive(Clone, Debug)]
 pub struct Repeater {
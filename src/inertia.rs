@@ -0,0 +1,195 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use core::f64::consts::E;
+use core::time::Duration;
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use crate::Linear;
+
+/// Length in seconds of the steps used to simulate rubber-banding.
+const BOUNCE_STEP: f64 = 1.0 / 240.0;
+
+/// Value that coasts with an initial velocity and slows down under
+/// friction, as after a fling gesture.
+///
+/// Optionally, the value can be kept within bounds. When it travels past
+/// a bound, it is pulled back by a critically damped spring, producing the
+/// familiar rubber band effect of scroll views.
+#[derive(Clone, Debug)]
+pub struct Inertia<T: Linear> {
+    value: T,
+    velocity: T,
+    friction: f64,
+    bounds: Option<(T, T)>,
+    bounce_stiffness: f64,
+    rest_threshold: f64,
+}
+
+impl<T: Linear> Inertia<T> {
+    /// Creates a new driver with the given value, velocity in units per
+    /// second and friction.
+    ///
+    /// Friction is the rate at which the velocity decays, so that a
+    /// friction of `k` reduces the velocity by a factor of `e` every `1 / k`
+    /// seconds.
+    pub fn new(value: T, velocity: T, friction: f64) -> Self {
+        Self {
+            value,
+            velocity,
+            friction,
+            bounds: None,
+            bounce_stiffness: 400.0,
+            rest_threshold: 1e-3,
+        }
+    }
+
+    /// Keeps the value within `min` and `max`, rubber-banding back when it
+    /// travels past them.
+    #[must_use]
+    pub fn with_bounds(mut self, min: T, max: T) -> Self {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    /// Sets the stiffness of the spring that pulls the value back within
+    /// bounds. Higher values bounce back more quickly.
+    #[must_use]
+    pub fn with_bounce_stiffness(mut self, stiffness: f64) -> Self {
+        self.bounce_stiffness = stiffness;
+        self
+    }
+
+    /// Sets the speed below which the value comes to rest.
+    #[must_use]
+    pub fn with_rest_threshold(mut self, threshold: f64) -> Self {
+        self.rest_threshold = threshold;
+        self
+    }
+
+    /// Returns the current value.
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    /// Returns the current velocity in units per second.
+    pub fn velocity(&self) -> T {
+        self.velocity
+    }
+
+    /// Returns the value at which the motion would come to rest in the
+    /// absence of bounds.
+    ///
+    /// This is useful for choosing a snap point before the motion ends.
+    pub fn projected_value(&self) -> T {
+        if self.friction <= 0.0 {
+            return self.value;
+        }
+        self.value.add_scaled(self.velocity, 1.0 / self.friction)
+    }
+
+    /// Returns true if the value has come to rest.
+    pub fn is_at_rest(&self) -> bool {
+        self.velocity.dot(self.velocity) == 0.0 && self.overshoot().dot(self.overshoot()) == 0.0
+    }
+
+    /// Returns the distance by which the value lies outside of the bounds.
+    fn overshoot(&self) -> T {
+        match self.bounds {
+            Some((min, max)) => self
+                .value
+                .add_scaled(self.value.clamp_components(min, max), -1.0),
+            None => T::default(),
+        }
+    }
+
+    /// Advances the simulation by the given amount of time and returns the
+    /// new value.
+    pub fn advance(&mut self, elapsed: Duration) -> T {
+        let mut remaining = elapsed.as_secs_f64();
+        while remaining > 0.0 && !self.is_at_rest() {
+            let overshoot = self.overshoot();
+            if overshoot.dot(overshoot) == 0.0 {
+                // Within bounds, coast analytically. With bounds, the step
+                // is limited so that crossing a bound is caught promptly.
+                let dt = if self.bounds.is_some() {
+                    remaining.min(BOUNCE_STEP)
+                } else {
+                    remaining
+                };
+                self.coast(dt);
+                remaining -= dt;
+            } else {
+                let dt = remaining.min(BOUNCE_STEP);
+                self.bounce(overshoot, dt);
+                remaining -= dt;
+            }
+            self.settle();
+        }
+        self.value
+    }
+
+    /// Applies friction for `dt` seconds.
+    fn coast(&mut self, dt: f64) {
+        if self.friction <= 0.0 {
+            self.value = self.value.add_scaled(self.velocity, dt);
+            return;
+        }
+        let decay = E.powf(-self.friction * dt);
+        self.value = self
+            .value
+            .add_scaled(self.velocity, (1.0 - decay) / self.friction);
+        self.velocity = T::default().add_scaled(self.velocity, decay);
+    }
+
+    /// Pulls the value back toward the bounds for `dt` seconds.
+    fn bounce(&mut self, overshoot: T, dt: f64) {
+        let damping = 2.0 * self.bounce_stiffness.sqrt();
+        let acceleration = T::default()
+            .add_scaled(overshoot, -self.bounce_stiffness)
+            .add_scaled(self.velocity, -damping);
+        self.velocity = self.velocity.add_scaled(acceleration, dt);
+        self.value = self.value.add_scaled(self.velocity, dt);
+    }
+
+    /// Stops the motion once it is slow enough.
+    fn settle(&mut self) {
+        let threshold = self.rest_threshold * self.rest_threshold;
+        let overshoot = self.overshoot();
+        if self.velocity.dot(self.velocity) <= threshold && overshoot.dot(overshoot) <= threshold {
+            self.velocity = T::default();
+            self.value = self.value.add_scaled(overshoot, -1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inertia_settles_at_projected_value() {
+        let mut inertia = Inertia::new(0.0, 1000.0, 4.0);
+        assert_eq!(inertia.projected_value(), 250.0);
+        inertia.advance(Duration::from_secs(10));
+        assert!(inertia.is_at_rest(), "inertia should come to rest");
+        assert!(
+            (inertia.value() - 250.0).abs() < 1e-2,
+            "inertia should settle at the projected value"
+        );
+    }
+
+    #[test]
+    fn inertia_rubber_bands_to_bounds() {
+        let mut inertia = Inertia::new(0.0, 1000.0, 4.0).with_bounds(0.0, 100.0);
+        let mut furthest: f64 = 0.0;
+        for _ in 0..600 {
+            furthest = furthest.max(inertia.advance(Duration::from_secs_f64(1.0 / 60.0)));
+        }
+        assert!(furthest > 100.0, "inertia should travel past the bound");
+        assert!(inertia.is_at_rest(), "inertia should come to rest");
+        assert_eq!(inertia.value(), 100.0);
+    }
+}
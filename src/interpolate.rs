@@ -0,0 +1,578 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+use peniko::kurbo;
+
+use crate::{Animated, SeekHint, Tween};
+
+/// Interpolation between the keyframes of an animated value.
+///
+/// Hold keyframes take precedence over the interpolation of the animated
+/// value, so individual keyframes can still hold when the animated value
+/// interpolates.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum Interpolation {
+    /// Each segment is interpolated using the easing of its starting
+    /// keyframe.
+    #[default]
+    Eased,
+    /// Every keyframe holds its value until the next keyframe, as if all of
+    /// them were hold keyframes. This suits discrete states such as
+    /// visibility flags or sprite indices.
+    Hold,
+    /// Catmull-Rom spline passing smoothly through every keyframe, as with
+    /// [`Tcb::CATMULL_ROM`].
+    ///
+    /// This applies to values that can be combined linearly, such as
+    /// numbers, points and sizes. Other values use keyframe easing. Splines
+    /// with other parameters are evaluated with [`Animated::evaluate_with`].
+    CatmullRom,
+    /// Monotone cubic curve passing smoothly through every keyframe without
+    /// overshooting, as with [`MonotoneCubic`].
+    ///
+    /// This applies to numbers. Other values use keyframe easing.
+    MonotoneCubic,
+}
+
+/// Strategy for interpolating between the keyframes of an animated value.
+///
+/// Unless the [interpolation](Animated::interpolation) of an animated value
+/// selects a spline, [`Animated::evaluate`] only considers the two keyframes
+/// surrounding a frame and the easing between them. Implementors of this
+/// trait receive the whole animation and may take neighboring keyframes into
+/// account to produce smooth curves across many keyframes.
+pub trait Interpolator<T: Tween> {
+    /// Returns the value within the segment between the keyframes at
+    /// indices `segment` and `segment + 1`.
+    ///
+    /// `t` is the linear progress through the segment in the range 0 to 1.
+    /// Hold keyframes are handled by the caller and never reach this
+    /// method.
+    fn interpolate(&self, animated: &Animated<T>, segment: usize, t: f64) -> T;
+}
+
+/// Interpolates each segment using the easing of its starting keyframe.
+///
+/// This matches the behavior of [`Animated::evaluate`].
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SegmentEasing;
+
+impl<T: Tween> Interpolator<T> for SegmentEasing {
+    fn interpolate(&self, animated: &Animated<T>, segment: usize, t: f64) -> T {
+        let (Some(v0), Some(v1)) = (
+            animated.values.get(segment),
+            animated.values.get(segment + 1),
+        ) else {
+            return T::default();
+        };
+        let easing = animated.times[segment].easing();
+        v0.tween(v1, t, easing.as_ref())
+    }
+}
+
+/// Values that can be combined linearly.
+///
+/// This allows interpolators to blend more than two keyframes at a time,
+/// and physical drivers such as [`Spring`](crate::Spring) to integrate
+/// motion.
+pub trait Linear: Tween + Copy {
+    /// Returns `self + other * scale`.
+    #[must_use]
+    fn add_scaled(self, other: Self, scale: f64) -> Self;
+
+    /// Returns the dot product of `self` and `other`, treating points as
+    /// vectors from the origin.
+    fn dot(self, other: Self) -> f64;
+
+    /// Returns the value with each component clamped to the corresponding
+    /// components of `min` and `max`.
+    #[must_use]
+    fn clamp_components(self, min: Self, max: Self) -> Self;
+
+    /// Returns the value with `f` applied to each component in turn.
+    #[must_use]
+    fn map_components(self, f: impl FnMut(f64) -> f64) -> Self;
+}
+
+impl Linear for f64 {
+    fn add_scaled(self, other: Self, scale: f64) -> Self {
+        self + other * scale
+    }
+
+    fn dot(self, other: Self) -> f64 {
+        self * other
+    }
+
+    fn clamp_components(self, min: Self, max: Self) -> Self {
+        self.max(min).min(max)
+    }
+
+    fn map_components(self, mut f: impl FnMut(f64) -> f64) -> Self {
+        f(self)
+    }
+}
+
+impl Linear for kurbo::Point {
+    fn add_scaled(self, other: Self, scale: f64) -> Self {
+        self + other.to_vec2() * scale
+    }
+
+    fn dot(self, other: Self) -> f64 {
+        self.to_vec2().dot(other.to_vec2())
+    }
+
+    fn clamp_components(self, min: Self, max: Self) -> Self {
+        Self::new(
+            self.x.clamp_components(min.x, max.x),
+            self.y.clamp_components(min.y, max.y),
+        )
+    }
+
+    fn map_components(self, mut f: impl FnMut(f64) -> f64) -> Self {
+        let x = f(self.x);
+        Self::new(x, f(self.y))
+    }
+}
+
+impl Linear for kurbo::Vec2 {
+    fn add_scaled(self, other: Self, scale: f64) -> Self {
+        self + other * scale
+    }
+
+    fn dot(self, other: Self) -> f64 {
+        Self::dot(self, other)
+    }
+
+    fn clamp_components(self, min: Self, max: Self) -> Self {
+        Self::new(
+            self.x.clamp_components(min.x, max.x),
+            self.y.clamp_components(min.y, max.y),
+        )
+    }
+
+    fn map_components(self, mut f: impl FnMut(f64) -> f64) -> Self {
+        let x = f(self.x);
+        Self::new(x, f(self.y))
+    }
+}
+
+impl Linear for kurbo::Size {
+    fn add_scaled(self, other: Self, scale: f64) -> Self {
+        self + other * scale
+    }
+
+    fn dot(self, other: Self) -> f64 {
+        self.to_vec2().dot(other.to_vec2())
+    }
+
+    fn clamp_components(self, min: Self, max: Self) -> Self {
+        Self::new(
+            self.width.clamp_components(min.width, max.width),
+            self.height.clamp_components(min.height, max.height),
+        )
+    }
+
+    fn map_components(self, mut f: impl FnMut(f64) -> f64) -> Self {
+        let width = f(self.width);
+        Self::new(width, f(self.height))
+    }
+}
+
+/// Kochanek–Bartels spline passing smoothly through every keyframe.
+///
+/// Tangents at each keyframe are derived from the neighboring keyframes, so
+/// long curves don't need hand-tuned easing handles. Keyframe easing is
+/// ignored. Tangents are scaled for the lengths of adjacent segments so
+/// that the speed is continuous across keyframes with uneven spacing.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct Tcb {
+    /// Length of the tangents. Positive values tighten the curve and
+    /// negative values round it.
+    pub tension: f64,
+    /// Sharpness of the change in direction at keyframes. Non-zero values
+    /// produce corners.
+    pub continuity: f64,
+    /// Direction of the curve as it passes through keyframes. Positive
+    /// values overshoot and negative values undershoot.
+    pub bias: f64,
+}
+
+impl Tcb {
+    /// Catmull-Rom spline, with all parameters set to zero.
+    pub const CATMULL_ROM: Self = Self {
+        tension: 0.0,
+        continuity: 0.0,
+        bias: 0.0,
+    };
+
+    /// Returns the outgoing (`outgoing = true`) or incoming tangent at the
+    /// given keyframe, in units of the adjacent segment.
+    fn tangent<T: Linear>(&self, animated: &Animated<T>, ix: usize, outgoing: bool) -> T {
+        let frame = |key: usize| animated.times.get(key).map_or(0.0, |time| time.frame);
+        let Some(&value) = animated.values.get(ix) else {
+            return T::default();
+        };
+        let keys = animated.values.len().min(animated.times.len());
+        let prev = ix.checked_sub(1);
+        let next = Some(ix + 1).filter(|next| *next < keys);
+        // Differences to the neighboring keyframes. At the ends, the one
+        // sided difference is used for both.
+        let (before, after) = match (prev, next) {
+            (Some(prev), Some(next)) => (
+                value.add_scaled(animated.values[prev], -1.0),
+                animated.values[next].add_scaled(value, -1.0),
+            ),
+            (Some(prev), None) => {
+                let d = value.add_scaled(animated.values[prev], -1.0);
+                (d, d)
+            }
+            (None, Some(next)) => {
+                let d = animated.values[next].add_scaled(value, -1.0);
+                (d, d)
+            }
+            (None, None) => return T::default(),
+        };
+        let (t, c, b) = (self.tension, self.continuity, self.bias);
+        let (c_before, c_after) = if outgoing {
+            (1.0 - c, 1.0 + c)
+        } else {
+            (1.0 + c, 1.0 - c)
+        };
+        let w_before = (1.0 - t) * (1.0 + b) * c_before * 0.5;
+        let w_after = (1.0 - t) * (1.0 - b) * c_after * 0.5;
+        let tangent = T::default()
+            .add_scaled(before, w_before)
+            .add_scaled(after, w_after);
+        let (Some(prev), Some(next)) = (prev, next) else {
+            return tangent;
+        };
+        let span_before = frame(ix) - frame(prev);
+        let span_after = frame(next) - frame(ix);
+        let span = if outgoing { span_after } else { span_before };
+        let total = span_before + span_after;
+        if total <= 0.0 {
+            return tangent;
+        }
+        T::default().add_scaled(tangent, 2.0 * span / total)
+    }
+}
+
+impl<T: Linear> Interpolator<T> for Tcb {
+    fn interpolate(&self, animated: &Animated<T>, segment: usize, t: f64) -> T {
+        let (Some(&p0), Some(&p1)) = (
+            animated.values.get(segment),
+            animated.values.get(segment + 1),
+        ) else {
+            return animated.values.get(segment).copied().unwrap_or_default();
+        };
+        let m0 = self.tangent(animated, segment, true);
+        let m1 = self.tangent(animated, segment + 1, false);
+        hermite(p0, m0, p1, m1, t)
+    }
+}
+
+/// Monotone cubic interpolation using the Fritsch–Carlson method.
+///
+/// The curve passes smoothly through every keyframe but never overshoots
+/// them, so values increase or decrease monotonically between keyframes.
+/// This is useful for properties such as opacity or progress where
+/// overshoot is unacceptable. Keyframe easing is ignored.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct MonotoneCubic;
+
+impl MonotoneCubic {
+    /// Returns the slope, in value per frame, of the segment starting at
+    /// the given keyframe.
+    fn secant(animated: &Animated<f64>, ix: usize) -> f64 {
+        let (Some(t0), Some(t1), Some(v0), Some(v1)) = (
+            animated.times.get(ix),
+            animated.times.get(ix + 1),
+            animated.values.get(ix),
+            animated.values.get(ix + 1),
+        ) else {
+            return 0.0;
+        };
+        let span = t1.frame - t0.frame;
+        if span <= 0.0 {
+            return 0.0;
+        }
+        (v1 - v0) / span
+    }
+
+    /// Returns the index of the last keyframe with both a time and a value.
+    fn last(animated: &Animated<f64>) -> usize {
+        animated
+            .values
+            .len()
+            .min(animated.times.len())
+            .saturating_sub(1)
+    }
+
+    /// Returns the tangent at the given keyframe before limiting.
+    fn initial_tangent(animated: &Animated<f64>, ix: usize) -> f64 {
+        let last = Self::last(animated);
+        if last == 0 {
+            return 0.0;
+        }
+        if ix == 0 {
+            return Self::secant(animated, 0);
+        }
+        if ix == last {
+            return Self::secant(animated, last - 1);
+        }
+        let before = Self::secant(animated, ix - 1);
+        let after = Self::secant(animated, ix);
+        if before * after <= 0.0 {
+            0.0
+        } else {
+            (before + after) * 0.5
+        }
+    }
+
+    /// Returns the tangent at the given keyframe, limited so that neither
+    /// adjacent segment overshoots.
+    fn tangent(animated: &Animated<f64>, ix: usize) -> f64 {
+        let tangent = Self::initial_tangent(animated, ix);
+        let last = Self::last(animated);
+        let mut scale: f64 = 1.0;
+        for segment in [ix.checked_sub(1), Some(ix).filter(|ix| *ix < last)]
+            .into_iter()
+            .flatten()
+        {
+            let secant = Self::secant(animated, segment);
+            if secant == 0.0 {
+                return 0.0;
+            }
+            let alpha = Self::initial_tangent(animated, segment) / secant;
+            let beta = Self::initial_tangent(animated, segment + 1) / secant;
+            let length = alpha * alpha + beta * beta;
+            if length > 9.0 {
+                scale = scale.min(3.0 / length.sqrt());
+            }
+        }
+        tangent * scale
+    }
+}
+
+impl Interpolator<f64> for MonotoneCubic {
+    fn interpolate(&self, animated: &Animated<f64>, segment: usize, t: f64) -> f64 {
+        let (Some(t0), Some(t1), Some(&v0), Some(&v1)) = (
+            animated.times.get(segment),
+            animated.times.get(segment + 1),
+            animated.values.get(segment),
+            animated.values.get(segment + 1),
+        ) else {
+            return animated.values.get(segment).copied().unwrap_or_default();
+        };
+        let span = t1.frame - t0.frame;
+        hermite(
+            v0,
+            Self::tangent(animated, segment) * span,
+            v1,
+            Self::tangent(animated, segment + 1) * span,
+            t,
+        )
+    }
+}
+
+/// Returns the value within the segment starting at the given keyframe
+/// using the interpolation of the animated value, or `None` if the value is
+/// interpolated using keyframe easing.
+pub(crate) fn interpolate_linear<T: Linear>(
+    animated: &Animated<T>,
+    segment: usize,
+    t: f64,
+) -> Option<T> {
+    match &animated.interpolation {
+        Interpolation::CatmullRom => Some(Tcb::CATMULL_ROM.interpolate(animated, segment, t)),
+        Interpolation::Eased | Interpolation::Hold | Interpolation::MonotoneCubic => None,
+    }
+}
+
+/// Evaluates a cubic Hermite curve with the given end points and tangents.
+fn hermite<T: Linear>(p0: T, m0: T, p1: T, m1: T, t: f64) -> T {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    T::default()
+        .add_scaled(p0, 2.0 * t3 - 3.0 * t2 + 1.0)
+        .add_scaled(m0, t3 - 2.0 * t2 + t)
+        .add_scaled(p1, -2.0 * t3 + 3.0 * t2)
+        .add_scaled(m1, t3 - t2)
+}
+
+impl<T: Tween> Animated<T> {
+    /// Returns the value at the specified frame, using the given strategy
+    /// to interpolate between keyframes.
+    ///
+    /// Frames before the first or after the last keyframe follow the
+    /// extrapolation of the animation.
+    pub fn evaluate_with(&self, frame: f64, interpolator: &impl Interpolator<T>) -> T {
+        self.extrapolation.apply(&self.times, frame, |frame| {
+            self.evaluate_segment_with(frame, interpolator)
+        })
+    }
+
+    fn evaluate_segment_with(&self, frame: f64, interpolator: &impl Interpolator<T>) -> T {
+        let Some(([ix0, ix1], t, _, hold)) = self.segment(frame, &mut SeekHint::default()) else {
+            return T::default();
+        };
+        if hold || ix0 == ix1 || t <= 0.0 {
+            return self.values.get(ix0).cloned().unwrap_or_default();
+        }
+        if t >= 1.0 {
+            return self.values.get(ix1).cloned().unwrap_or_default();
+        }
+        interpolator.interpolate(self, ix0, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Time;
+    use alloc::vec;
+
+    /// Interpolator that always picks the nearest keyframe.
+    struct Nearest;
+
+    impl Interpolator<f64> for Nearest {
+        fn interpolate(&self, animated: &Animated<f64>, segment: usize, t: f64) -> f64 {
+            animated.values[if t < 0.5 { segment } else { segment + 1 }]
+        }
+    }
+
+    #[test]
+    fn evaluate_with_custom_interpolator() {
+        let animated = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0), Time::new(20.0)],
+            values: vec![0.0, 10.0, 30.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        assert_eq!(animated.evaluate_with(-5.0, &Nearest), 0.0);
+        assert_eq!(animated.evaluate_with(4.0, &Nearest), 0.0);
+        assert_eq!(animated.evaluate_with(16.0, &Nearest), 30.0);
+        assert_eq!(animated.evaluate_with(25.0, &Nearest), 30.0);
+        for frame in [2.0, 10.0, 13.0] {
+            assert!(
+                (animated.evaluate_with(frame, &SegmentEasing) - animated.evaluate(frame)).abs()
+                    < 1e-6,
+                "segment easing should match the default evaluation"
+            );
+        }
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_keyframes() {
+        let points = [(0.0, 0.0), (10.0, 10.0), (20.0, 0.0), (40.0, 10.0)];
+        let animated = Animated {
+            times: vec![
+                Time::new(0.0),
+                Time::new(10.0),
+                Time::new(20.0),
+                Time::new(40.0),
+            ],
+            values: points
+                .iter()
+                .map(|(x, y)| kurbo::Point::new(*x, *y))
+                .collect(),
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let spline = Tcb::CATMULL_ROM;
+        for (key, value) in animated.times.iter().zip(&animated.values) {
+            assert_eq!(animated.evaluate_with(key.frame, &spline), *value);
+        }
+        // Collinear keyframes with even spacing produce a straight line.
+        let line = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0), Time::new(20.0)],
+            values: vec![0.0, 10.0, 20.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        assert!(
+            (line.evaluate_with(15.0, &spline) - 15.0).abs() < 1e-9,
+            "evenly spaced collinear keyframes should interpolate linearly"
+        );
+        // The curve is smooth across the middle keyframe, unlike the linear
+        // default.
+        let before = animated.evaluate_with(9.99, &spline);
+        let after = animated.evaluate_with(10.01, &spline);
+        assert!(
+            (before.y - after.y).abs() < 1e-3,
+            "the curve should be smooth at keyframes"
+        );
+        // Selecting the spline for the property applies it to the default
+        // evaluation, which the renderer uses.
+        let selected = Animated {
+            interpolation: Interpolation::CatmullRom,
+            ..animated.clone()
+        };
+        for frame in [-5.0, 5.0, 10.0, 15.0, 33.0, 50.0] {
+            assert_eq!(
+                selected.evaluate(frame),
+                animated.evaluate_with(frame, &spline)
+            );
+        }
+        assert_ne!(selected.evaluate(25.0), animated.evaluate(25.0));
+    }
+
+    #[test]
+    fn monotone_cubic_does_not_overshoot() {
+        let animated = Animated {
+            times: vec![
+                Time::new(0.0),
+                Time::new(10.0),
+                Time::new(20.0),
+                Time::new(22.0),
+            ],
+            values: vec![0.0, 1.0, 1.0, 0.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let mut previous = 0.0;
+        for frame in 0..=10 {
+            let value = animated.evaluate_with(frame as f64, &MonotoneCubic);
+            assert!(
+                value >= previous && value <= 1.0,
+                "values should rise monotonically without overshoot"
+            );
+            previous = value;
+        }
+        for frame in 10..=20 {
+            assert_eq!(animated.evaluate_with(frame as f64, &MonotoneCubic), 1.0);
+        }
+        assert!(
+            animated.evaluate_with(15.0, &Tcb::CATMULL_ROM) > 1.0,
+            "catmull-rom should overshoot the plateau for comparison"
+        );
+        let selected = Animated {
+            interpolation: Interpolation::MonotoneCubic,
+            ..animated.clone()
+        };
+        for frame in [5.0, 15.0, 21.0] {
+            assert_eq!(
+                selected.evaluate(frame),
+                animated.evaluate_with(frame, &MonotoneCubic)
+            );
+        }
+    }
+
+    #[test]
+    fn splines_tolerate_missing_values() {
+        let animated = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0), Time::new(20.0)],
+            values: vec![0.0, 10.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        for frame in [5.0, 15.0] {
+            animated.evaluate_with(frame, &Tcb::CATMULL_ROM);
+            animated.evaluate_with(frame, &MonotoneCubic);
+        }
+        assert_eq!(animated.evaluate_with(15.0, &MonotoneCubic), 10.0);
+    }
+}
@@ -0,0 +1,633 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! Traversal of the keyframes of animated properties.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::ops::Range;
+
+use crate::{
+    animated, Animated, Brush, ColorStops, Content, Draw, Extrapolation, Geometry, GroupTransform,
+    Layer, Mask, Modifier, PuckerBloat, Repeater, Shape, Stroke, Time, Transform, Tween, Twist,
+    Value, Wave, ZigZag,
+};
+
+/// Callback receiving the keyframe times of a property and returning the
+/// range of keyframes to retain.
+pub(crate) type EditKeyframes<'a> = dyn FnMut(&mut [Time]) -> Range<usize> + 'a;
+
+/// Callback receiving the keyframe times of a property and its behavior
+/// outside of them.
+pub(crate) type VisitKeyframes<'a> = dyn FnMut(&[Time], Extrapolation) + 'a;
+
+/// Types containing keyframed properties.
+pub(crate) trait Keyframed {
+    /// Calls `f` with the keyframe times of each animated property,
+    /// dropping any keyframes outside of the returned range.
+    ///
+    /// For layers, this only visits properties evaluated in the time of the
+    /// containing layer set and not the layers of instanced assets.
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>);
+
+    /// Calls `f` with the keyframe times of each animated property.
+    ///
+    /// This visits the same properties as [`Keyframed::edit_keyframes`].
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>);
+
+    /// Returns true if every property is known to evaluate identically at
+    /// both frames.
+    fn is_constant_between(&self, a: f64, b: f64) -> bool {
+        let mut constant = true;
+        self.visit_keyframes(&mut |times, extrapolation| {
+            constant = constant && Time::is_constant_over(times, extrapolation, a, b);
+        });
+        constant
+    }
+}
+
+/// Applies `f` to a list of times and the corresponding list of values.
+fn edit<T>(times: &mut Vec<Time>, values: &mut Vec<T>, f: &mut EditKeyframes<'_>) {
+    if times.is_empty() {
+        return;
+    }
+    let keep = f(times);
+    times.truncate(keep.end);
+    times.drain(..keep.start);
+    values.truncate(keep.end);
+    values.drain(..keep.start.min(values.len()));
+}
+
+impl<T: Tween> Keyframed for Animated<T> {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        edit(&mut self.times, &mut self.values, f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        f(&self.times, self.extrapolation);
+    }
+}
+
+impl<T: Tween> Keyframed for Value<T> {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        if let Self::Animated(animated) = self {
+            animated.edit_keyframes(f);
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        if let Self::Animated(animated) = self {
+            animated.visit_keyframes(f);
+        }
+    }
+}
+
+impl<T: Keyframed> Keyframed for Option<T> {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        if let Some(value) = self {
+            value.edit_keyframes(f);
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        if let Some(value) = self {
+            value.visit_keyframes(f);
+        }
+    }
+}
+
+impl<T: Keyframed + ?Sized> Keyframed for Box<T> {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        (**self).edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        (**self).visit_keyframes(f);
+    }
+}
+
+impl<T: Keyframed> Keyframed for [T] {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        for value in self {
+            value.edit_keyframes(f);
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        for value in self {
+            value.visit_keyframes(f);
+        }
+    }
+}
+
+impl Keyframed for animated::Position {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        match self {
+            Self::Value(value) => value.edit_keyframes(f),
+            Self::SplitValues((x, y)) => {
+                x.edit_keyframes(f);
+                y.edit_keyframes(f);
+            }
+            Self::Spatial(spatial) => spatial.edit_keyframes(f),
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        match self {
+            Self::Value(value) => value.visit_keyframes(f),
+            Self::SplitValues((x, y)) => {
+                x.visit_keyframes(f);
+                y.visit_keyframes(f);
+            }
+            Self::Spatial(spatial) => spatial.visit_keyframes(f),
+        }
+    }
+}
+
+impl Keyframed for animated::SpatialPosition {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        let mut kept = 0..self.tangents.len();
+        edit(
+            &mut self.value.times,
+            &mut self.value.values,
+            &mut |times: &mut [Time]| {
+                kept = f(times);
+                kept.clone()
+            },
+        );
+        self.tangents.truncate(kept.end);
+        self.tangents.drain(..kept.start.min(self.tangents.len()));
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.value.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::Transform {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.anchor.edit_keyframes(f);
+        self.position.edit_keyframes(f);
+        self.rotation.edit_keyframes(f);
+        self.scale.edit_keyframes(f);
+        self.skew.edit_keyframes(f);
+        self.skew_angle.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.anchor.visit_keyframes(f);
+        self.position.visit_keyframes(f);
+        self.rotation.visit_keyframes(f);
+        self.scale.visit_keyframes(f);
+        self.skew.visit_keyframes(f);
+        self.skew_angle.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::Ellipse {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.position.edit_keyframes(f);
+        self.size.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.position.visit_keyframes(f);
+        self.size.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::PolyStar {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.position.edit_keyframes(f);
+        self.inner_radius.edit_keyframes(f);
+        self.inner_roundness.edit_keyframes(f);
+        self.outer_radius.edit_keyframes(f);
+        self.outer_roundness.edit_keyframes(f);
+        self.rotation.edit_keyframes(f);
+        self.points.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.position.visit_keyframes(f);
+        self.inner_radius.visit_keyframes(f);
+        self.inner_roundness.visit_keyframes(f);
+        self.outer_radius.visit_keyframes(f);
+        self.outer_roundness.visit_keyframes(f);
+        self.rotation.visit_keyframes(f);
+        self.points.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::Rect {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.position.edit_keyframes(f);
+        self.size.edit_keyframes(f);
+        self.corner_radius.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.position.visit_keyframes(f);
+        self.size.visit_keyframes(f);
+        self.corner_radius.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::Spline {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        edit(&mut self.times, &mut self.values, f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        f(&self.times, Extrapolation::Hold);
+    }
+}
+
+impl Keyframed for animated::Path {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.vertices.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.vertices.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::PathVertex {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.point.edit_keyframes(f);
+        self.in_tangent.edit_keyframes(f);
+        self.out_tangent.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.point.visit_keyframes(f);
+        self.in_tangent.visit_keyframes(f);
+        self.out_tangent.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::Repeater {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.copies.edit_keyframes(f);
+        self.offset.edit_keyframes(f);
+        self.anchor_point.edit_keyframes(f);
+        self.position.edit_keyframes(f);
+        self.rotation.edit_keyframes(f);
+        self.scale.edit_keyframes(f);
+        self.start_opacity.edit_keyframes(f);
+        self.end_opacity.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.copies.visit_keyframes(f);
+        self.offset.visit_keyframes(f);
+        self.anchor_point.visit_keyframes(f);
+        self.position.visit_keyframes(f);
+        self.rotation.visit_keyframes(f);
+        self.scale.visit_keyframes(f);
+        self.start_opacity.visit_keyframes(f);
+        self.end_opacity.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::Stroke {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.width.edit_keyframes(f);
+        self.dash_pattern.edit_keyframes(f);
+        self.dash_offset.edit_keyframes(f);
+        self.taper.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.width.visit_keyframes(f);
+        self.dash_pattern.visit_keyframes(f);
+        self.dash_offset.visit_keyframes(f);
+        self.taper.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::Taper {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.start_width.edit_keyframes(f);
+        self.end_width.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.start_width.visit_keyframes(f);
+        self.end_width.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for animated::ColorStops {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        edit(&mut self.frames, &mut self.values, f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        f(&self.frames, Extrapolation::Hold);
+    }
+}
+
+impl Keyframed for animated::Gradient {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.start_point.edit_keyframes(f);
+        self.end_point.edit_keyframes(f);
+        self.highlight_length.edit_keyframes(f);
+        self.highlight_angle.edit_keyframes(f);
+        self.rotation.edit_keyframes(f);
+        self.scale.edit_keyframes(f);
+        self.stops.edit_keyframes(f);
+        if let Some(opacity_stops) = &mut self.opacity_stops {
+            edit(&mut opacity_stops.frames, &mut opacity_stops.values, f);
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.start_point.visit_keyframes(f);
+        self.end_point.visit_keyframes(f);
+        self.highlight_length.visit_keyframes(f);
+        self.highlight_angle.visit_keyframes(f);
+        self.rotation.visit_keyframes(f);
+        self.scale.visit_keyframes(f);
+        self.stops.visit_keyframes(f);
+        if let Some(opacity_stops) = &self.opacity_stops {
+            f(&opacity_stops.frames, Extrapolation::Hold);
+        }
+    }
+}
+
+impl Keyframed for animated::Brush {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        match self {
+            Self::Solid(value, _) => value.edit_keyframes(f),
+            Self::Gradient(gradient) => gradient.edit_keyframes(f),
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        match self {
+            Self::Solid(value, _) => value.visit_keyframes(f),
+            Self::Gradient(gradient) => gradient.visit_keyframes(f),
+        }
+    }
+}
+
+macro_rules! simple_value_keyframes {
+    ($($name:ident),*) => {
+        $(
+            impl Keyframed for $name {
+                fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+                    if let Self::Animated(animated) = self {
+                        animated.edit_keyframes(f);
+                    }
+                }
+
+                fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+                    if let Self::Animated(animated) = self {
+                        animated.visit_keyframes(f);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+simple_value_keyframes!(Transform, Stroke, Repeater, ColorStops);
+
+impl Keyframed for Brush {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        match self {
+            Self::Fixed(_) => {}
+            Self::Animated(animated) => animated.edit_keyframes(f),
+            Self::Instance(brush) => brush.transform.edit_keyframes(f),
+            Self::Pattern(brush) => brush.transform.edit_keyframes(f),
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        match self {
+            Self::Fixed(_) => {}
+            Self::Animated(animated) => animated.visit_keyframes(f),
+            Self::Instance(brush) => brush.transform.visit_keyframes(f),
+            Self::Pattern(brush) => brush.transform.visit_keyframes(f),
+        }
+    }
+}
+
+impl Keyframed for Geometry {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        match self {
+            Self::Fixed(_) => {}
+            Self::Rect(rect) => rect.edit_keyframes(f),
+            Self::Ellipse(ellipse) => ellipse.edit_keyframes(f),
+            Self::PolyStar(star) => star.edit_keyframes(f),
+            Self::Spline(spline) => spline.edit_keyframes(f),
+            Self::Path(path) => path.edit_keyframes(f),
+            Self::Morph(morph) => morph.edit_keyframes(f),
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        match self {
+            Self::Fixed(_) => {}
+            Self::Rect(rect) => rect.visit_keyframes(f),
+            Self::Ellipse(ellipse) => ellipse.visit_keyframes(f),
+            Self::PolyStar(star) => star.visit_keyframes(f),
+            Self::Spline(spline) => spline.visit_keyframes(f),
+            Self::Path(path) => path.visit_keyframes(f),
+            Self::Morph(morph) => morph.visit_keyframes(f),
+        }
+    }
+}
+
+impl Keyframed for Draw {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.stroke.edit_keyframes(f);
+        self.brush.edit_keyframes(f);
+        self.opacity.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.stroke.visit_keyframes(f);
+        self.brush.visit_keyframes(f);
+        self.opacity.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for GroupTransform {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.transform.edit_keyframes(f);
+        self.opacity.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.transform.visit_keyframes(f);
+        self.opacity.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for Shape {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        match self {
+            Self::Group(shapes, transform) => {
+                shapes.edit_keyframes(f);
+                transform.edit_keyframes(f);
+            }
+            Self::Geometry(geometry) => geometry.edit_keyframes(f),
+            Self::Draw(draw) => draw.edit_keyframes(f),
+            Self::Repeater(repeater) => repeater.edit_keyframes(f),
+            Self::Modifier(modifier) => modifier.edit_keyframes(f),
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        match self {
+            Self::Group(shapes, transform) => {
+                shapes.visit_keyframes(f);
+                transform.visit_keyframes(f);
+            }
+            Self::Geometry(geometry) => geometry.visit_keyframes(f),
+            Self::Draw(draw) => draw.visit_keyframes(f),
+            Self::Repeater(repeater) => repeater.visit_keyframes(f),
+            Self::Modifier(modifier) => modifier.visit_keyframes(f),
+        }
+    }
+}
+
+impl Keyframed for Modifier {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        match self {
+            Self::ZigZag(zig_zag) => zig_zag.edit_keyframes(f),
+            Self::PuckerBloat(pucker_bloat) => pucker_bloat.edit_keyframes(f),
+            Self::Twist(twist) => twist.edit_keyframes(f),
+            Self::Wave(wave) => wave.edit_keyframes(f),
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        match self {
+            Self::ZigZag(zig_zag) => zig_zag.visit_keyframes(f),
+            Self::PuckerBloat(pucker_bloat) => pucker_bloat.visit_keyframes(f),
+            Self::Twist(twist) => twist.visit_keyframes(f),
+            Self::Wave(wave) => wave.visit_keyframes(f),
+        }
+    }
+}
+
+impl Keyframed for ZigZag {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.size.edit_keyframes(f);
+        self.ridges.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.size.visit_keyframes(f);
+        self.ridges.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for PuckerBloat {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.amount.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.amount.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for Twist {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.angle.edit_keyframes(f);
+        self.center.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.angle.visit_keyframes(f);
+        self.center.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for Wave {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.amplitude.edit_keyframes(f);
+        self.wavelength.edit_keyframes(f);
+        self.phase.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.amplitude.visit_keyframes(f);
+        self.wavelength.visit_keyframes(f);
+        self.phase.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for Mask {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.geometry.edit_keyframes(f);
+        self.opacity.edit_keyframes(f);
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.geometry.visit_keyframes(f);
+        self.opacity.visit_keyframes(f);
+    }
+}
+
+impl Keyframed for Layer {
+    fn edit_keyframes(&mut self, f: &mut EditKeyframes<'_>) {
+        self.transform.edit_keyframes(f);
+        self.opacity.edit_keyframes(f);
+        self.masks.edit_keyframes(f);
+        match &mut self.content {
+            Content::None | Content::External(_) => {}
+            Content::Instance { time_remap, .. } => time_remap.edit_keyframes(f),
+            Content::Shape(shapes) => shapes.edit_keyframes(f),
+        }
+    }
+
+    fn visit_keyframes(&self, f: &mut VisitKeyframes<'_>) {
+        self.transform.visit_keyframes(f);
+        self.opacity.visit_keyframes(f);
+        self.masks.visit_keyframes(f);
+        match &self.content {
+            Content::None | Content::External(_) => {}
+            Content::Instance { time_remap, .. } => time_remap.visit_keyframes(f),
+            Content::Shape(shapes) => shapes.visit_keyframes(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::Easing;
+
+    #[test]
+    fn layer_constancy_covers_all_properties() {
+        let time = |frame, easing| Time::new(frame).with_easing(easing);
+        let opacity = Animated {
+            times: vec![time(0.0, Easing::Hold), time(10.0, Easing::LERP)],
+            values: vec![50.0, 100.0],
+            extrapolation: Extrapolation::Hold,
+            interpolation: Default::default(),
+        };
+        let mut layer = Layer {
+            opacity: Value::Animated(opacity.clone()),
+            ..Default::default()
+        };
+        assert!(layer.is_constant_between(2.0, 8.0), "holding");
+        assert!(!layer.is_constant_between(8.0, 12.0), "crossing a keyframe");
+        assert!(layer.is_constant_between(12.0, 20.0), "after the keyframes");
+
+        layer.opacity = Value::Animated(Animated {
+            extrapolation: Extrapolation::Loop,
+            ..opacity
+        });
+        assert!(
+            !layer.is_constant_between(12.0, 20.0),
+            "looping repeats the animation after the keyframes"
+        );
+    }
+}
@@ -25,26 +25,108 @@
 )]
 
 extern crate alloc;
+// Allows the derive macros to refer to this crate by name from within it.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as interpoli;
+// Only used by the benchmarks.
+#[cfg(test)]
+use criterion as _;
+// Only used by the tests of the `serde` feature.
+#[cfg(all(test, not(feature = "serde")))]
+use serde_json as _;
 
 use kurbo::Affine;
 
+mod analysis;
+mod angle;
+mod backend;
+mod bake;
+mod batch;
+mod color;
+mod compare;
+mod compile;
 mod composition;
+mod css;
+mod display;
+mod edit;
+mod entity;
+mod extract;
+mod extrapolation;
+mod inertia;
+mod interpolate;
+mod keyframes;
+mod merge;
+mod modifier;
+mod morph;
+mod retarget;
+mod retime;
 mod spline;
+mod spring;
+mod sprite;
+mod stagger;
+mod taper;
+mod temporal;
+mod theme;
+mod time_variation;
+mod tweens;
+mod units;
+mod validate;
 mod value;
+mod variant;
+mod wiggle;
+mod winding;
 
-#[cfg(feature = "vello")]
 mod render;
 
 pub mod animated;
 pub mod fixed;
 
+pub use analysis::{LoopPoint, LoopSearch, Property};
+pub use angle::Angle;
+pub use backend::RenderBackend;
+pub use bake::{BakeError, BakedComposition, MAX_BAKED_FRAMES};
+pub use batch::ChannelBatch;
+pub use color::{ColorSpace, HueDirection};
+pub use compare::{Comparison, Divergence, PropertyDivergence};
+pub use compile::CompiledComposition;
 pub use composition::{
-    Composition, Content, Draw, Geometry, GroupTransform, Layer, Mask, Matte, Shape,
+    Composition, CompositionId, Content, Draw, ExternalId, Geometry, GeometryPathElements,
+    GeometryShape, GroupTransform, InstanceBrush, Layer, Mask, MaskMode, Matte, PatternBrush,
+    PatternSource, Shape,
+};
+pub use css::ParseEasingError;
+pub use display::{DisplayCommand, DisplayList};
+pub use edit::KeyframeError;
+pub use entity::{Entity, EntityId, EntityList};
+pub use extrapolation::Extrapolation;
+pub use inertia::Inertia;
+pub use interpolate::{Interpolation, Interpolator, Linear, MonotoneCubic, SegmentEasing, Tcb};
+pub use merge::MergePolicy;
+pub use modifier::{Modifier, PuckerBloat, Twist, Wave, ZigZag};
+pub use retime::FrameSnap;
+pub use spline::{SplinePathElements, SplineShape};
+pub use spring::{Spring, SpringConfig};
+pub use sprite::{SpriteFrame, SpriteSheet};
+pub use stagger::{Stagger, StaggerFrom};
+pub use temporal::TemporalEase;
+pub use theme::MotionTheme;
+pub use time_variation::TimeVariation;
+pub use tweens::Tweens;
+pub use units::{Length, Opacity};
+pub use validate::{Limits, ValidationError};
+pub use value::{
+    Animated, Easing, EasingFn, EasingHandle, JumpTerm, SeekHint, SeekHints, Time, Tween, Value,
+    ValueRef,
 };
-pub use value::{Animated, Easing, EasingHandle, Time, Tween, Value, ValueRef};
+pub use variant::{Patch, VariantSet};
+pub use wiggle::Wiggle;
+pub use winding::{Contour, Winding};
+
+/// Derives [`Tween`] for a struct by tweening each field.
+#[cfg(feature = "derive")]
+pub use interpoli_derive::Tween;
 
-#[cfg(feature = "vello")]
-pub use render::Renderer;
+pub use render::{FrozenProperties, LayerInfo, LayoutDirection, MirrorPolicy, Renderer};
 
 macro_rules! simple_value {
     ($name:ident) => {
@@ -59,7 +141,7 @@ macro_rules! simple_value {
             pub fn is_fixed(&self) -> bool {
                 matches!(self, Self::Fixed(_))
             }
-            pub fn evaluate(&self, frame: f64) -> ValueRef<fixed::$name> {
+            pub fn evaluate(&self, frame: f64) -> ValueRef<'_, fixed::$name> {
                 match self {
                     Self::Fixed(value) => ValueRef::Borrowed(value),
                     Self::Animated(value) => ValueRef::Owned(value.evaluate(frame)),
@@ -78,6 +160,10 @@ simple_value!(ColorStops);
 pub enum Brush {
     Fixed(fixed::Brush),
     Animated(animated::Brush),
+    /// Content of an asset, clipped to the filled or stroked shape.
+    Instance(InstanceBrush),
+    /// Repeating pattern, clipped to the filled or stroked shape.
+    Pattern(PatternBrush),
 }
 
 impl Brush {
@@ -85,7 +171,13 @@ impl Brush {
         matches!(self, Self::Fixed(_))
     }
 
-    pub fn evaluate(&self, alpha: f64, frame: f64) -> ValueRef<fixed::Brush> {
+    /// Evaluates the brush at the specified frame.
+    ///
+    /// Instance brushes and instance patterns have no fixed representation
+    /// and evaluate to a transparent color, while image patterns evaluate
+    /// to the repeating image without the pattern transform. The renderer
+    /// draws these brushes separately.
+    pub fn evaluate(&self, alpha: f64, frame: f64) -> ValueRef<'_, fixed::Brush> {
         match self {
             Self::Fixed(value) => {
                 if alpha == 1.0 {
@@ -95,6 +187,41 @@ impl Brush {
                 }
             }
             Self::Animated(value) => ValueRef::Owned(value.evaluate(alpha, frame)),
+            Self::Pattern(PatternBrush {
+                source: PatternSource::Image(image),
+                ..
+            }) => ValueRef::Owned(
+                image
+                    .clone()
+                    .with_extend(peniko::Extend::Repeat)
+                    .multiply_alpha(alpha as f32)
+                    .into(),
+            ),
+            Self::Instance(_) | Self::Pattern(_) => {
+                ValueRef::Owned(fixed::Color::TRANSPARENT.into())
+            }
+        }
+    }
+}
+
+impl Transform {
+    /// Evaluates the transform at the specified frame, using the hints for
+    /// each of its properties in the set.
+    pub fn evaluate_cached(&self, frame: f64, hints: &mut SeekHints) -> ValueRef<'_, Affine> {
+        match self {
+            Self::Fixed(value) => ValueRef::Borrowed(value),
+            Self::Animated(value) => ValueRef::Owned(value.evaluate_cached(frame, hints)),
+        }
+    }
+}
+
+impl Stroke {
+    /// Evaluates the taper of the stroke at the specified frame, or returns
+    /// `None` if the width of the stroke is constant along its path.
+    pub fn taper(&self, frame: f64) -> Option<fixed::Taper> {
+        match self {
+            Self::Fixed(_) => None,
+            Self::Animated(value) => value.evaluate_taper(frame),
         }
     }
 }
@@ -104,11 +231,3 @@ impl Default for Transform {
         Self::Fixed(Affine::IDENTITY)
     }
 }
-
-#[cfg(test)]
-mod tests {
-    // CI will fail unless cargo nextest can execute at least one test per workspace.
-    // Delete this dummy test once we have an actual real test.
-    #[test]
-    fn dummy_test_until_we_have_a_real_test() {}
-}
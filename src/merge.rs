@@ -0,0 +1,150 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::{format, string::String, vec::Vec};
+
+use hashbrown::HashMap;
+
+use crate::{
+    Brush, Composition, Content, Draw, InstanceBrush, Layer, PatternBrush, PatternSource, Shape,
+};
+
+/// Policy for resolving assets with the same name when merging compositions.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum MergePolicy {
+    /// Rename the incoming asset to a unique name and update any instances
+    /// that reference it.
+    #[default]
+    Rename,
+    /// Replace the existing asset with the incoming one.
+    Overwrite,
+    /// Keep the existing asset and discard the incoming one. Incoming
+    /// instances will reference the existing asset.
+    Skip,
+}
+
+impl Composition {
+    /// Merges the layers and assets of another composition into this one.
+    ///
+    /// Incoming layers are stacked below the existing layers, with their
+    /// parent and matte indices adjusted accordingly. Asset name conflicts
+    /// are resolved according to `policy`. The frame range is extended to
+    /// cover both compositions and the size is grown to fit both.
+    pub fn merge(&mut self, other: &Self, policy: MergePolicy) {
+        crate::edit::bump_generation();
+        let mut renames: HashMap<&str, String> = HashMap::new();
+        // Names under which incoming assets were inserted.
+        let mut inserted: Vec<String> = Vec::new();
+        for (name, layers) in &other.assets {
+            let target = if !self.assets.contains_key(name) {
+                name.clone()
+            } else {
+                match policy {
+                    MergePolicy::Rename => {
+                        let mut index = 1;
+                        let renamed = loop {
+                            let candidate = format!("{name}_{index}");
+                            if !self.assets.contains_key(&candidate)
+                                && !other.assets.contains_key(&candidate)
+                            {
+                                break candidate;
+                            }
+                            index += 1;
+                        };
+                        renames.insert(name, renamed.clone());
+                        renamed
+                    }
+                    MergePolicy::Overwrite => name.clone(),
+                    MergePolicy::Skip => continue,
+                }
+            };
+            self.assets.insert(target.clone(), layers.clone());
+            inserted.push(target);
+        }
+        // Incoming assets may themselves instance renamed assets.
+        for name in &inserted {
+            if let Some(layers) = self.assets.get_mut(name) {
+                rename_instances(layers, &renames);
+            }
+        }
+        let offset = self.layers.len();
+        let mut layers = other.layers.clone();
+        rename_instances(&mut layers, &renames);
+        for layer in &mut layers {
+            layer.parent = layer.parent.map(|parent| parent + offset);
+            layer.mask_layer = layer.mask_layer.map(|(mode, matte)| (mode, matte + offset));
+        }
+        self.layers.extend(layers);
+        self.frames =
+            self.frames.start.min(other.frames.start)..self.frames.end.max(other.frames.end);
+        self.width = self.width.max(other.width);
+        self.height = self.height.max(other.height);
+    }
+}
+
+fn rename_instances(layers: &mut [Layer], renames: &HashMap<&str, String>) {
+    for layer in layers {
+        match &mut layer.content {
+            Content::Instance { name, .. } => rename(name, renames),
+            Content::Shape(shapes) => rename_brushes(shapes, renames),
+            _ => {}
+        }
+    }
+}
+
+fn rename_brushes(shapes: &mut [Shape], renames: &HashMap<&str, String>) {
+    for shape in shapes {
+        match shape {
+            Shape::Group(group, _) => rename_brushes(group, renames),
+            Shape::Draw(Draw {
+                brush: Brush::Instance(InstanceBrush { name, .. }),
+                ..
+            })
+            | Shape::Draw(Draw {
+                brush:
+                    Brush::Pattern(PatternBrush {
+                        source: PatternSource::Instance { name, .. },
+                        ..
+                    }),
+                ..
+            }) => rename(name, renames),
+            _ => {}
+        }
+    }
+}
+
+fn rename(name: &mut String, renames: &HashMap<&str, String>) {
+    if let Some(renamed) = renames.get(name.as_str()) {
+        name.clone_from(renamed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::instance_names;
+    use alloc::vec;
+
+    #[test]
+    fn merge_renames_conflicting_assets() {
+        let mut a = Composition::default();
+        a.assets.insert("icon".into(), vec![]);
+        a.layers = vec![Layer::instance("icon")];
+        let mut b = Composition::default();
+        b.assets.insert("icon".into(), vec![Layer::default()]);
+        b.layers = vec![
+            Layer {
+                parent: Some(1),
+                ..Layer::instance("icon")
+            },
+            Layer::default(),
+        ];
+        a.merge(&b, MergePolicy::Rename);
+        assert_eq!(a.layers.len(), 3);
+        assert_eq!(instance_names(&a.layers[0]), ["icon"]);
+        assert_eq!(instance_names(&a.layers[1]), ["icon_1"]);
+        assert_eq!(a.layers[1].parent, Some(2));
+        assert_eq!(a.assets["icon"].len(), 0);
+        assert_eq!(a.assets["icon_1"].len(), 1);
+    }
+}
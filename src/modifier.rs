@@ -0,0 +1,442 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+use kurbo::{
+    Affine, CubicBez, ParamCurve, ParamCurveArclen, ParamCurveDeriv, PathEl, PathSeg, Point, Vec2,
+};
+
+use crate::Value;
+
+/// Accuracy of arc length computations when placing zig-zag ridges.
+const ARCLEN_ACCURACY: f64 = 1e-3;
+
+/// Maximum number of ridges per segment of a zig-zag. This bounds the size
+/// of the modified path.
+const MAX_RIDGES: usize = 1000;
+
+/// Number of points sampled along each period of a wave.
+const SAMPLES_PER_WAVELENGTH: usize = 8;
+
+/// Maximum number of points sampled along a segment for a wave.
+const MAX_SAMPLES: usize = 4096;
+
+/// Number of cubic pieces each segment is split into for a twist.
+const WARP_SUBDIVISIONS: usize = 8;
+
+/// Procedural modification of the geometries that precede it in a shape
+/// group, including those of nested groups.
+///
+/// Modifiers operate in the coordinate space of the group containing them.
+/// Successive modifiers are applied in order, each to the paths produced by
+/// the ones before it.
+#[derive(Clone, Debug)]
+pub enum Modifier {
+    /// Displaces the path alternately to each side.
+    ZigZag(ZigZag),
+    /// Moves vertices and control points towards or away from the center.
+    PuckerBloat(PuckerBloat),
+    /// Rotates the path around a center, more strongly near the center.
+    Twist(Twist),
+    /// Displaces the path along a sine wave.
+    Wave(Wave),
+}
+
+/// Parameters of a [`Modifier::ZigZag`].
+#[derive(Clone, Debug)]
+pub struct ZigZag {
+    /// Distance of the ridges from the path.
+    pub size: Value<f64>,
+    /// Number of ridges in each segment of the path, in addition to the
+    /// vertices.
+    pub ridges: Value<f64>,
+    /// True if the ridges are smooth waves rather than sharp corners.
+    pub smooth: bool,
+}
+
+/// Parameters of a [`Modifier::PuckerBloat`].
+#[derive(Clone, Debug)]
+pub struct PuckerBloat {
+    /// Amount in percent. Positive values bloat the path by moving vertices
+    /// towards the center of their contour and control points away from
+    /// it, while negative values pucker it.
+    pub amount: Value<f64>,
+}
+
+/// Parameters of a [`Modifier::Twist`].
+#[derive(Clone, Debug)]
+pub struct Twist {
+    /// Rotation at the center in degrees. The rotation decreases linearly
+    /// with distance from the center, down to zero at the point of the
+    /// contour farthest from it.
+    pub angle: Value<f64>,
+    /// Center of the rotation.
+    pub center: Value<Point>,
+}
+
+/// Parameters of a [`Modifier::Wave`].
+#[derive(Clone, Debug)]
+pub struct Wave {
+    /// Distance of the crests from the path.
+    pub amplitude: Value<f64>,
+    /// Length of a period of the wave along the path.
+    pub wavelength: Value<f64>,
+    /// Phase of the wave at the start of each contour in degrees. Animating
+    /// the phase makes the wave travel along the path.
+    pub phase: Value<f64>,
+}
+
+impl Modifier {
+    /// Evaluates the modifier at the specified frame and appends the
+    /// modified path to `out`.
+    pub fn apply(&self, frame: f64, path: &[PathEl], out: &mut Vec<PathEl>) {
+        match self {
+            Self::ZigZag(zig_zag) => zig_zag.apply(frame, path, out),
+            Self::PuckerBloat(pucker_bloat) => pucker_bloat.apply(frame, path, out),
+            Self::Twist(twist) => twist.apply(frame, path, out),
+            Self::Wave(wave) => wave.apply(frame, path, out),
+        }
+    }
+}
+
+impl ZigZag {
+    fn apply(&self, frame: f64, path: &[PathEl], out: &mut Vec<PathEl>) {
+        let size = self.size.evaluate(frame);
+        let ridges = (self.ridges.evaluate(frame).round().max(0.0) as usize).min(MAX_RIDGES);
+        let mut points: Vec<(Point, Vec2)> = Vec::new();
+        for contour in contours(path) {
+            points.clear();
+            let segments: Vec<PathSeg> = kurbo::segments(contour.iter().copied()).collect();
+            let closed = matches!(contour.last(), Some(PathEl::ClosePath));
+            for (ix, segment) in segments.iter().enumerate() {
+                let length = segment.arclen(ARCLEN_ACCURACY);
+                // The end of the segment is the start of the next one.
+                let last = if ix + 1 == segments.len() && !closed {
+                    ridges + 1
+                } else {
+                    ridges
+                };
+                for k in 0..=last {
+                    let t = if k == 0 {
+                        0.0
+                    } else {
+                        let distance = length * k as f64 / (ridges + 1) as f64;
+                        segment.inv_arclen(distance, ARCLEN_ACCURACY)
+                    };
+                    points.push((segment.eval(t), direction(*segment, t)));
+                }
+            }
+            if points.is_empty() {
+                out.extend_from_slice(contour);
+                continue;
+            }
+            for (ix, (point, direction)) in points.iter_mut().enumerate() {
+                let side = if ix % 2 == 0 { 1.0 } else { -1.0 };
+                *point += normal(*direction) * size * side;
+            }
+            connect(&points, closed, self.smooth, out);
+        }
+    }
+}
+
+impl PuckerBloat {
+    fn apply(&self, frame: f64, path: &[PathEl], out: &mut Vec<PathEl>) {
+        let amount = self.amount.evaluate(frame) / 100.0;
+        for contour in contours(path) {
+            let segments: Vec<CubicBez> = kurbo::segments(contour.iter().copied())
+                .map(|segment| match segment {
+                    PathSeg::Line(line) => CubicBez::new(line.p0, line.p0, line.p1, line.p1),
+                    PathSeg::Quad(quad) => quad.raise(),
+                    PathSeg::Cubic(cubic) => cubic,
+                })
+                .collect();
+            let Some(first) = segments.first() else {
+                out.extend_from_slice(contour);
+                continue;
+            };
+            let center = segments
+                .iter()
+                .fold(Vec2::ZERO, |sum, segment| sum + segment.p0.to_vec2())
+                / segments.len() as f64;
+            let center = center.to_point();
+            // Vertices move towards the center by the amount, while control
+            // points move away from it.
+            let vertex = |point: Point| point.lerp(center, amount);
+            let handle = |point: Point| point.lerp(center, -amount);
+            out.push(PathEl::MoveTo(vertex(first.p0)));
+            for segment in &segments {
+                out.push(PathEl::CurveTo(
+                    handle(segment.p1),
+                    handle(segment.p2),
+                    vertex(segment.p3),
+                ));
+            }
+            if matches!(contour.last(), Some(PathEl::ClosePath)) {
+                out.push(PathEl::ClosePath);
+            }
+        }
+    }
+}
+
+impl Twist {
+    fn apply(&self, frame: f64, path: &[PathEl], out: &mut Vec<PathEl>) {
+        let angle = self.angle.evaluate(frame).to_radians();
+        let center = self.center.evaluate(frame);
+        for contour in contours(path) {
+            let radius = contour
+                .iter()
+                .flat_map(element_points)
+                .map(|point| point.distance(center))
+                .fold(0.0, f64::max);
+            if radius == 0.0 {
+                out.extend_from_slice(contour);
+                continue;
+            }
+            warp(contour, out, |point| {
+                let rotation = angle * (1.0 - point.distance(center) / radius).max(0.0);
+                Affine::rotate_about(rotation, center) * point
+            });
+        }
+    }
+}
+
+impl Wave {
+    fn apply(&self, frame: f64, path: &[PathEl], out: &mut Vec<PathEl>) {
+        let amplitude = self.amplitude.evaluate(frame);
+        let wavelength = self.wavelength.evaluate(frame);
+        let phase = self.phase.evaluate(frame).to_radians();
+        if wavelength <= 0.0 || !wavelength.is_finite() {
+            out.extend_from_slice(path);
+            return;
+        }
+        let step = wavelength / SAMPLES_PER_WAVELENGTH as f64;
+        let mut points: Vec<(Point, Vec2)> = Vec::new();
+        for contour in contours(path) {
+            points.clear();
+            let segments: Vec<PathSeg> = kurbo::segments(contour.iter().copied()).collect();
+            let closed = matches!(contour.last(), Some(PathEl::ClosePath));
+            let mut distance = 0.0;
+            for (ix, segment) in segments.iter().enumerate() {
+                let length = segment.arclen(ARCLEN_ACCURACY);
+                let samples = ((length / step).ceil() as usize).clamp(1, MAX_SAMPLES);
+                let last = if ix + 1 == segments.len() && !closed {
+                    samples
+                } else {
+                    samples - 1
+                };
+                for k in 0..=last {
+                    let along = length * k as f64 / samples as f64;
+                    let t = segment.inv_arclen(along, ARCLEN_ACCURACY);
+                    let direction = direction(*segment, t);
+                    let offset = amplitude
+                        * (core::f64::consts::TAU * (distance + along) / wavelength + phase).sin();
+                    points.push((segment.eval(t) + normal(direction) * offset, direction));
+                }
+                distance += length;
+            }
+            if points.is_empty() {
+                out.extend_from_slice(contour);
+                continue;
+            }
+            connect(&points, closed, true, out);
+        }
+    }
+}
+
+/// Appends a contour through the given points, with lines or with curves
+/// whose handles follow the given directions.
+fn connect(points: &[(Point, Vec2)], closed: bool, smooth: bool, out: &mut Vec<PathEl>) {
+    let count = points.len();
+    out.push(PathEl::MoveTo(points[0].0));
+    let targets = if closed { count + 1 } else { count };
+    for ix in 1..targets {
+        let (from, from_direction) = points[ix - 1];
+        let (to, to_direction) = points[ix % count];
+        if smooth {
+            let handle = (to - from).hypot() / 3.0;
+            out.push(PathEl::CurveTo(
+                from + from_direction * handle,
+                to - to_direction * handle,
+                to,
+            ));
+        } else {
+            out.push(PathEl::LineTo(to));
+        }
+    }
+    if closed {
+        out.push(PathEl::ClosePath);
+    }
+}
+
+/// Appends a contour with each segment split into cubic pieces whose points
+/// are mapped by `f`, approximating a nonlinear warp of the contour.
+fn warp(contour: &[PathEl], out: &mut Vec<PathEl>, f: impl Fn(Point) -> Point) {
+    let mut segments = kurbo::segments(contour.iter().copied()).peekable();
+    let Some(first) = segments.peek() else {
+        out.extend_from_slice(contour);
+        return;
+    };
+    out.push(PathEl::MoveTo(f(first.start())));
+    for segment in segments {
+        let cubic = match segment {
+            PathSeg::Line(line) => CubicBez::new(
+                line.p0,
+                line.p0.lerp(line.p1, 1.0 / 3.0),
+                line.p0.lerp(line.p1, 2.0 / 3.0),
+                line.p1,
+            ),
+            PathSeg::Quad(quad) => quad.raise(),
+            PathSeg::Cubic(cubic) => cubic,
+        };
+        for k in 0..WARP_SUBDIVISIONS {
+            let range =
+                k as f64 / WARP_SUBDIVISIONS as f64..(k + 1) as f64 / WARP_SUBDIVISIONS as f64;
+            let piece = cubic.subsegment(range);
+            out.push(PathEl::CurveTo(f(piece.p1), f(piece.p2), f(piece.p3)));
+        }
+    }
+    if matches!(contour.last(), Some(PathEl::ClosePath)) {
+        out.push(PathEl::ClosePath);
+    }
+}
+
+/// Returns the points of a path element.
+fn element_points(element: &PathEl) -> impl Iterator<Item = Point> {
+    let points = match *element {
+        PathEl::MoveTo(p0) | PathEl::LineTo(p0) => [Some(p0), None, None],
+        PathEl::QuadTo(p0, p1) => [Some(p0), Some(p1), None],
+        PathEl::CurveTo(p0, p1, p2) => [Some(p0), Some(p1), Some(p2)],
+        PathEl::ClosePath => [None, None, None],
+    };
+    points.into_iter().flatten()
+}
+
+/// Returns the normal to the left of a direction in a y-down coordinate
+/// system.
+pub(crate) fn normal(direction: Vec2) -> Vec2 {
+    Vec2::new(-direction.y, direction.x)
+}
+
+/// Splits a path into its contours.
+pub(crate) fn contours(path: &[PathEl]) -> impl Iterator<Item = &[PathEl]> {
+    let mut rest = path;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .iter()
+            .skip(1)
+            .position(|element| matches!(element, PathEl::MoveTo(_)))
+            .map_or(rest.len(), |ix| ix + 1);
+        let (contour, remainder) = rest.split_at(end);
+        rest = remainder;
+        Some(contour)
+    })
+}
+
+/// Returns the unit direction of a segment at a parameter, falling back to
+/// the direction of its chord where the derivative vanishes, or zero for
+/// degenerate segments.
+pub(crate) fn direction(segment: PathSeg, t: f64) -> Vec2 {
+    let derivative = match segment {
+        PathSeg::Line(line) => line.deriv().eval(t),
+        PathSeg::Quad(quad) => quad.deriv().eval(t),
+        PathSeg::Cubic(cubic) => cubic.deriv().eval(t),
+    }
+    .to_vec2();
+    let derivative = if derivative.hypot2() > 1e-12 {
+        derivative
+    } else {
+        segment.end() - segment.start()
+    };
+    if derivative.hypot2() > 0.0 {
+        derivative.normalize()
+    } else {
+        Vec2::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::{Rect, Shape as _};
+
+    use super::*;
+
+    #[test]
+    fn modifiers_displace_square() {
+        let square: Vec<PathEl> = Rect::new(0.0, 0.0, 10.0, 10.0).path_elements(0.1).collect();
+        let zig_zag = Modifier::ZigZag(ZigZag {
+            size: Value::Fixed(1.0),
+            ridges: Value::Fixed(1.0),
+            smooth: false,
+        });
+        let mut out = Vec::new();
+        zig_zag.apply(0.0, &square, &mut out);
+        // Each of the four sides gains a ridge at its midpoint.
+        assert_eq!(out.len(), 10);
+        assert!(matches!(out.last(), Some(PathEl::ClosePath)));
+        let PathEl::LineTo(ridge) = out[1] else {
+            panic!("corner zig-zags should be made of lines");
+        };
+        assert!((ridge - Point::new(5.0, -1.0)).hypot() < 1e-3);
+
+        let bloat = Modifier::PuckerBloat(PuckerBloat {
+            amount: Value::Fixed(50.0),
+        });
+        out.clear();
+        bloat.apply(0.0, &square, &mut out);
+        assert_eq!(out[0], PathEl::MoveTo(Point::new(2.5, 2.5)));
+        assert_eq!(
+            out[1],
+            PathEl::CurveTo(
+                Point::new(-2.5, -2.5),
+                Point::new(12.5, -2.5),
+                Point::new(7.5, 2.5)
+            )
+        );
+    }
+
+    #[test]
+    fn warps_keep_their_anchors() {
+        let line = [
+            PathEl::MoveTo(Point::new(0.0, 0.0)),
+            PathEl::LineTo(Point::new(16.0, 0.0)),
+        ];
+        let wave = Modifier::Wave(Wave {
+            amplitude: Value::Fixed(1.0),
+            wavelength: Value::Fixed(8.0),
+            phase: Value::Fixed(90.0),
+        });
+        let mut out = Vec::new();
+        wave.apply(0.0, &line, &mut out);
+        // Eight samples per wavelength, plus the end of the line.
+        assert_eq!(out.len(), 17);
+        let end = |element: &PathEl| match *element {
+            PathEl::MoveTo(p) | PathEl::CurveTo(_, _, p) => p,
+            _ => panic!("waves should be made of curves"),
+        };
+        assert!((end(&out[0]) - Point::new(0.0, 1.0)).hypot() < 1e-6);
+        assert!((end(&out[2]) - Point::new(2.0, 0.0)).hypot() < 1e-6);
+        assert!((end(&out[16]) - Point::new(16.0, 1.0)).hypot() < 1e-6);
+
+        let square: Vec<PathEl> = Rect::new(0.0, 0.0, 10.0, 10.0).path_elements(0.1).collect();
+        let twist = Modifier::Twist(Twist {
+            angle: Value::Fixed(90.0),
+            center: Value::Fixed(Point::new(5.0, 5.0)),
+        });
+        out.clear();
+        twist.apply(0.0, &square, &mut out);
+        // The corners are farthest from the center and stay in place, while
+        // the midpoints of the sides rotate.
+        assert_eq!(out[0], PathEl::MoveTo(Point::ZERO));
+        let midpoint = end(&out[4]);
+        assert!((midpoint - Point::new(5.0, 0.0)).hypot() > 1.0);
+        assert!((midpoint.distance(Point::new(5.0, 5.0)) - 5.0).abs() < 1e-6);
+        assert!((end(&out[8]) - Point::new(10.0, 0.0)).hypot() < 1e-6);
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use kurbo::{BezPath, CubicBez, ParamCurve, PathEl, Point, QuadBez};
+
+use crate::{Easing, Tween};
+
+/// Morphs between arbitrary paths.
+///
+/// The subpaths of both paths are paired in order, with missing subpaths
+/// growing from or shrinking to a point. Within each pair, segments are
+/// converted to cubic beziers and the longest segments are subdivided
+/// until both have the same number of segments, without changing their
+/// shape. Closed subpaths are rotated to pair up the closest starting
+/// points, which keeps them from twisting.
+///
+/// Matching happens on every call, so morphs between detailed paths
+/// should be evaluated sparingly.
+impl Tween for BezPath {
+    fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
+        let t = easing.evaluate(t);
+        let mut from = contours(self);
+        let mut to = contours(other);
+        pad(&mut from, to.len());
+        pad(&mut to, from.len());
+        let mut path = Self::new();
+        for (mut a, mut b) in from.into_iter().zip(to) {
+            let count = a.segments.len().max(b.segments.len());
+            a.subdivide(count);
+            b.subdivide(count);
+            if a.closed && b.closed {
+                b.align_start(&a);
+            }
+            path.move_to(a.start.lerp(b.start, t));
+            for (sa, sb) in a.segments.iter().zip(&b.segments) {
+                path.curve_to(
+                    sa.p1.lerp(sb.p1, t),
+                    sa.p2.lerp(sb.p2, t),
+                    sa.p3.lerp(sb.p3, t),
+                );
+            }
+            let closed = if t < 0.5 { a.closed } else { b.closed };
+            if closed {
+                path.close_path();
+            }
+        }
+        path
+    }
+}
+
+/// Subpath converted to cubic bezier segments.
+#[derive(Clone, Debug)]
+struct Contour {
+    start: Point,
+    segments: Vec<CubicBez>,
+    closed: bool,
+}
+
+impl Contour {
+    fn point(point: Point) -> Self {
+        Self {
+            start: point,
+            segments: Vec::new(),
+            closed: false,
+        }
+    }
+
+    fn end(&self) -> Point {
+        self.segments
+            .last()
+            .map_or(self.start, |segment| segment.p3)
+    }
+
+    /// Splits the longest segments in half until there are `count`
+    /// segments.
+    fn subdivide(&mut self, count: usize) {
+        if self.segments.is_empty() && count > 0 {
+            self.segments.push(line(self.start, self.start));
+        }
+        while self.segments.len() < count {
+            let Some((ix, _)) = self
+                .segments
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| hull_length(a).total_cmp(&hull_length(b)))
+            else {
+                return;
+            };
+            let (first, second) = self.segments[ix].subdivide();
+            self.segments[ix] = first;
+            self.segments.insert(ix + 1, second);
+        }
+    }
+
+    /// Rotates the segments of this closed contour so that its starting
+    /// point pairs up with that of `target`, minimizing the total distance
+    /// between corresponding points.
+    fn align_start(&mut self, target: &Self) {
+        let count = self.segments.len();
+        if count != target.segments.len() || count < 2 {
+            return;
+        }
+        let cost = |offset: usize| -> f64 {
+            (0..count)
+                .map(|ix| {
+                    let a = target.segments[ix].p0;
+                    let b = self.segments[(ix + offset) % count].p0;
+                    (a - b).hypot2()
+                })
+                .sum()
+        };
+        let offset = (0..count)
+            .min_by(|a, b| cost(*a).total_cmp(&cost(*b)))
+            .unwrap_or(0);
+        self.segments.rotate_left(offset);
+        self.start = self.segments[0].p0;
+    }
+}
+
+/// Splits a path into contours of cubic segments.
+fn contours(path: &BezPath) -> Vec<Contour> {
+    let mut contours: Vec<Contour> = Vec::new();
+    for element in path.elements() {
+        if let PathEl::MoveTo(point) = element {
+            contours.push(Contour::point(*point));
+            continue;
+        }
+        if contours.is_empty() {
+            contours.push(Contour::point(Point::ZERO));
+        }
+        let Some(contour) = contours.last_mut() else {
+            continue;
+        };
+        let p0 = contour.end();
+        match *element {
+            PathEl::MoveTo(_) => {}
+            PathEl::LineTo(p1) => contour.segments.push(line(p0, p1)),
+            PathEl::QuadTo(p1, p2) => contour.segments.push(QuadBez::new(p0, p1, p2).raise()),
+            PathEl::CurveTo(p1, p2, p3) => {
+                contour.segments.push(CubicBez::new(p0, p1, p2, p3));
+            }
+            PathEl::ClosePath => {
+                if p0 != contour.start {
+                    contour.segments.push(line(p0, contour.start));
+                }
+                contour.closed = true;
+            }
+        }
+    }
+    contours
+}
+
+/// Adds contours collapsed to a point until there are `count` contours.
+fn pad(contours: &mut Vec<Contour>, count: usize) {
+    let point = contours.last().map_or(Point::ZERO, |contour| contour.start);
+    contours.resize(count.max(contours.len()), Contour::point(point));
+}
+
+/// Returns a straight line as a cubic bezier.
+fn line(p0: Point, p1: Point) -> CubicBez {
+    CubicBez::new(p0, p0.lerp(p1, 1.0 / 3.0), p0.lerp(p1, 2.0 / 3.0), p1)
+}
+
+/// Returns the length of the control polygon, an upper bound on the arc
+/// length that is cheap to compute.
+fn hull_length(segment: &CubicBez) -> f64 {
+    (segment.p1 - segment.p0).hypot()
+        + (segment.p2 - segment.p1).hypot()
+        + (segment.p3 - segment.p2).hypot()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kurbo::Shape;
+
+    #[test]
+    fn morph_square_into_triangle() {
+        let square = kurbo::Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1);
+        let mut triangle = BezPath::new();
+        triangle.move_to((0.0, 0.0));
+        triangle.line_to((10.0, 0.0));
+        triangle.line_to((5.0, 10.0));
+        triangle.close_path();
+        let start = square.tween(&triangle, 0.0, &Easing::LERP);
+        let end = square.tween(&triangle, 1.0, &Easing::LERP);
+        assert!(
+            (start.area().abs() - 100.0).abs() < 1e-9,
+            "the morph should start as the square"
+        );
+        assert!(
+            (end.area().abs() - 50.0).abs() < 1e-9,
+            "the morph should end as the triangle"
+        );
+        let mid = square.tween(&triangle, 0.5, &Easing::LERP);
+        let area = mid.area().abs();
+        assert!(
+            area > 50.0 && area < 100.0,
+            "the halfway shape should be between the two, got area {area}"
+        );
+        let mut two = triangle.clone();
+        two.extend(square.elements().iter().copied());
+        let grown = triangle.tween(&two, 0.0, &Easing::LERP);
+        assert!(
+            (grown.area().abs() - 50.0).abs() < 1e-9,
+            "missing subpaths should grow from a point"
+        );
+    }
+}
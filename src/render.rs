@@ -3,25 +3,196 @@
 
 #![allow(clippy::shadow_unrelated)]
 
-use crate::{fixed, Composition, Content, Draw, Geometry, GroupTransform, Layer, Shape};
-use kurbo::{Affine, PathEl, Rect};
-use peniko::{Fill, Mix};
-use std::ops::Range;
+use crate::{
+    animated,
+    backend::RenderBackend,
+    batch::{self, LayerTransforms},
+    edit, extrapolation, fixed,
+    keyframes::Keyframed,
+    Brush, CompiledComposition, Composition, CompositionId, Content, Draw, ExternalId, Geometry,
+    Layer, Limits, MaskMode, Modifier, PatternSource, SeekHints, Shape, Transform, ValueRef,
+};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::ops::Range;
+use hashbrown::HashMap;
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+use kurbo::{Affine, BezPath, ParamCurveArclen as _, PathEl, Rect, Shape as _, StrokeOpts};
+use peniko::{Compose, Fill, Mix};
+
+/// Tolerance used when expanding strokes that clip the content of instance
+/// brushes.
+const STROKE_TOLERANCE: f64 = 0.25;
+
+/// Maximum number of copies of a pattern tile drawn for a single geometry.
+/// Patterns that would need more tiles are not drawn.
+const MAX_PATTERN_TILES: usize = 4096;
+
+/// Maximum number of dashes drawn along a single geometry. Strokes that would
+/// need more dashes are drawn solid.
+const MAX_DASHES: usize = 4096;
+
+/// Accuracy of the path length used to count the dashes of a stroke.
+const DASH_LENGTH_ACCURACY: f64 = 0.1;
+
+/// Backend drawn to by a [`Renderer`] when none is specified.
+///
+/// This doesn't depend on the enabled features, so that enabling a feature
+/// in another crate of the dependency graph can't change the type of a
+/// renderer.
+type DefaultBackend = crate::DisplayList;
 
 /// Renders a composition into a scene.
+///
+/// The renderer draws to any [`RenderBackend`], which defaults to a
+/// [`DisplayList`](crate::DisplayList). Name the backend to draw to another,
+/// such as `Renderer::<vello::Scene>::new()` with the `vello` feature.
 #[allow(missing_debug_implementations)]
 #[derive(Default)]
-pub struct Renderer {
-    batch: Batch,
+pub struct Renderer<S = DefaultBackend> {
+    batch: Batch<S>,
     mask_elements: Vec<PathEl>,
+    brushes: BrushCache,
+    paths: PathCache,
+    contents: ContentCache<S>,
+    /// Seek hints for the layer transforms of the most recently rendered
+    /// composition, so that playback finds keyframes in constant time.
+    hints: SeekHints,
+    /// Local transforms of the layers, evaluated in a batch for each set of
+    /// layers.
+    transforms: LayerTransforms,
+    /// Identity of the most recently rendered composition, used to discard
+    /// cached state when a different composition is rendered.
+    composition: Option<CompositionId>,
+    /// Number of in-place edits made when the cached state was retained,
+    /// used to discard it after keyframes are edited.
+    generation: u64,
+    /// Addresses of the assets currently being instanced, used to break
+    /// cycles in compositions that haven't been validated.
+    instances: Vec<usize>,
+    /// True while rendering the matte layer of a luma matte with a backend
+    /// that can't convert layers, so that its brushes are converted to
+    /// coverage by their luminance instead.
+    luma_matte: bool,
+    limits: Limits,
+    frozen: FrozenProperties,
+    direction: LayoutDirection,
+    mirror_policy: MirrorPolicy,
+    /// Addresses of the layers exempted from mirroring by the policy,
+    /// resolved for the composition being rendered.
+    unflipped: Vec<usize>,
+    simplify_tolerance: Option<f64>,
+    pre_layer: Option<LayerCallback<S>>,
+    post_layer: Option<LayerCallback<S>>,
+    external: Option<ExternalCallback<S>>,
 }
 
-impl Renderer {
+impl<S: RenderBackend> Renderer<S> {
     /// Creates a new renderer.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Creates a new renderer that skips any structure exceeding the given
+    /// limits.
+    ///
+    /// Nested instances and shape groups beyond the limits are not drawn,
+    /// and repeaters are clamped to the maximum number of copies. Use
+    /// [`Composition::validate_with_limits`] to report these cases as errors
+    /// instead.
+    pub fn with_limits(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the classes of properties whose animation is disabled.
+    pub fn set_frozen(&mut self, frozen: FrozenProperties) {
+        self.frozen = frozen;
+        self.contents.clear();
+    }
+
+    /// Sets the layout direction for which compositions are rendered.
+    ///
+    /// Right-to-left layouts mirror the composition horizontally, except
+    /// for the content of layers exempted by the mirror policy.
+    pub fn set_layout_direction(&mut self, direction: LayoutDirection) {
+        self.direction = direction;
+        self.contents.clear();
+    }
+
+    /// Sets the policy that controls mirroring for right-to-left layouts.
+    pub fn set_mirror_policy(&mut self, policy: MirrorPolicy) {
+        self.mirror_policy = policy;
+        self.contents.clear();
+    }
+
+    /// Sets the tolerance used to simplify geometry before drawing, or `None`
+    /// to draw geometry as is.
+    ///
+    /// See [`Geometry::evaluate_simplified`].
+    pub fn set_simplify_tolerance(&mut self, tolerance: Option<f64>) {
+        self.simplify_tolerance = tolerance;
+        self.contents.clear();
+    }
+
+    /// Sets a callback that is invoked before each active layer is drawn,
+    /// or `None` to remove it.
+    ///
+    /// Anything the callback appends to the scene is drawn beneath the
+    /// content of the layer, but above the layers below it. This allows
+    /// hosts to inject custom drawing, such as a video frame, at a specific
+    /// point in the layer stack.
+    pub fn set_pre_layer_callback(
+        &mut self,
+        callback: Option<impl FnMut(&LayerInfo<'_>, &mut S) + Send + 'static>,
+    ) {
+        self.pre_layer = callback.map(|callback| Box::new(callback) as LayerCallback<S>);
+    }
+
+    /// Sets a callback that is invoked after each active layer is drawn,
+    /// or `None` to remove it.
+    ///
+    /// Anything the callback appends to the scene is drawn above the
+    /// content of the layer, but beneath the layers above it.
+    pub fn set_post_layer_callback(
+        &mut self,
+        callback: Option<impl FnMut(&LayerInfo<'_>, &mut S) + Send + 'static>,
+    ) {
+        self.post_layer = callback.map(|callback| Box::new(callback) as LayerCallback<S>);
+    }
+
+    /// Sets the callback that draws the content of external layers, or
+    /// `None` to leave them empty.
+    ///
+    /// The callback should append the content to the scene in the
+    /// coordinate space of the layer, using the transform of the given
+    /// layer info. Anything drawn is clipped to the layer bounds and
+    /// composited with the masks and opacity of the layer.
+    pub fn set_external_content_callback(
+        &mut self,
+        callback: Option<impl FnMut(ExternalId, &LayerInfo<'_>, &mut S) + Send + 'static>,
+    ) {
+        self.external = callback.map(|callback| Box::new(callback) as ExternalCallback<S>);
+    }
+
+    /// Discards any state cached across frames.
+    ///
+    /// Cached state is keyed by the [identity](Composition::id) of the
+    /// rendered composition, and discarded after keyframes are edited with
+    /// methods such as [`Animated::insert_keyframe`](crate::Animated::insert_keyframe).
+    /// This must be called after modifying the fields of a composition in
+    /// place by other means, unless it is given a new identity.
+    pub fn clear_cache(&mut self) {
+        self.brushes.clear();
+        self.paths.clear();
+        self.contents.clear();
+        self.hints.clear();
+        self.transforms.clear();
+    }
+
     /// Renders the animation at a given frame to a new scene.
     pub fn render(
         &mut self,
@@ -29,8 +200,8 @@ impl Renderer {
         frame: f64,
         transform: Affine,
         alpha: f64,
-    ) -> vello::Scene {
-        let mut scene = vello::Scene::new();
+    ) -> S {
+        let mut scene = S::default();
         self.append(animation, frame, transform, alpha, &mut scene);
         scene
     }
@@ -42,23 +213,84 @@ impl Renderer {
         frame: f64,
         transform: Affine,
         alpha: f64,
-        scene: &mut vello::Scene,
+        scene: &mut S,
+    ) {
+        self.append_planned(animation, None, frame, transform, alpha, scene);
+    }
+
+    /// Renders a compiled animation at a given frame to a new scene.
+    ///
+    /// See [`Composition::compile`].
+    pub fn render_compiled(
+        &mut self,
+        compiled: &CompiledComposition<'_>,
+        frame: f64,
+        transform: Affine,
+        alpha: f64,
+    ) -> S {
+        let mut scene = S::default();
+        self.append_compiled(compiled, frame, transform, alpha, &mut scene);
+        scene
+    }
+
+    /// Renders and appends a compiled animation at a given frame to the
+    /// provided scene.
+    ///
+    /// This produces the same scene as [`Renderer::append`] with the
+    /// composition that was compiled, without resolving layer parenting and
+    /// instanced assets on every frame.
+    pub fn append_compiled(
+        &mut self,
+        compiled: &CompiledComposition<'_>,
+        frame: f64,
+        transform: Affine,
+        alpha: f64,
+        scene: &mut S,
+    ) {
+        let animation = compiled.composition();
+        self.append_planned(animation, Some(compiled), frame, transform, alpha, scene);
+    }
+
+    fn append_planned(
+        &mut self,
+        animation: &Composition,
+        compiled: Option<&CompiledComposition<'_>>,
+        frame: f64,
+        transform: Affine,
+        alpha: f64,
+        scene: &mut S,
     ) {
         self.batch.clear();
+        let generation = edit::generation();
+        if self.composition != Some(animation.id) || self.generation != generation {
+            self.clear_cache();
+            self.composition = Some(animation.id);
+            self.generation = generation;
+        }
+        self.transforms.begin();
         scene.push_layer(
             Mix::Clip,
             1.0,
             transform,
             &Rect::new(0.0, 0.0, animation.width as _, animation.height as _),
         );
-        for layer in animation.layers.iter().rev() {
+        self.unflipped.clear();
+        let transform = match self.direction {
+            LayoutDirection::LeftToRight => transform,
+            LayoutDirection::RightToLeft => {
+                self.unflipped.extend(self.mirror_policy.resolve(animation));
+                transform * mirror_about(animation.width as f64 * 0.5)
+            }
+        };
+        for (index, layer) in animation.layers.iter().enumerate().rev() {
             if layer.is_mask {
                 continue;
             }
             self.render_layer(
                 animation,
+                compiled.map(|compiled| (compiled, 0)),
                 &animation.layers,
-                layer,
+                index,
                 transform,
                 alpha,
                 frame,
@@ -72,91 +304,436 @@ impl Renderer {
     fn render_layer(
         &mut self,
         animation: &Composition,
+        plan: Plan<'_>,
         layer_set: &[Layer],
-        layer: &Layer,
+        index: usize,
         transform: Affine,
         alpha: f64,
         frame: f64,
-        scene: &mut vello::Scene,
+        scene: &mut S,
     ) {
-        if !layer.frames.contains(&frame) {
+        let Some(layer) = layer_set.get(index) else {
+            return;
+        };
+        if !layer.is_active(frame) {
             return;
         }
         let parent_transform = transform;
-        let transform = self.compute_transform(layer_set, layer, parent_transform, frame);
+        let frames = self.frozen.frames(frame);
+        let mut transform =
+            self.compute_transform(plan, layer_set, index, parent_transform, frames.transform);
+        if let Some(unflipped) = self.unflipped_ancestor(layer_set, index) {
+            // Flip the content back in place so that it reads correctly. The
+            // children of the layer are flipped back along with it, so that
+            // they keep their position relative to its content.
+            let pivot = match &layer_set[unflipped].transform {
+                Transform::Animated(value) => value.anchor.evaluate(frames.transform).x,
+                // The anchor is folded into fixed transforms, which places it
+                // at the origin of the layer.
+                Transform::Fixed(_) => 0.0,
+            };
+            let unflipped_transform = self.compute_transform(
+                plan,
+                layer_set,
+                unflipped,
+                parent_transform,
+                frames.transform,
+            );
+            if unflipped_transform.determinant() != 0.0 {
+                transform = unflipped_transform
+                    * mirror_about(pivot)
+                    * unflipped_transform.inverse()
+                    * transform;
+            }
+        }
+        let info = LayerInfo {
+            composition: animation,
+            layer,
+            transform,
+            bounds: Rect::new(0.0, 0.0, layer.width, layer.height),
+            alpha,
+            frame,
+        };
+        if let Some(pre_layer) = &mut self.pre_layer {
+            pre_layer(&info, scene);
+        }
         let full_rect = Rect::new(0.0, 0.0, animation.width as f64, animation.height as f64);
-        if let Some((mode, mask_index)) = layer.mask_layer {
-            // todo: re-enable masking when it is more understood (and/or if
-            // it's currently supported in vello?) Extra layer to
-            // isolate blending for the mask
+        if let Some((matte, matte_index)) = layer.mask_layer {
+            // Isolates the matte from the layers below, so that the content
+            // composited onto it replaces it.
             scene.push_layer(Mix::Normal, 1.0, parent_transform, &full_rect);
-            if let Some(mask) = layer_set.get(mask_index) {
-                self.render_layer(
-                    animation,
-                    layer_set,
-                    mask,
-                    parent_transform,
+            // Luma mattes are drawn normally and converted to coverage by
+            // the backend, or else drawn with converted brushes.
+            let luminance_layer =
+                matte.is_luma() && scene.push_luminance_layer(1.0, parent_transform, &full_rect);
+            let luma_matte =
+                core::mem::replace(&mut self.luma_matte, matte.is_luma() && !luminance_layer);
+            self.render_layer(
+                animation,
+                plan,
+                layer_set,
+                matte_index,
+                parent_transform,
+                alpha,
+                frame,
+                scene,
+            );
+            self.luma_matte = luma_matte;
+            if luminance_layer {
+                scene.pop_layer();
+            }
+            scene.push_layer(matte.blend_mode(), 1.0, parent_transform, &full_rect);
+        }
+        let alpha = alpha * layer.opacity_at(frames.opacity).fraction();
+        let mask_layers = self.push_masks(
+            layer,
+            transform,
+            parent_transform,
+            &full_rect,
+            frames.mask,
+            scene,
+        );
+        match &layer.content {
+            Content::None => {}
+            Content::Instance { name, .. } => {
+                let frame = layer.instance_frame(frame);
+                let asset = plan.and_then(|(compiled, set)| {
+                    Some((compiled, compiled.set(set)?.instances[index]?))
+                });
+                self.render_instance_layer(
+                    animation, layer, asset, name, transform, alpha, frame, scene,
+                );
+            }
+            Content::Shape(shapes) => {
+                self.render_shape_layer(
+                    animation, layer, shapes, transform, alpha, frame, &frames, scene,
+                );
+            }
+            Content::External(id) => {
+                if let Some(external) = &mut self.external {
+                    scene.push_layer(Mix::Normal, alpha as f32, transform, &info.bounds);
+                    external(*id, &info, scene);
+                    scene.pop_layer();
+                }
+            }
+        }
+        self.pop_layers(layer, mask_layers, scene);
+        if let Some(post_layer) = &mut self.post_layer {
+            post_layer(&info, scene);
+        }
+    }
+
+    /// Renders the shapes of a layer, replaying the content encoded for a
+    /// previous frame if the shapes are known not to have changed since.
+    ///
+    /// Content is encoded in the coordinate space of the layer, so it is
+    /// reused even when the layer transform is animated.
+    #[allow(clippy::too_many_arguments)]
+    fn render_shape_layer(
+        &mut self,
+        animation: &Composition,
+        layer: &Layer,
+        shapes: &[Shape],
+        transform: Affine,
+        alpha: f64,
+        frame: f64,
+        frames: &Frames,
+        scene: &mut S,
+    ) {
+        let key = layer as *const Layer as usize;
+        if self.luma_matte {
+            // Luma mattes are drawn with converted brushes, which aren't
+            // cached.
+            self.render_shapes(animation, shapes, transform, alpha, frames, 1);
+            self.batch.render(scene);
+            self.batch.clear();
+            return;
+        }
+        if let Some(cached) = self.contents.layers.get(&key) {
+            if cached.alpha == alpha && shapes.is_constant_between(cached.frame, frame) {
+                scene.append(&cached.scene, Some(transform));
+                return;
+            }
+        }
+        self.render_shapes(animation, shapes, Affine::IDENTITY, alpha, frames, 1);
+        let mut content = self
+            .contents
+            .layers
+            .remove(&key)
+            .map(|cached| cached.scene)
+            .unwrap_or_default();
+        content.reset();
+        self.batch.render(&mut content);
+        scene.append(&content, Some(transform));
+        // The content of instance brushes has a timeline of its own.
+        let has_instances = self
+            .batch
+            .draws
+            .iter()
+            .any(|draw| matches!(draw.paint, Paint::Content { .. }));
+        self.batch.clear();
+        if !has_instances {
+            self.contents.layers.insert(
+                key,
+                CachedContent {
+                    frame,
                     alpha,
+                    scene: content,
+                },
+            );
+        }
+    }
+
+    /// Renders the asset of an instance layer, replaying the content encoded
+    /// for a previous frame if the whole subtree of the asset is known not
+    /// to have changed since.
+    ///
+    /// As with shape layers, content is encoded in the coordinate space of
+    /// the instance layer. Subtrees are not cached while layer callbacks
+    /// are set, since those must be invoked for each nested layer, nor
+    /// while rendering a luma matte.
+    #[allow(clippy::too_many_arguments)]
+    fn render_instance_layer(
+        &mut self,
+        animation: &Composition,
+        layer: &Layer,
+        asset: Plan<'_>,
+        name: &str,
+        transform: Affine,
+        alpha: f64,
+        frame: f64,
+        scene: &mut S,
+    ) {
+        if self.pre_layer.is_some()
+            || self.post_layer.is_some()
+            || self.external.is_some()
+            || self.luma_matte
+        {
+            self.render_instance(animation, asset, name, transform, alpha, frame, scene);
+            return;
+        }
+        let key = layer as *const Layer as usize;
+        if let Some(cached) = self.contents.layers.get(&key) {
+            // The check follows the instances that are rendered, starting
+            // from the assets currently being instanced.
+            let mut instances = core::mem::take(&mut self.instances);
+            let constant = asset_layers(animation, asset, name).map_or(true, |layers| {
+                layers_constant_between(
+                    animation,
+                    layers,
+                    cached.frame,
                     frame,
-                    scene,
+                    &mut instances,
+                    &self.limits,
+                )
+            });
+            self.instances = instances;
+            if cached.alpha == alpha && constant {
+                scene.append(&cached.scene, Some(transform));
+                return;
+            }
+        }
+        let mut content = self
+            .contents
+            .layers
+            .remove(&key)
+            .map(|cached| cached.scene)
+            .unwrap_or_default();
+        content.reset();
+        let brush_contents = self.contents.brush_contents;
+        self.render_instance(
+            animation,
+            asset,
+            name,
+            Affine::IDENTITY,
+            alpha,
+            frame,
+            &mut content,
+        );
+        scene.append(&content, Some(transform));
+        // The content of instance brushes has a timeline of its own.
+        if self.contents.brush_contents == brush_contents {
+            self.contents.layers.insert(
+                key,
+                CachedContent {
+                    frame,
+                    alpha,
+                    scene: content,
+                },
+            );
+        }
+    }
+
+    /// Renders the layers of the named asset, unless this would exceed the
+    /// instance depth limit or form a cycle.
+    ///
+    /// When the asset has been resolved by compiling the composition, its
+    /// compiled layers are used instead of looking up the name.
+    #[allow(clippy::too_many_arguments)]
+    fn render_instance(
+        &mut self,
+        animation: &Composition,
+        asset: Plan<'_>,
+        name: &str,
+        transform: Affine,
+        alpha: f64,
+        frame: f64,
+        scene: &mut S,
+    ) {
+        let Some(asset_layers) = asset_layers(animation, asset, name) else {
+            return;
+        };
+        let instance = asset_layers.as_ptr() as usize;
+        // See Composition::validate
+        if self.instances.contains(&instance)
+            || self.instances.len() >= self.limits.max_instance_depth
+        {
+            return;
+        }
+        self.instances.push(instance);
+        for (index, asset_layer) in asset_layers.iter().enumerate().rev() {
+            if asset_layer.is_mask || !asset_layer.is_active(frame) {
+                continue;
+            }
+            self.render_layer(
+                animation,
+                asset,
+                asset_layers,
+                index,
+                transform,
+                alpha,
+                frame,
+                scene,
+            );
+        }
+        self.instances.pop();
+    }
+
+    /// Renders the content of an instance brush or pattern into a scene
+    /// retained by the batch and returns its index.
+    ///
+    /// If a tile is given, the content is clipped to it.
+    fn render_brush_content(
+        &mut self,
+        animation: &Composition,
+        name: &str,
+        tile: Option<Rect>,
+        frame: f64,
+    ) -> usize {
+        // The asset is rendered with a batch of its own since the current
+        // batch is still being collected.
+        let batch = core::mem::take(&mut self.batch);
+        self.contents.brush_contents += 1;
+        let mut content = S::default();
+        if let Some(tile) = &tile {
+            content.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, tile);
+        }
+        self.render_instance(
+            animation,
+            None,
+            name,
+            Affine::IDENTITY,
+            1.0,
+            frame,
+            &mut content,
+        );
+        if tile.is_some() {
+            content.pop_layer();
+        }
+        self.batch = batch;
+        self.batch.contents.push(content);
+        self.batch.contents.len() - 1
+    }
+
+    /// Pushes the layers that limit the content of a layer to its masks and
+    /// returns their number.
+    ///
+    /// Masks that only narrow the coverage are pushed as nested clips.
+    /// Otherwise, the coverage of the masks is drawn in order into an
+    /// isolated layer covering `bounds`, onto which the content is
+    /// composited.
+    fn push_masks(
+        &mut self,
+        layer: &Layer,
+        transform: Affine,
+        bounds_transform: Affine,
+        bounds: &Rect,
+        frame: f64,
+        scene: &mut S,
+    ) -> usize {
+        let clips = layer.masks.iter().enumerate().all(|(ix, mask)| {
+            !mask.inverted
+                && (mask.mode == MaskMode::Intersect || (ix == 0 && mask.mode == MaskMode::Add))
+        });
+        if clips {
+            for mask in &layer.masks {
+                let alpha = mask.opacity_at(frame).fraction();
+                self.paths
+                    .evaluate(&mask.geometry, frame, None, &mut self.mask_elements);
+                scene.push_layer(
+                    Mix::Clip,
+                    alpha as f32,
+                    transform,
+                    &self.mask_elements.as_slice(),
                 );
+                self.mask_elements.clear();
             }
-            scene.push_layer(mode, 1.0, parent_transform, &full_rect);
+            return layer.masks.len();
+        }
+        let white = fixed::Brush::Solid(fixed::Color::WHITE);
+        scene.push_layer(Mix::Normal, 1.0, bounds_transform, bounds);
+        if layer
+            .masks
+            .first()
+            .is_some_and(|mask| mask.mode != MaskMode::Add)
+        {
+            scene.fill(Fill::NonZero, bounds_transform, &white, None, bounds);
         }
-        let alpha = alpha * layer.opacity.evaluate(frame) / 100.0;
         for mask in &layer.masks {
-            let alpha = mask.opacity.evaluate(frame) / 100.0;
-            mask.geometry.evaluate(frame, &mut self.mask_elements);
+            let alpha = mask.opacity_at(frame).fraction();
+            self.paths
+                .evaluate(&mask.geometry, frame, None, &mut self.mask_elements);
+            let path = self.mask_elements.as_slice();
             scene.push_layer(
-                Mix::Clip,
+                mask.mode.blend_mode(),
                 alpha as f32,
-                transform,
-                &self.mask_elements.as_slice(),
+                bounds_transform,
+                bounds,
             );
-            self.mask_elements.clear();
-        }
-        match &layer.content {
-            Content::None => {}
-            Content::Instance {
-                name,
-                time_remap: _,
-            } => {
-                // TODO: Use time_remap
-                // let frame = time_remap
-                //     .as_ref()
-                //     .map(|tm| tm.evaluate(frame))
-                //     .unwrap_or(frame);
-                if let Some(asset_layers) = animation.assets.get(name) {
-                    let frame = frame / layer.stretch;
-                    let frame_delta = -layer.start_frame / layer.stretch;
-                    for asset_layer in asset_layers.iter().rev() {
-                        if asset_layer.is_mask {
-                            continue;
-                        }
-                        self.render_layer(
-                            animation,
-                            asset_layers,
-                            asset_layer,
-                            transform,
-                            alpha,
-                            frame + frame_delta,
-                            scene,
-                        );
-                    }
-                }
-            }
-            Content::Shape(shapes) => {
-                self.render_shapes(shapes, transform, alpha, frame);
-                self.batch.render(scene);
-                self.batch.clear();
+            if mask.inverted {
+                scene.fill(Fill::NonZero, bounds_transform, &white, None, bounds);
+                scene.push_layer(Compose::DestOut, 1.0, bounds_transform, bounds);
+                scene.fill(Fill::NonZero, transform, &white, None, &path);
+                scene.pop_layer();
+            } else {
+                scene.fill(Fill::NonZero, transform, &white, None, &path);
             }
+            scene.pop_layer();
+            self.mask_elements.clear();
         }
-        for _ in 0..layer.masks.len() + (layer.mask_layer.is_some() as usize * 2) {
+        scene.push_layer(Compose::SrcIn, 1.0, bounds_transform, bounds);
+        2
+    }
+
+    /// Pops the layers pushed for the masks and matte of a layer.
+    fn pop_layers(&self, layer: &Layer, mask_layers: usize, scene: &mut S) {
+        for _ in 0..mask_layers + (layer.mask_layer.is_some() as usize * 2) {
             scene.pop_layer();
         }
     }
 
-    fn render_shapes(&mut self, shapes: &[Shape], transform: Affine, alpha: f64, frame: f64) {
+    fn render_shapes(
+        &mut self,
+        animation: &Composition,
+        shapes: &[Shape],
+        transform: Affine,
+        alpha: f64,
+        frames: &Frames,
+        depth: usize,
+    ) {
+        if depth > self.limits.max_shape_depth {
+            return;
+        }
         // Keep track of our local top of the geometry stack. Any subsequent
         // draws are bounded by this.
         let geometry_start = self.batch.geometries.len();
@@ -167,90 +744,607 @@ impl Renderer {
             match shape {
                 Shape::Group(shapes, group_transform) => {
                     let (group_transform, group_alpha) =
-                        if let Some(GroupTransform { transform, opacity }) = group_transform {
+                        if let Some(group_transform) = group_transform {
                             (
-                                transform.evaluate(frame).into_owned(),
-                                opacity.evaluate(frame) / 100.0,
+                                group_transform
+                                    .transform
+                                    .evaluate(frames.transform)
+                                    .into_owned(),
+                                group_transform.opacity_at(frames.opacity).fraction(),
                             )
                         } else {
                             (Affine::IDENTITY, 1.0)
                         };
                     self.render_shapes(
+                        animation,
                         shapes,
                         transform * group_transform,
                         alpha * group_alpha,
-                        frame,
+                        frames,
+                        depth + 1,
                     );
                 }
                 Shape::Geometry(geometry) => {
-                    self.batch.push_geometry(geometry, transform, frame);
+                    self.batch.push_geometry(
+                        &mut self.paths,
+                        geometry,
+                        transform,
+                        frames.geometry,
+                        self.simplify_tolerance,
+                    );
                 }
                 Shape::Draw(draw) => {
-                    self.batch.push_draw(draw, alpha, geometry_start, frame);
+                    let paint = match &draw.brush {
+                        Brush::Instance(brush) => Paint::Content {
+                            index: self.render_brush_content(
+                                animation,
+                                &brush.name,
+                                None,
+                                frames.content,
+                            ),
+                            transform: brush.transform.evaluate(frames.transform).into_owned(),
+                            tile: None,
+                        },
+                        Brush::Pattern(pattern) => {
+                            let transform = pattern.transform.evaluate(frames.transform);
+                            match &pattern.source {
+                                PatternSource::Image(_) => Paint::Brush {
+                                    brush: self.brushes.evaluate(&draw.brush, frames.brush),
+                                    transform: Some(transform.into_owned()),
+                                },
+                                PatternSource::Instance { name, tile } => Paint::Content {
+                                    index: self.render_brush_content(
+                                        animation,
+                                        name,
+                                        Some(*tile),
+                                        frames.content,
+                                    ),
+                                    transform: transform.into_owned(),
+                                    tile: Some(*tile),
+                                },
+                            }
+                        }
+                        brush => Paint::Brush {
+                            brush: self.brushes.evaluate(brush, frames.brush),
+                            transform: None,
+                        },
+                    };
+                    let paint = match paint {
+                        Paint::Brush { brush, transform } if self.luma_matte => Paint::Brush {
+                            brush: luminance_to_alpha(brush),
+                            transform,
+                        },
+                        paint => paint,
+                    };
+                    self.batch
+                        .push_draw(draw, paint, alpha, geometry_start, frames);
                 }
                 Shape::Repeater(repeater) => {
-                    let repeater = repeater.evaluate(frame);
+                    let mut repeater = repeater.evaluate(frames.repeater);
+                    if repeater.as_ref().copies > self.limits.max_repeater_copies {
+                        repeater = ValueRef::Owned(fixed::Repeater {
+                            copies: self.limits.max_repeater_copies,
+                            ..repeater.into_owned()
+                        });
+                    }
                     self.batch
                         .repeat(repeater.as_ref(), geometry_start, draw_start);
                 }
+                Shape::Modifier(modifier) => {
+                    self.batch
+                        .modify(modifier, frames.geometry, transform, geometry_start);
+                }
+            }
+        }
+    }
+
+    /// Returns the index of the outermost layer in the chain of parents of
+    /// a layer, including the layer itself, that is exempted from
+    /// mirroring.
+    fn unflipped_ancestor(&self, layer_set: &[Layer], index: usize) -> Option<usize> {
+        if self.unflipped.is_empty() {
+            return None;
+        }
+        let mut unflipped = None;
+        let mut current = Some(index);
+        // Bounded in case of parenting cycles, as in Layer::parented_transform
+        for _ in 0..=layer_set.len() {
+            let Some(layer) = current.and_then(|index| layer_set.get(index)) else {
+                break;
+            };
+            if self.unflipped.contains(&(layer as *const Layer as usize)) {
+                unflipped = current;
             }
+            current = layer.parent;
         }
+        unflipped
     }
 
-    /// Computes the transform for a single layer. This currently chases the
-    /// full transform chain each time. If it becomes a bottleneck, we can
-    /// implement caching.
+    /// Computes the transform for a single layer. The local transforms of
+    /// the whole set of layers are evaluated together in a batch, once per
+    /// frame, and then combined along the chain of parents of the layer.
     fn compute_transform(
-        &self,
+        &mut self,
+        plan: Plan<'_>,
         layer_set: &[Layer],
-        layer: &Layer,
+        index: usize,
         global_transform: Affine,
         frame: f64,
     ) -> Affine {
-        let mut transform = layer.transform.evaluate(frame).into_owned();
-        let mut parent_index = layer.parent;
-        let mut count = 0_usize;
-        while let Some(index) = parent_index {
-            // We don't check for cycles at import time, so this heuristic
-            // prevents infinite loops.
-            if count >= layer_set.len() {
-                break;
-            }
-            if let Some(parent) = layer_set.get(index) {
-                parent_index = parent.parent;
-                transform = parent.transform.evaluate(frame).into_owned() * transform;
-                count += 1;
-            } else {
-                break;
+        let locals = self.transforms.evaluate(layer_set, frame, &mut self.hints);
+        let local = match plan.and_then(|(compiled, set)| compiled.set(set)) {
+            Some(set) => {
+                batch::parented_transform(locals, index, set.parents(index).iter().copied())
             }
+            None => layer_set.get(index).map_or(Affine::IDENTITY, |layer| {
+                batch::parented_transform(locals, index, layer.parents(layer_set))
+            }),
+        };
+        global_transform * local
+    }
+}
+
+/// Compiled composition and the index of the compiled set of layers being
+/// rendered, if the composition was compiled.
+type Plan<'a> = Option<(&'a CompiledComposition<'a>, usize)>;
+
+/// Returns the layers of the asset resolved by compiling the composition,
+/// or else the layers of the named asset.
+fn asset_layers<'a>(
+    animation: &'a Composition,
+    asset: Plan<'a>,
+    name: &str,
+) -> Option<&'a [Layer]> {
+    match asset {
+        Some((compiled, set)) => compiled.set(set).map(|set| set.layers),
+        None => animation.assets.get(name).map(Vec::as_slice),
+    }
+}
+
+/// Returns true if the instanced layers of an asset, including the layers
+/// of any assets they instance, are known to render identically at both
+/// frames.
+///
+/// `instances` holds the addresses of the assets being instanced, as in
+/// [`Renderer::render_instance`]. Instances that would form a cycle or
+/// exceed the depth limit aren't rendered, so they never change.
+fn layers_constant_between(
+    animation: &Composition,
+    layers: &[Layer],
+    a: f64,
+    b: f64,
+    instances: &mut Vec<usize>,
+    limits: &Limits,
+) -> bool {
+    let instance = layers.as_ptr() as usize;
+    // See Composition::validate
+    if instances.contains(&instance) || instances.len() >= limits.max_instance_depth {
+        return true;
+    }
+    instances.push(instance);
+    let constant = layers.iter().all(|layer| {
+        let active = layer.is_active(a);
+        if active != layer.is_active(b) {
+            return false;
         }
-        global_transform * transform
+        !active
+            || layer.is_constant_between(a, b)
+                && match &layer.content {
+                    Content::Instance { name, .. } => {
+                        animation.assets.get(name).map_or(true, |asset| {
+                            layers_constant_between(
+                                animation,
+                                asset,
+                                layer.instance_frame(a),
+                                layer.instance_frame(b),
+                                instances,
+                                limits,
+                            )
+                        })
+                    }
+                    _ => true,
+                }
+    });
+    instances.pop();
+    constant
+}
+
+type LayerCallback<S> = Box<dyn FnMut(&LayerInfo<'_>, &mut S) + Send>;
+type ExternalCallback<S> = Box<dyn FnMut(ExternalId, &LayerInfo<'_>, &mut S) + Send>;
+
+/// Layer being rendered, passed to the callbacks of a [`Renderer`].
+#[derive(Copy, Clone, Debug)]
+pub struct LayerInfo<'a> {
+    /// Composition that contains the layer.
+    pub composition: &'a Composition,
+    /// Layer being rendered. This may belong to the layers of an asset
+    /// rather than the top level layers of the composition.
+    pub layer: &'a Layer,
+    /// Transform from the coordinate space of the layer to the scene,
+    /// including the transforms of parent layers and instances.
+    pub transform: Affine,
+    /// Bounds of the layer in its own coordinate space, from its width and
+    /// height.
+    pub bounds: Rect,
+    /// Opacity inherited from the containing layers, excluding the opacity
+    /// of the layer itself.
+    pub alpha: f64,
+    /// Frame at which the layer is evaluated, after any time remapping of
+    /// the containing instances.
+    pub frame: f64,
+}
+
+/// Direction of the layout in which a composition is displayed.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum LayoutDirection {
+    /// Left-to-right layout. Compositions are rendered as authored.
+    #[default]
+    LeftToRight,
+    /// Right-to-left layout. Compositions are mirrored horizontally.
+    RightToLeft,
+}
+
+/// Controls how compositions are mirrored for right-to-left layouts.
+///
+/// Exempted layers, such as text or logos, keep the original orientation of
+/// their content. They still move to their mirrored positions, but are
+/// flipped back about their anchor point, along with the layers parented to
+/// them.
+#[derive(Clone, Default, Debug)]
+pub struct MirrorPolicy {
+    /// Indices of the top level layers exempted from mirroring.
+    pub unflipped_layers: Vec<usize>,
+    /// Indices of the layers of assets exempted from mirroring, by asset
+    /// name.
+    pub unflipped_asset_layers: HashMap<String, Vec<usize>>,
+}
+
+impl MirrorPolicy {
+    /// Returns the addresses of the exempted layers of a composition.
+    fn resolve<'a>(&'a self, animation: &'a Composition) -> impl Iterator<Item = usize> + 'a {
+        let top_level = self
+            .unflipped_layers
+            .iter()
+            .filter_map(|index| animation.layers.get(*index));
+        let assets = self
+            .unflipped_asset_layers
+            .iter()
+            .filter_map(|(name, indices)| Some((animation.assets.get(name)?, indices)))
+            .flat_map(|(layers, indices)| indices.iter().filter_map(|index| layers.get(*index)));
+        top_level
+            .chain(assets)
+            .map(|layer| layer as *const Layer as usize)
     }
 }
 
+/// Returns the translations of the copies of a pattern tile needed to cover
+/// the given bounds in the coordinate space of the geometry.
+fn tile_offsets(
+    tile: Rect,
+    content_transform: Affine,
+    transform: Affine,
+    bounds: Rect,
+) -> impl Iterator<Item = Affine> {
+    let (width, height) = (tile.width(), tile.height());
+    let range = if width > 0.0 && height > 0.0 && content_transform.determinant() != 0.0 {
+        // Bounds of the geometry in the coordinate space of the pattern.
+        let bounds = (content_transform.inverse() * transform).transform_rect_bbox(bounds);
+        let columns =
+            ((bounds.x0 - tile.x0) / width).floor()..((bounds.x1 - tile.x0) / width).ceil();
+        let rows =
+            ((bounds.y0 - tile.y0) / height).floor()..((bounds.y1 - tile.y0) / height).ceil();
+        if (columns.end - columns.start) * (rows.end - rows.start) <= MAX_PATTERN_TILES as f64 {
+            Some((columns, rows))
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    range.into_iter().flat_map(move |(columns, rows)| {
+        let rows = rows.start as i64..rows.end as i64;
+        (columns.start as i64..columns.end as i64).flat_map(move |column| {
+            rows.clone()
+                .map(move |row| Affine::translate((column as f64 * width, row as f64 * height)))
+        })
+    })
+}
+
+/// Returns a transform that mirrors horizontally about `x`.
+fn mirror_about(x: f64) -> Affine {
+    Affine::new([-1.0, 0.0, 0.0, 1.0, 2.0 * x, 0.0])
+}
+
+/// Classes of animated properties that the renderer can freeze.
+///
+/// Frozen properties are evaluated at their first keyframe regardless of the
+/// frame being rendered. This allows disabling costly animation on low-end
+/// devices without modifying the composition.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct FrozenProperties {
+    /// Layer and group transforms.
+    pub transform: bool,
+    /// Layer, group and draw opacity.
+    pub opacity: bool,
+    /// Shape geometry.
+    pub geometry: bool,
+    /// Fill and stroke brushes, including gradients.
+    pub brush: bool,
+    /// Stroke widths.
+    pub stroke: bool,
+    /// Repeater parameters.
+    pub repeater: bool,
+    /// Layer mask geometry and opacity.
+    pub mask: bool,
+}
+
+impl FrozenProperties {
+    /// Returns the frames at which each class of property is evaluated.
+    fn frames(self, frame: f64) -> Frames {
+        // Evaluating at negative infinity yields the first keyframe, as it
+        // is never extrapolated.
+        let freeze = |frozen: bool| if frozen { f64::NEG_INFINITY } else { frame };
+        Frames {
+            transform: freeze(self.transform),
+            opacity: freeze(self.opacity),
+            geometry: freeze(self.geometry),
+            brush: freeze(self.brush),
+            stroke: freeze(self.stroke),
+            repeater: freeze(self.repeater),
+            mask: freeze(self.mask),
+            content: frame,
+        }
+    }
+}
+
+/// Frames at which each class of property is evaluated.
+struct Frames {
+    transform: f64,
+    opacity: f64,
+    geometry: f64,
+    brush: f64,
+    stroke: f64,
+    repeater: f64,
+    mask: f64,
+    /// Frame at which the content of instance brushes is evaluated. This is
+    /// never frozen.
+    content: f64,
+}
+
+/// Source of the color for a draw operation.
+#[derive(Clone, Debug)]
+enum Paint {
+    Brush {
+        brush: fixed::Brush,
+        transform: Option<Affine>,
+    },
+    /// Content of an instance brush or pattern.
+    Content {
+        /// Index into `Batch::contents`
+        index: usize,
+        transform: Affine,
+        /// Rectangle at which the content repeats, if it is a pattern.
+        tile: Option<Rect>,
+    },
+}
+
 #[derive(Clone, Debug)]
 struct DrawData {
     stroke: Option<fixed::Stroke>,
-    brush: fixed::Brush,
+    /// Variation of the stroke width, drawn by filling the outline of the
+    /// stroke instead of stroking it.
+    taper: Option<fixed::Taper>,
+    paint: Paint,
     alpha: f64,
     /// Range into `ShapeBatch::geometries`
     geometry: Range<usize>,
 }
 
 impl DrawData {
-    fn new(draw: &Draw, alpha: f64, geometry: Range<usize>, frame: f64) -> Self {
+    fn new(draw: &Draw, paint: Paint, alpha: f64, geometry: Range<usize>, frames: &Frames) -> Self {
         Self {
-            stroke: draw
+            stroke: draw.stroke.as_ref().map(|stroke| {
+                let mut stroke = stroke.evaluate(frames.stroke).into_owned();
+                // Dashing doesn't terminate for these patterns, so they are
+                // drawn as solid strokes.
+                let total: f64 = stroke.dash_pattern.iter().sum();
+                if stroke.dash_pattern.iter().any(|dash| *dash < 0.0)
+                    || total <= 0.0
+                    || !total.is_finite()
+                    || !stroke.dash_offset.is_finite()
+                {
+                    stroke.dash_pattern.clear();
+                } else {
+                    stroke.dash_offset = extrapolation::wrap(stroke.dash_offset, total);
+                }
+                stroke
+            }),
+            taper: draw
                 .stroke
                 .as_ref()
-                .map(|stroke| stroke.evaluate(frame).into_owned()),
-            brush: draw.brush.evaluate(1.0, frame).into_owned(),
-            alpha: alpha * draw.opacity.evaluate(frame) / 100.0,
+                .and_then(|stroke| stroke.taper(frames.stroke)),
+            paint,
+            alpha: alpha * draw.opacity_at(frames.opacity).fraction(),
             geometry,
         }
     }
 }
 
+/// Returns whether dashing the path with the pattern of the stroke draws at
+/// most [`MAX_DASHES`] dashes.
+fn dashes_fit(stroke: &kurbo::Stroke, path: &[PathEl]) -> bool {
+    let total: f64 = stroke.dash_pattern.iter().sum();
+    let length: f64 = kurbo::segments(path.iter().copied())
+        .map(|segment| segment.arclen(DASH_LENGTH_ACCURACY))
+        .sum();
+    // One more period is started by the offset.
+    let periods = (length / total).ceil() + 1.0;
+    periods * stroke.dash_pattern.len() as f64 <= MAX_DASHES as f64
+}
+
+/// Converts a brush to white with an opacity proportional to its luminance,
+/// for drawing the matte layer of a luma matte with a backend that can't
+/// convert layers.
+///
+/// Image brushes are returned unchanged.
+fn luminance_to_alpha(brush: fixed::Brush) -> fixed::Brush {
+    let convert = |color: fixed::Color| {
+        let luminance = 0.2126 * color.r as f64 + 0.7152 * color.g as f64 + 0.0722 * color.b as f64;
+        let alpha = (luminance / 255.0 * color.a as f64).round();
+        fixed::Color::rgba8(255, 255, 255, alpha as u8)
+    };
+    match brush {
+        fixed::Brush::Solid(color) => fixed::Brush::Solid(convert(color)),
+        fixed::Brush::Gradient(mut gradient) => {
+            for stop in gradient.stops.iter_mut() {
+                stop.color = convert(stop.color);
+            }
+            fixed::Brush::Gradient(gradient)
+        }
+        brush => brush,
+    }
+}
+
+/// Animated gradient brushes retained across frames.
+///
+/// Entries are keyed by the address of the animated gradient within the
+/// composition being rendered, and reused for as long as its start point,
+/// end point and stops are known not to change. The cache is cleared when a
+/// composition with a different identity is rendered or keyframes have been
+/// edited in place, so entries never outlive the gradients they were
+/// evaluated from.
+#[derive(Default)]
+struct BrushCache {
+    gradients: HashMap<usize, CachedBrush>,
+}
+
+struct CachedBrush {
+    /// Frame at which the brush was evaluated.
+    frame: f64,
+    brush: fixed::Brush,
+}
+
+impl BrushCache {
+    fn evaluate(&mut self, brush: &Brush, frame: f64) -> fixed::Brush {
+        let Brush::Animated(animated::Brush::Gradient(gradient)) = brush else {
+            return brush.evaluate(1.0, frame).into_owned();
+        };
+        let key = &**gradient as *const animated::Gradient as usize;
+        if let Some(cached) = self.gradients.get(&key) {
+            if gradient.is_constant_between(cached.frame, frame) {
+                return cached.brush.clone();
+            }
+        }
+        let evaluated = gradient.evaluate(frame);
+        self.gradients.insert(
+            key,
+            CachedBrush {
+                frame,
+                brush: evaluated.clone(),
+            },
+        );
+        evaluated
+    }
+
+    fn clear(&mut self) {
+        self.gradients.clear();
+    }
+}
+
+/// Encoded content of shape layers retained across frames.
+///
+/// Entries are keyed by the address of the layer within the composition
+/// being rendered, and replayed for as long as its content is known not to
+/// change and it is drawn with the same opacity. As with [`BrushCache`], the
+/// cache is cleared when a composition with a different identity is
+/// rendered or keyframes have been edited in place.
+#[derive(Default)]
+struct ContentCache<S> {
+    layers: HashMap<usize, CachedContent<S>>,
+    /// Number of instance brushes rendered, used to detect subtrees that
+    /// contain them.
+    brush_contents: usize,
+}
+
+struct CachedContent<S> {
+    /// Frame at which the content was encoded.
+    frame: f64,
+    /// Opacity with which the content was encoded.
+    alpha: f64,
+    scene: S,
+}
+
+impl<S> ContentCache<S> {
+    fn clear(&mut self) {
+        self.layers.clear();
+    }
+}
+
+/// Paths of animated geometry retained across frames.
+///
+/// Entries are keyed by the address of the geometry within the composition
+/// being rendered, and reused for as long as the geometry is known not to
+/// change, which avoids rebuilding and simplifying paths that are still
+/// between keyframes. Each entry keeps its buffer when the geometry changes,
+/// so steady playback doesn't allocate. As with [`BrushCache`], the cache is
+/// cleared when a composition with a different identity is rendered or
+/// keyframes have been edited in place.
+#[derive(Default)]
+struct PathCache {
+    paths: HashMap<usize, CachedPath>,
+}
+
+struct CachedPath {
+    /// Frame at which the path was evaluated.
+    frame: f64,
+    tolerance: Option<f64>,
+    elements: Vec<PathEl>,
+}
+
+impl PathCache {
+    /// Appends the path of the geometry at the given frame to `path`,
+    /// simplified if a tolerance is given.
+    fn evaluate(
+        &mut self,
+        geometry: &Geometry,
+        frame: f64,
+        tolerance: Option<f64>,
+        path: &mut Vec<PathEl>,
+    ) {
+        if let Geometry::Fixed(elements) = geometry {
+            if tolerance.is_none() {
+                path.extend_from_slice(elements);
+                return;
+            }
+        }
+        let key = geometry as *const Geometry as usize;
+        let cached = self.paths.entry(key).or_insert_with(|| CachedPath {
+            frame: f64::NAN,
+            tolerance,
+            elements: Vec::new(),
+        });
+        if cached.frame.is_nan()
+            || cached.tolerance != tolerance
+            || !geometry.is_constant_between(cached.frame, frame)
+        {
+            cached.elements.clear();
+            match tolerance {
+                Some(tolerance) => {
+                    geometry.evaluate_simplified(frame, tolerance, &mut cached.elements);
+                }
+                None => geometry.evaluate(frame, &mut cached.elements),
+            }
+            cached.frame = frame;
+            cached.tolerance = tolerance;
+        }
+        path.extend_from_slice(&cached.elements);
+    }
+
+    fn clear(&mut self) {
+        self.paths.clear();
+    }
+}
+
 #[derive(Clone, Debug)]
 struct GeometryData {
     /// Range into `ShapeBatch::elements`
@@ -259,19 +1353,32 @@ struct GeometryData {
 }
 
 #[derive(Default)]
-struct Batch {
+struct Batch<S> {
     elements: Vec<PathEl>,
     geometries: Vec<GeometryData>,
     draws: Vec<DrawData>,
     repeat_geometries: Vec<GeometryData>,
     repeat_draws: Vec<DrawData>,
+    /// Path elements of a geometry being modified.
+    modify_elements: Vec<PathEl>,
+    /// Rendered content of instance brushes.
+    contents: Vec<S>,
     /// Length of geometries at time of most recent draw. This is
     /// used to prevent merging into already used geometries.
     drawn_geometry: usize,
 }
 
-impl Batch {
-    fn push_geometry(&mut self, geometry: &Geometry, transform: Affine, frame: f64) {
+impl<S: RenderBackend> Batch<S> {
+    fn push_geometry(
+        &mut self,
+        paths: &mut PathCache,
+        geometry: &Geometry,
+        transform: Affine,
+        frame: f64,
+        tolerance: Option<f64>,
+    ) {
+        let mut evaluate =
+            |elements: &mut Vec<PathEl>| paths.evaluate(geometry, frame, tolerance, elements);
         // Merge with the previous geometry if possible. There are two
         // conditions:
         // 1. The previous geometry has not yet been referenced by a draw
@@ -279,11 +1386,11 @@ impl Batch {
         if self.drawn_geometry < self.geometries.len()
             && self.geometries.last().map(|last| last.transform) == Some(transform)
         {
-            geometry.evaluate(frame, &mut self.elements);
+            evaluate(&mut self.elements);
             self.geometries.last_mut().unwrap().elements.end = self.elements.len();
         } else {
             let start = self.elements.len();
-            geometry.evaluate(frame, &mut self.elements);
+            evaluate(&mut self.elements);
             let end = self.elements.len();
             self.geometries.push(GeometryData {
                 elements: start..end,
@@ -292,12 +1399,20 @@ impl Batch {
         }
     }
 
-    fn push_draw(&mut self, draw: &Draw, alpha: f64, geometry_start: usize, frame: f64) {
+    fn push_draw(
+        &mut self,
+        draw: &Draw,
+        paint: Paint,
+        alpha: f64,
+        geometry_start: usize,
+        frames: &Frames,
+    ) {
         self.draws.push(DrawData::new(
             draw,
+            paint,
             alpha,
             geometry_start..self.geometries.len(),
-            frame,
+            frames,
         ));
         self.drawn_geometry = self.geometries.len();
     }
@@ -348,25 +1463,148 @@ impl Batch {
         self.drawn_geometry = self.geometries.len();
     }
 
-    fn render(&self, scene: &mut vello::Scene) {
+    /// Replaces the geometries following the given start with their
+    /// modified paths, in the coordinate space given by `transform`.
+    fn modify(
+        &mut self,
+        modifier: &Modifier,
+        frame: f64,
+        transform: Affine,
+        geometry_start: usize,
+    ) {
+        if transform.determinant() == 0.0 {
+            return;
+        }
+        let inverse = transform.inverse();
+        for geometry in &mut self.geometries[geometry_start..] {
+            let relative = inverse * geometry.transform;
+            self.modify_elements.clear();
+            self.modify_elements.extend(
+                self.elements[geometry.elements.clone()]
+                    .iter()
+                    .map(|element| relative * *element),
+            );
+            let start = self.elements.len();
+            modifier.apply(frame, &self.modify_elements, &mut self.elements);
+            geometry.elements = start..self.elements.len();
+            geometry.transform = transform;
+        }
+        // Prevent merging into the modified geometries.
+        self.drawn_geometry = self.geometries.len();
+    }
+
+    fn render(&self, scene: &mut S) {
         // Process all draws in reverse
         for draw in self.draws.iter().rev() {
-            // Some nastiness to avoid cloning the brush if unnecessary
-            let modified_brush = if draw.alpha != 1.0 {
-                Some(draw.brush.clone().multiply_alpha(draw.alpha as f32))
+            match &draw.paint {
+                Paint::Brush { brush, transform } => {
+                    self.render_brush(draw, brush, *transform, scene);
+                }
+                Paint::Content {
+                    index,
+                    transform,
+                    tile,
+                } => {
+                    let content = &self.contents[*index];
+                    self.render_content(draw, content, *transform, *tile, scene);
+                }
+            }
+        }
+    }
+
+    fn render_brush(
+        &self,
+        draw: &DrawData,
+        brush: &fixed::Brush,
+        brush_transform: Option<Affine>,
+        scene: &mut S,
+    ) {
+        // Some nastiness to avoid cloning the brush if unnecessary
+        let modified_brush = if draw.alpha != 1.0 {
+            Some(brush.clone().multiply_alpha(draw.alpha as f32))
+        } else {
+            None
+        };
+        let brush = modified_brush.as_ref().unwrap_or(brush);
+        // Dashes are applied here rather than by the backend, so that all
+        // backends draw them identically.
+        let dashed = draw
+            .stroke
+            .as_ref()
+            .filter(|stroke| !stroke.dash_pattern.is_empty())
+            .map(|stroke| stroke.clone().with_dashes(0.0, [0.0; 0]));
+        for geometry in self.geometries[draw.geometry.clone()].iter() {
+            let path = &self.elements[geometry.elements.clone()];
+            let transform = geometry.transform;
+            if let (Some(stroke), Some(taper)) = (draw.stroke.as_ref(), draw.taper.as_ref()) {
+                // Dashes are not applied to tapered strokes.
+                let mut outline = Vec::new();
+                taper.outline(stroke.width, path, &mut outline);
+                let outline = BezPath::from_vec(outline);
+                scene.fill(Fill::NonZero, transform, brush, brush_transform, &outline);
+            } else if let (Some(stroke), Some(solid)) = (draw.stroke.as_ref(), dashed.as_ref()) {
+                if dashes_fit(stroke, path) {
+                    let path: BezPath = kurbo::dash(
+                        path.iter().copied(),
+                        stroke.dash_offset,
+                        &stroke.dash_pattern,
+                    )
+                    .collect();
+                    scene.stroke(solid, transform, brush, brush_transform, &path);
+                } else {
+                    scene.stroke(solid, transform, brush, brush_transform, &path);
+                }
+            } else if let Some(stroke) = draw.stroke.as_ref() {
+                scene.stroke(stroke, transform, brush, brush_transform, &path);
             } else {
-                None
+                scene.fill(Fill::NonZero, transform, brush, brush_transform, &path);
+            }
+        }
+    }
+
+    /// Draws rendered content clipped to the filled or stroked geometry,
+    /// repeating it to cover the geometry if a tile is given.
+    fn render_content(
+        &self,
+        draw: &DrawData,
+        content: &S,
+        content_transform: Affine,
+        tile: Option<Rect>,
+        scene: &mut S,
+    ) {
+        for geometry in self.geometries[draw.geometry.clone()].iter() {
+            let path = &self.elements[geometry.elements.clone()];
+            let transform = geometry.transform;
+            let bounds = if let Some(stroke) = draw.stroke.as_ref() {
+                let outline = match &draw.taper {
+                    Some(taper) => {
+                        let mut outline = Vec::new();
+                        taper.outline(stroke.width, path, &mut outline);
+                        BezPath::from_vec(outline)
+                    }
+                    None => kurbo::stroke(
+                        path.iter().copied(),
+                        stroke,
+                        &StrokeOpts::default(),
+                        STROKE_TOLERANCE,
+                    ),
+                };
+                scene.push_layer(Mix::Clip, draw.alpha as f32, transform, &outline);
+                outline.bounding_box()
+            } else {
+                scene.push_layer(Mix::Clip, draw.alpha as f32, transform, &path);
+                path.bounding_box()
             };
-            let brush = modified_brush.as_ref().unwrap_or(&draw.brush);
-            for geometry in self.geometries[draw.geometry.clone()].iter() {
-                let path = &self.elements[geometry.elements.clone()];
-                let transform = geometry.transform;
-                if let Some(stroke) = draw.stroke.as_ref() {
-                    scene.stroke(stroke, transform, brush, None, &path);
-                } else {
-                    scene.fill(Fill::NonZero, transform, brush, None, &path);
+            let content_transform = transform * content_transform;
+            match tile {
+                Some(tile) => {
+                    for offset in tile_offsets(tile, content_transform, transform, bounds) {
+                        scene.append(content, Some(content_transform * offset));
+                    }
                 }
+                None => scene.append(content, Some(content_transform)),
             }
+            scene.pop_layer();
         }
     }
 
@@ -376,6 +1614,645 @@ impl Batch {
         self.draws.clear();
         self.repeat_geometries.clear();
         self.repeat_draws.clear();
+        self.contents.clear();
         self.drawn_geometry = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use peniko::{BlendMode, Compose};
+
+    use super::*;
+    use crate::{composition::square_layer, DisplayCommand, DisplayList, Mask, Matte, Value};
+
+    #[test]
+    fn new_identity_discards_cached_brushes() {
+        let stops = |color: fixed::Color| {
+            crate::ColorStops::Fixed(
+                [(0.0, color), (1.0, color)]
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            )
+        };
+        let gradient = animated::Gradient {
+            is_radial: false,
+            start_point: Value::Fixed(kurbo::Point::new(0.0, 0.0)),
+            end_point: Value::Fixed(kurbo::Point::new(10.0, 0.0)),
+            stops: stops(fixed::Color::BLACK),
+            opacity_stops: None,
+            highlight_length: Value::Fixed(0.0),
+            highlight_angle: Value::Fixed(0.0),
+            // Animated, but constant over the rendered frames.
+            rotation: Value::Animated(crate::Animated {
+                times: vec![crate::Time::new(20.0), crate::Time::new(30.0)],
+                values: vec![0.0, 90.0],
+                extrapolation: Default::default(),
+                interpolation: Default::default(),
+            }),
+            scale: Value::Fixed(100.0),
+        };
+        let mut layer = square_layer(fixed::Color::BLACK);
+        let Content::Shape(shapes) = &mut layer.content else {
+            unreachable!();
+        };
+        shapes[1] = Shape::Draw(Draw {
+            stroke: None,
+            brush: Brush::Animated(animated::Brush::Gradient(Box::new(gradient))),
+            opacity: Value::Fixed(100.0),
+        });
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![layer],
+            ..Default::default()
+        };
+        let first_stop = |list: &DisplayList| match &list.commands[1] {
+            DisplayCommand::Fill {
+                brush: fixed::Brush::Gradient(gradient),
+                ..
+            } => gradient.stops[0].color,
+            command => panic!("expected a gradient fill, got {command:?}"),
+        };
+        let mut renderer = Renderer::<DisplayList>::new();
+        fn gradient_mut(composition: &mut Composition) -> &mut animated::Gradient {
+            let Content::Shape(shapes) = &mut composition.layers[0].content else {
+                unreachable!();
+            };
+            let Shape::Draw(Draw {
+                brush: Brush::Animated(animated::Brush::Gradient(gradient)),
+                ..
+            }) = &mut shapes[1]
+            else {
+                unreachable!();
+            };
+            gradient
+        }
+        let list = renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0);
+        assert_eq!(first_stop(&list), fixed::Color::BLACK);
+
+        gradient_mut(&mut composition).stops = stops(fixed::Color::WHITE);
+        composition.id = CompositionId::new();
+        let list = renderer.render(&composition, 1.0, Affine::IDENTITY, 1.0);
+        assert_eq!(first_stop(&list), fixed::Color::WHITE);
+
+        // Keyframes edited in place are picked up under the same identity.
+        let kind = |list: &DisplayList| match &list.commands[1] {
+            DisplayCommand::Fill {
+                brush: fixed::Brush::Gradient(gradient),
+                ..
+            } => gradient.kind,
+            command => panic!("expected a gradient fill, got {command:?}"),
+        };
+        let unrotated = kind(&list);
+        let Value::Animated(rotation) = &mut gradient_mut(&mut composition).rotation else {
+            unreachable!();
+        };
+        rotation.insert_keyframe(1.0, 90.0).unwrap();
+        let list = renderer.render(&composition, 1.0, Affine::IDENTITY, 1.0);
+        assert_ne!(kind(&list), unrotated);
+    }
+
+    #[test]
+    fn new_identity_discards_cached_paths() {
+        let rect = |width: f64| {
+            Geometry::Rect(animated::Rect {
+                is_ccw: false,
+                position: Value::Fixed(kurbo::Point::new(width * 0.5, 5.0)),
+                // Animated, but constant over the rendered frames.
+                size: Value::Animated(crate::Animated {
+                    times: vec![crate::Time::new(20.0), crate::Time::new(30.0)],
+                    values: vec![kurbo::Size::new(width, 10.0), kurbo::Size::new(1.0, 1.0)],
+                    extrapolation: Default::default(),
+                    interpolation: Default::default(),
+                }),
+                corner_radius: Value::Fixed(0.0),
+            })
+        };
+        let mut layer = square_layer(fixed::Color::BLACK);
+        let Content::Shape(shapes) = &mut layer.content else {
+            unreachable!();
+        };
+        shapes[0] = Shape::Geometry(rect(10.0));
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![layer],
+            ..Default::default()
+        };
+        let width = |list: &DisplayList| match &list.commands[1] {
+            DisplayCommand::Fill { path, .. } => path.bounding_box().width(),
+            command => panic!("expected a fill, got {command:?}"),
+        };
+        let mut renderer = Renderer::<DisplayList>::new();
+        let list = renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0);
+        assert_eq!(width(&list), 10.0);
+
+        let Content::Shape(shapes) = &mut composition.layers[0].content else {
+            unreachable!();
+        };
+        shapes[0] = Shape::Geometry(rect(5.0));
+        composition.id = CompositionId::new();
+        let list = renderer.render(&composition, 1.0, Affine::IDENTITY, 1.0);
+        assert_eq!(width(&list), 5.0);
+
+        // Keyframes edited in place are picked up under the same identity.
+        let Content::Shape(shapes) = &mut composition.layers[0].content else {
+            unreachable!();
+        };
+        let Shape::Geometry(Geometry::Rect(animated::Rect {
+            size: Value::Animated(size),
+            ..
+        })) = &mut shapes[0]
+        else {
+            unreachable!();
+        };
+        size.insert_keyframe(1.0, kurbo::Size::new(3.0, 10.0))
+            .unwrap();
+        let list = renderer.render(&composition, 1.0, Affine::IDENTITY, 1.0);
+        assert_eq!(width(&list), 3.0);
+    }
+
+    #[test]
+    fn new_identity_discards_cached_content() {
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![square_layer(fixed::Color::BLACK)],
+            ..Default::default()
+        };
+        let brush = |list: &DisplayList| match &list.commands[1] {
+            DisplayCommand::Fill { brush, .. } => brush.clone(),
+            command => panic!("expected a fill, got {command:?}"),
+        };
+        let mut renderer = Renderer::<DisplayList>::new();
+        let list = renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0);
+        assert_eq!(brush(&list), fixed::Color::BLACK.into());
+
+        composition.layers[0] = square_layer(fixed::Color::WHITE);
+        composition.id = CompositionId::new();
+        let list = renderer.render(&composition, 1.0, Affine::IDENTITY, 1.0);
+        assert_eq!(brush(&list), fixed::Color::WHITE.into());
+
+        // Keyframes edited in place are picked up under the same identity.
+        let Content::Shape(shapes) = &mut composition.layers[0].content else {
+            unreachable!();
+        };
+        let Shape::Draw(draw) = &mut shapes[1] else {
+            unreachable!();
+        };
+        // Animated, but constant over the rendered frames.
+        draw.opacity = Value::Animated(crate::Animated {
+            times: vec![crate::Time::new(20.0)],
+            values: vec![100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        });
+        composition.id = CompositionId::new();
+        let list = renderer.render(&composition, 1.0, Affine::IDENTITY, 1.0);
+        assert_eq!(brush(&list), fixed::Color::WHITE.into());
+        let Content::Shape(shapes) = &mut composition.layers[0].content else {
+            unreachable!();
+        };
+        let Shape::Draw(Draw {
+            opacity: Value::Animated(opacity),
+            ..
+        }) = &mut shapes[1]
+        else {
+            unreachable!();
+        };
+        opacity.insert_keyframe(1.0, 50.0).unwrap();
+        let list = renderer.render(&composition, 1.0, Affine::IDENTITY, 1.0);
+        assert_eq!(
+            brush(&list),
+            fixed::Brush::from(fixed::Color::WHITE).multiply_alpha(0.5)
+        );
+    }
+
+    #[test]
+    fn cyclic_instance_content_is_cached_per_identity() {
+        let instance = || Layer {
+            frames: 0.0..10.0,
+            stretch: 1.0,
+            opacity: Value::Fixed(100.0),
+            ..Layer::instance("logo")
+        };
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![instance()],
+            ..Default::default()
+        };
+        // The asset instances itself, which is only rendered once.
+        composition.assets.insert(
+            "logo".into(),
+            vec![instance(), square_layer(fixed::Color::BLACK)],
+        );
+        let brushes = |list: &DisplayList| {
+            list.commands
+                .iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::Fill { brush, .. } => Some(brush.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut renderer = Renderer::<DisplayList>::new();
+        let black: fixed::Brush = fixed::Color::BLACK.into();
+        for frame in [0.0, 1.0] {
+            let list = renderer.render(&composition, frame, Affine::IDENTITY, 1.0);
+            assert_eq!(
+                brushes(&list),
+                core::slice::from_ref(&black),
+                "frame {frame}"
+            );
+        }
+
+        composition.assets.get_mut("logo").unwrap()[1] = square_layer(fixed::Color::WHITE);
+        composition.id = CompositionId::new();
+        let list = renderer.render(&composition, 2.0, Affine::IDENTITY, 1.0);
+        assert_eq!(brushes(&list), [fixed::Color::WHITE.into()]);
+    }
+
+    #[test]
+    fn frozen_properties_stay_fixed() {
+        let ramp = |to: f64, extrapolation| crate::Animated {
+            times: vec![crate::Time::new(0.0), crate::Time::new(10.0)],
+            values: vec![0.0, to],
+            extrapolation,
+            interpolation: Default::default(),
+        };
+        let ramps = |extrapolation| {
+            let transform = animated::Transform {
+                anchor: Value::Fixed(kurbo::Point::ZERO),
+                position: animated::Position::SplitValues((
+                    Value::Animated(ramp(10.0, extrapolation)),
+                    Value::Fixed(0.0),
+                )),
+                rotation: Value::Fixed(0.0),
+                scale: Value::Fixed(kurbo::Vec2::new(100.0, 100.0)),
+                skew: Value::Fixed(0.0),
+                skew_angle: Value::Fixed(0.0),
+            };
+            Composition {
+                frames: 0.0..10.0,
+                width: 10,
+                height: 10,
+                layers: vec![Layer {
+                    transform: transform.into_model(),
+                    opacity: Value::Animated(ramp(100.0, extrapolation)),
+                    ..square_layer(fixed::Color::BLACK)
+                }],
+                ..Default::default()
+            }
+        };
+        let composition = ramps(crate::Extrapolation::Hold);
+        let fill = |list: &DisplayList| match &list.commands[1] {
+            DisplayCommand::Fill {
+                transform, brush, ..
+            } => (*transform, brush.clone()),
+            command => panic!("expected a fill, got {command:?}"),
+        };
+        let mut renderer = Renderer::<DisplayList>::new();
+        renderer.set_frozen(FrozenProperties {
+            transform: true,
+            ..Default::default()
+        });
+        let (start_transform, start_brush) =
+            fill(&renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0));
+        let (transform, brush) = fill(&renderer.render(&composition, 5.0, Affine::IDENTITY, 1.0));
+        assert_eq!(transform, start_transform, "frozen transforms stay fixed");
+        assert_ne!(brush, start_brush, "opacity should still animate");
+
+        renderer.set_frozen(FrozenProperties {
+            opacity: true,
+            ..Default::default()
+        });
+        let (transform, brush) = fill(&renderer.render(&composition, 5.0, Affine::IDENTITY, 1.0));
+        assert_eq!(transform, Affine::translate((5.0, 0.0)));
+        assert_eq!(brush, start_brush, "frozen opacity stays fixed");
+
+        // Frozen properties aren't extrapolated from their first keyframe.
+        for extrapolation in [
+            crate::Extrapolation::Linear,
+            crate::Extrapolation::Loop,
+            crate::Extrapolation::PingPong,
+        ] {
+            let composition = ramps(extrapolation);
+            renderer.set_frozen(FrozenProperties {
+                transform: true,
+                opacity: true,
+                ..Default::default()
+            });
+            let (transform, brush) =
+                fill(&renderer.render(&composition, 5.0, Affine::IDENTITY, 1.0));
+            assert_eq!(transform, start_transform, "{extrapolation:?}");
+            assert_eq!(brush, start_brush, "{extrapolation:?}");
+        }
+    }
+
+    #[test]
+    fn unflipped_layers_keep_their_orientation() {
+        let anchored = animated::Transform {
+            anchor: Value::Fixed(kurbo::Point::new(5.0, 0.0)),
+            position: animated::Position::Value(Value::Fixed(kurbo::Point::new(25.0, 0.0))),
+            rotation: Value::Fixed(0.0),
+            scale: Value::Fixed(kurbo::Vec2::new(100.0, 100.0)),
+            skew: Value::Fixed(0.0),
+            skew_angle: Value::Fixed(0.0),
+        };
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 100,
+            height: 10,
+            layers: vec![
+                Layer {
+                    transform: Transform::Fixed(Affine::translate((20.0, 0.0))),
+                    ..square_layer(fixed::Color::BLACK)
+                },
+                Layer {
+                    parent: Some(0),
+                    transform: Transform::Fixed(Affine::translate((5.0, 0.0))),
+                    ..square_layer(fixed::Color::BLACK)
+                },
+            ],
+            ..Default::default()
+        };
+        composition.assets.insert(
+            "logo".into(),
+            vec![Layer {
+                transform: Transform::Animated(anchored),
+                ..square_layer(fixed::Color::BLACK)
+            }],
+        );
+        let transforms = |renderer: &mut Renderer<DisplayList>, composition: &Composition| {
+            let list = renderer.render(composition, 0.0, Affine::IDENTITY, 1.0);
+            list.commands
+                .iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::Fill { transform, .. } => Some(*transform),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mirrored = |x: f64| Affine::new([-1.0, 0.0, 0.0, 1.0, x, 0.0]);
+        let mut renderer = Renderer::<DisplayList>::new();
+        renderer.set_layout_direction(LayoutDirection::RightToLeft);
+        // Layers are drawn from the bottom up.
+        assert_eq!(
+            transforms(&mut renderer, &composition),
+            [mirrored(75.0), mirrored(80.0)]
+        );
+
+        renderer.set_mirror_policy(MirrorPolicy {
+            unflipped_layers: vec![0],
+            ..Default::default()
+        });
+        assert_eq!(
+            transforms(&mut renderer, &composition),
+            [
+                Affine::translate((85.0, 0.0)),
+                Affine::translate((80.0, 0.0))
+            ],
+            "the child should be flipped back along with its parent"
+        );
+
+        composition.layers.truncate(1);
+        composition.layers[0].content = Content::Instance {
+            name: "logo".into(),
+            time_remap: None,
+        };
+        composition.layers[0].stretch = 1.0;
+        renderer.set_mirror_policy(MirrorPolicy {
+            unflipped_asset_layers: [("logo".into(), vec![0])].into_iter().collect(),
+            ..Default::default()
+        });
+        // The content of the asset is flipped back about its anchor, at the
+        // mirrored position of its original bounds.
+        assert_eq!(
+            transforms(&mut renderer, &composition),
+            [Affine::translate((50.0, 0.0))]
+        );
+    }
+
+    #[test]
+    fn luma_mattes_draw_luminance_as_coverage() {
+        // The matte is white, partly covered by black.
+        let mut matte = square_layer(fixed::Color::WHITE);
+        let Content::Shape(shapes) = &mut matte.content else {
+            unreachable!();
+        };
+        shapes.splice(
+            0..0,
+            [
+                Shape::Geometry(Geometry::Fixed(
+                    Rect::new(2.0, 2.0, 8.0, 8.0).path_elements(0.1).collect(),
+                )),
+                Shape::Draw(Draw {
+                    stroke: None,
+                    brush: Brush::Fixed(fixed::Color::BLACK.into()),
+                    opacity: Value::Fixed(100.0),
+                }),
+            ],
+        );
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![
+                Layer {
+                    is_mask: true,
+                    ..matte
+                },
+                Layer {
+                    mask_layer: Some((Matte::InvertLuma, 0)),
+                    ..square_layer(fixed::Color::BLACK)
+                },
+            ],
+            ..Default::default()
+        };
+        let brush = |command: &DisplayCommand| match command {
+            DisplayCommand::Fill { brush, .. } => brush.clone(),
+            command => panic!("expected a fill, got {command:?}"),
+        };
+        let (white, black): (fixed::Brush, fixed::Brush) =
+            (fixed::Color::WHITE.into(), fixed::Color::BLACK.into());
+        let mut renderer = Renderer::<DisplayList>::new();
+        for frame in [0.0, 1.0] {
+            let list = renderer.render(&composition, frame, Affine::IDENTITY, 1.0);
+            // The matte is isolated by a layer inside the clip of the
+            // composition and drawn unchanged in a luminance layer, so that
+            // the black square hides the white one.
+            assert_eq!(list.commands.len(), 12, "at frame {frame}");
+            assert!(
+                matches!(list.commands[2], DisplayCommand::PushLuminanceLayer { .. }),
+                "the matte should be converted at frame {frame}"
+            );
+            let brushes: Vec<_> = list.commands[3..6].iter().map(brush).collect();
+            assert_eq!(brushes, [white.clone(), white.clone(), black.clone()]);
+            assert_eq!(list.commands[6], DisplayCommand::PopLayer);
+            let DisplayCommand::PushLayer { blend, .. } = &list.commands[7] else {
+                panic!("content should be composited onto the matte at frame {frame}");
+            };
+            assert_eq!(*blend, BlendMode::from(Compose::SrcOut));
+        }
+        // Backends that can't convert layers draw converted brushes. Green
+        // has a luminance of 0.7152.
+        assert_eq!(
+            luminance_to_alpha(fixed::Color::rgb8(0, 255, 0).into()),
+            fixed::Color::rgba8(255, 255, 255, 182).into()
+        );
+
+        composition.layers[1].mask_layer = Some((Matte::Alpha, 0));
+        renderer.clear_cache();
+        let list = renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0);
+        assert_eq!(list.commands.len(), 10);
+        let brushes: Vec<_> = list.commands[2..5].iter().map(brush).collect();
+        assert_eq!(
+            brushes,
+            [white.clone(), white, black],
+            "alpha mattes are drawn directly"
+        );
+    }
+
+    #[test]
+    fn masks_combine_in_order() {
+        let mask = |mode, inverted| Mask {
+            mode,
+            inverted,
+            geometry: Geometry::Fixed(Rect::new(2.0, 2.0, 8.0, 8.0).path_elements(0.1).collect()),
+            opacity: Value::Fixed(100.0),
+        };
+        let mut composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![Layer {
+                masks: vec![mask(MaskMode::Add, false), mask(MaskMode::Intersect, false)],
+                ..square_layer(fixed::Color::BLACK)
+            }],
+            ..Default::default()
+        };
+        let mut renderer = Renderer::<DisplayList>::new();
+        let blends = |list: &DisplayList| -> Vec<BlendMode> {
+            list.commands
+                .iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::PushLayer { blend, .. } => Some(*blend),
+                    _ => None,
+                })
+                .collect()
+        };
+        // Masks that only narrow the coverage are clips.
+        let list = renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0);
+        assert_eq!(blends(&list), [Mix::Clip.into(); 3]);
+
+        composition.layers[0].masks = vec![mask(MaskMode::Subtract, true)];
+        renderer.clear_cache();
+        let list = renderer.render(&composition, 0.0, Affine::IDENTITY, 1.0);
+        assert_eq!(
+            blends(&list),
+            [
+                Mix::Clip.into(),
+                Mix::Normal.into(),
+                Compose::DestOut.into(),
+                Compose::DestOut.into(),
+                Compose::SrcIn.into(),
+            ]
+        );
+        // Coverage starts out full since the first mask subtracts from it.
+        assert!(matches!(
+            list.commands[2],
+            DisplayCommand::Fill { transform, .. } if transform == Affine::IDENTITY
+        ));
+        assert_eq!(list.commands.len(), 14);
+    }
+
+    /// Strokes a horizontal line of the given length with a dash pattern,
+    /// returning the drawn style and the starts of the drawn subpaths.
+    fn dash_line(length: f64, pattern: [f64; 2], offset: f64) -> (kurbo::Stroke, Vec<f64>) {
+        let line = vec![
+            PathEl::MoveTo(kurbo::Point::new(0.0, 5.0)),
+            PathEl::LineTo(kurbo::Point::new(length, 5.0)),
+        ];
+        let stroke = animated::Stroke {
+            width: Value::Fixed(1.0),
+            join: kurbo::Join::Miter,
+            miter_limit: None,
+            cap: kurbo::Cap::Butt,
+            dash_pattern: pattern.into_iter().map(Value::Fixed).collect(),
+            dash_offset: Value::Fixed(offset),
+            taper: None,
+        };
+        let composition = Composition {
+            frames: 0.0..10.0,
+            width: 10,
+            height: 10,
+            layers: vec![Layer {
+                frames: 0.0..10.0,
+                opacity: Value::Fixed(100.0),
+                content: Content::Shape(vec![
+                    Shape::Geometry(Geometry::Fixed(line)),
+                    Shape::Draw(Draw {
+                        stroke: Some(stroke.into_model()),
+                        brush: Brush::Fixed(fixed::Color::BLACK.into()),
+                        opacity: Value::Fixed(100.0),
+                    }),
+                ]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let list = composition.display_list(0.0);
+        let DisplayCommand::Stroke { style, path, .. } = &list.commands[1] else {
+            panic!("line should be stroked: {:?}", list.commands);
+        };
+        let mut starts: Vec<f64> = path
+            .elements()
+            .iter()
+            .filter_map(|element| match element {
+                PathEl::MoveTo(point) => Some(point.x),
+                _ => None,
+            })
+            .collect();
+        starts.sort_by(f64::total_cmp);
+        (style.clone(), starts)
+    }
+
+    #[test]
+    fn dashes_are_applied_to_strokes() {
+        let (style, starts) = dash_line(10.0, [2.0, 3.0], 0.0);
+        assert!(style.dash_pattern.is_empty(), "dashes should be resolved");
+        assert_eq!(starts, [0.0, 5.0]);
+    }
+
+    #[test]
+    fn dash_offsets_and_counts_are_bounded() {
+        // Offsets are reduced to a single period of the pattern.
+        assert_eq!(
+            dash_line(10.0, [2.0, 3.0], -7.0).1,
+            dash_line(10.0, [2.0, 3.0], 3.0).1
+        );
+        assert_eq!(
+            dash_line(10.0, [2.0, 3.0], 1e12 + 3.0).1,
+            dash_line(10.0, [2.0, 3.0], 3.0).1
+        );
+        // Non-finite offsets and too many dashes draw solid strokes.
+        for (length, pattern, offset) in [
+            (10.0, [2.0, 3.0], f64::INFINITY),
+            (10.0, [2.0, 3.0], f64::NAN),
+            (1e6, [0.01, 0.01], 0.0),
+        ] {
+            let (style, starts) = dash_line(length, pattern, offset);
+            assert!(style.dash_pattern.is_empty(), "dashes should be resolved");
+            assert_eq!(starts, [0.0], "{length} {pattern:?} {offset}");
+        }
+    }
+}
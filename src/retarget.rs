@@ -0,0 +1,98 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec;
+
+use crate::{Animated, EasingHandle, Extrapolation, Linear, Time};
+
+impl<T: Linear> Animated<T> {
+    /// Returns an animation that moves from the value at `frame` to
+    /// `target`, arriving at `end_frame`.
+    ///
+    /// This is used to redirect an animation that is already in flight
+    /// without a visible jump. When `preserve_velocity` is true, the new
+    /// animation also starts with the current rate of change along the
+    /// direction of the target, so that motion continues smoothly. Any
+    /// motion perpendicular to that direction is dropped.
+    ///
+    /// The arrival is eased with the incoming easing of the final keyframe
+    /// segment of this animation.
+    #[must_use]
+    pub fn retarget(&self, frame: f64, target: T, end_frame: f64, preserve_velocity: bool) -> Self {
+        let current = self.evaluate(frame);
+        let duration = end_frame - frame;
+        let out_tangent = if preserve_velocity && duration > 0.0 {
+            let velocity = self.velocity(frame);
+            let delta = target.add_scaled(current, -1.0);
+            let length = delta.dot(delta);
+            // Slope of the easing curve at the start of the segment.
+            let slope = if length > 0.0 {
+                velocity.dot(delta) / length * duration
+            } else {
+                0.0
+            };
+            Some(EasingHandle {
+                x: 1.0 / 3.0,
+                y: slope / 3.0,
+            })
+        } else {
+            None
+        };
+        let in_tangent = self
+            .times
+            .len()
+            .checked_sub(2)
+            .and_then(|ix| self.times[ix].in_tangent);
+        Self {
+            times: vec![
+                Time {
+                    in_tangent,
+                    out_tangent,
+                    ..Time::new(frame)
+                },
+                Time::new(end_frame),
+            ],
+            values: vec![current, target],
+            extrapolation: Extrapolation::Hold,
+            interpolation: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retarget_preserves_value_and_velocity() {
+        let animated = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0)],
+            values: vec![0.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let retargeted = animated.retarget(5.0, 0.0, 15.0, true);
+        assert!(
+            (retargeted.evaluate(5.0) - 50.0).abs() < 1e-6,
+            "retargeting should start from the current value"
+        );
+        assert!(
+            retargeted.evaluate(15.0).abs() < 1e-6,
+            "retargeting should arrive at the target"
+        );
+        assert!(
+            (animated.velocity(5.0) - 10.0).abs() < 1e-6,
+            "velocity should be the rate of change per frame"
+        );
+        let velocity = retargeted.velocity(5.0);
+        assert!(
+            (velocity - 10.0).abs() < 0.5,
+            "retargeting should preserve the velocity, got {velocity}"
+        );
+        let stopped = animated.retarget(5.0, 0.0, 15.0, false);
+        assert!(
+            stopped.evaluate(5.01) < 50.0,
+            "without velocity, the value should head straight for the target"
+        );
+    }
+}
@@ -0,0 +1,469 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use crate::{
+    extract::{intersect, select_layers},
+    keyframes::Keyframed,
+    Animated, Composition, Easing, EasingHandle, Time, Tween, Value,
+};
+
+/// Policy for aligning retimed frames to whole frame numbers.
+///
+/// When snapping, keyframes that would land on or before the previous
+/// keyframe are pushed to the frame following it.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum FrameSnap {
+    /// Keep the exact scaled frames.
+    #[default]
+    None,
+    /// Round frames to the nearest whole frame.
+    Nearest,
+    /// Round frames down to the previous whole frame.
+    Down,
+    /// Round frames up to the next whole frame.
+    Up,
+}
+
+impl FrameSnap {
+    fn apply(self, frame: f64) -> f64 {
+        match self {
+            Self::None => frame,
+            Self::Nearest => frame.round(),
+            Self::Down => frame.floor(),
+            Self::Up => frame.ceil(),
+        }
+    }
+}
+
+/// Multiplies keyframe times by `factor` and snaps them to whole frames
+/// according to `snap`.
+///
+/// Keyframes scaled to a frame at or before `end` are kept at or before it
+/// when snapping pushes them forward.
+fn scale_times(times: &mut [Time], factor: f64, snap: FrameSnap, end: f64) {
+    let mut previous = f64::NEG_INFINITY;
+    for time in times {
+        let scaled = time.frame * factor;
+        time.frame = snap.apply(scaled);
+        if snap != FrameSnap::None && time.frame <= previous {
+            time.frame = previous + 1.0;
+            if scaled <= end {
+                time.frame = time.frame.min(snap.apply(end)).max(previous);
+            }
+        }
+        previous = time.frame;
+    }
+}
+
+/// Returns true if `factor` can scale frame numbers.
+fn is_valid_factor(factor: f64) -> bool {
+    factor > 0.0 && factor.is_finite()
+}
+
+impl Composition {
+    /// Returns a copy of the composition cut down to the given frame range,
+    /// with frames rebased so that the range starts at zero.
+    ///
+    /// Layers that are not active within the range are dropped unless other
+    /// layers depend on them as transform parents or mattes, along with any
+    /// assets that are no longer instanced. Keyframes outside of the range
+    /// are dropped, except for those needed to interpolate values at its
+    /// edges. Layers of instanced assets are evaluated in their own time
+    /// and are left untouched.
+    #[must_use]
+    pub fn trim(&self, range: Range<f64>) -> Self {
+        let range = intersect(&self.frames, &range);
+        let start = range.start;
+        let end = range.end;
+        let active: Vec<usize> = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| {
+                !layer.is_mask && layer.frames.start < end && layer.frames.end > start
+            })
+            .map(|(ix, _)| ix)
+            .collect();
+        let mut layers = select_layers(&self.layers, &active);
+        for layer in &mut layers {
+            layer.edit_keyframes(&mut |times: &mut [Time]| {
+                let hi = times
+                    .partition_point(|time| time.frame < end)
+                    .min(times.len() - 1);
+                let lo = times
+                    .partition_point(|time| time.frame <= start)
+                    .saturating_sub(1)
+                    .min(hi);
+                for time in times.iter_mut() {
+                    time.frame -= start;
+                }
+                lo..hi + 1
+            });
+            let frames = intersect(&layer.frames, &range);
+            layer.frames = frames.start - start..frames.end - start;
+            layer.start_frame -= start;
+        }
+        Self {
+            frames: 0.0..end - start,
+            assets: self.referenced_assets(&layers),
+            layers,
+            ..self.empty_copy()
+        }
+    }
+}
+
+impl Composition {
+    /// Returns a copy of the composition playing at a different pace.
+    ///
+    /// All frame numbers are multiplied by `factor`, so a factor of 2 plays
+    /// the animation at half speed. Easing curves are relative to the length
+    /// of each keyframe segment, so their shapes are preserved. Instanced
+    /// assets keep their own timing and are scaled through the stretch
+    /// factor of the instancing layer.
+    ///
+    /// When snapping moves keyframes apart, keyframes within the frame range
+    /// of a layer stay within it.
+    ///
+    /// Returns `None` if the factor is not positive and finite.
+    #[must_use]
+    pub fn retime(&self, factor: f64, snap: FrameSnap) -> Option<Self> {
+        if !is_valid_factor(factor) {
+            return None;
+        }
+        let scale = |frame: f64| snap.apply(frame * factor);
+        let mut layers = self.layers.clone();
+        for layer in &mut layers {
+            let end = layer.frames.end * factor;
+            layer.edit_keyframes(&mut |times: &mut [Time]| {
+                scale_times(times, factor, snap, end);
+                0..times.len()
+            });
+            layer.frames = scale(layer.frames.start)..scale(layer.frames.end);
+            layer.start_frame = scale(layer.start_frame);
+            layer.stretch *= factor;
+        }
+        Some(Self {
+            frames: scale(self.frames.start)..scale(self.frames.end),
+            assets: self.assets.clone(),
+            layers,
+            ..self.empty_copy()
+        })
+    }
+
+    /// Returns a copy of the composition retimed to last for the given
+    /// number of frames.
+    ///
+    /// Returns `None` if the composition or the requested duration is empty
+    /// or not finite. See [`Composition::retime`].
+    #[must_use]
+    pub fn retime_to_duration(&self, frames: f64, snap: FrameSnap) -> Option<Self> {
+        let duration = self.frames.end - self.frames.start;
+        if !is_valid_factor(duration) {
+            return None;
+        }
+        self.retime(frames / duration, snap)
+    }
+}
+
+impl<T: Tween> Animated<T> {
+    /// Returns a copy of the animation with keyframes moved from one frame
+    /// rate to another.
+    ///
+    /// Keyframe times are converted to frames at `to_rate` and then snapped
+    /// to whole frames according to `snap`. Easing is preserved.
+    #[must_use]
+    pub fn resample(&self, from_rate: f64, to_rate: f64, snap: FrameSnap) -> Self {
+        let mut times = self.times.clone();
+        scale_times(&mut times, to_rate / from_rate, snap, f64::INFINITY);
+        Self {
+            times,
+            values: self.values.clone(),
+            extrapolation: self.extrapolation,
+            interpolation: self.interpolation,
+        }
+    }
+}
+
+impl<T: Tween> Animated<T> {
+    /// Returns a copy of the animation with all keyframe times multiplied by
+    /// `factor`, so a factor of 2 plays the animation at half speed.
+    ///
+    /// Negative factors are not supported; use [`Animated::reverse`] to
+    /// change direction.
+    #[must_use]
+    pub fn stretch(&self, factor: f64) -> Self {
+        let mut times = self.times.clone();
+        scale_times(&mut times, factor, FrameSnap::None, f64::INFINITY);
+        Self {
+            times,
+            values: self.values.clone(),
+            extrapolation: self.extrapolation,
+            interpolation: self.interpolation,
+        }
+    }
+
+    /// Returns a copy of the animation with all keyframes moved by `offset`
+    /// frames.
+    #[must_use]
+    pub fn shift(&self, offset: f64) -> Self {
+        let mut times = self.times.clone();
+        for time in &mut times {
+            time.frame += offset;
+        }
+        Self {
+            times,
+            values: self.values.clone(),
+            extrapolation: self.extrapolation,
+            interpolation: self.interpolation,
+        }
+    }
+
+    /// Returns a copy of the animation playing backwards over the same range
+    /// of frames.
+    ///
+    /// Easing curves are mirrored so that each segment follows the same
+    /// path in the opposite direction. A hold segment continues to hold the
+    /// value of the keyframe that starts it, which after reversal is the
+    /// keyframe that originally ended it.
+    #[must_use]
+    pub fn reverse(&self) -> Self {
+        let (Some(first), Some(last)) = (self.times.first(), self.times.last()) else {
+            return self.clone();
+        };
+        let mirror = first.frame + last.frame;
+        let mirror_handle = |handle: EasingHandle| EasingHandle {
+            x: 1.0 - handle.x,
+            y: 1.0 - handle.y,
+        };
+        let count = self.times.len();
+        let times = (0..count)
+            .rev()
+            .map(|ix| {
+                let time = &self.times[ix];
+                // The segment starting at this keyframe is the one that
+                // previously ended at it.
+                let segment = ix.checked_sub(1).map(|prev| &self.times[prev]);
+                Time {
+                    frame: mirror - time.frame,
+                    in_tangent: segment.and_then(|prev| prev.out_tangent.map(mirror_handle)),
+                    out_tangent: segment.and_then(|prev| prev.in_tangent.map(mirror_handle)),
+                    hold: segment.is_some_and(|prev| prev.hold),
+                    easing: segment.and_then(|prev| prev.easing.as_ref().map(Easing::reversed)),
+                }
+            })
+            .collect();
+        Self {
+            times,
+            values: self.values.iter().rev().cloned().collect(),
+            extrapolation: self.extrapolation,
+            interpolation: self.interpolation,
+        }
+    }
+}
+
+impl<T: Tween> Value<T> {
+    /// Returns a copy of the value with keyframes moved from one frame rate
+    /// to another.
+    ///
+    /// See [`Animated::resample`].
+    #[must_use]
+    pub fn resample(&self, from_rate: f64, to_rate: f64, snap: FrameSnap) -> Self {
+        match self {
+            Self::Fixed(value) => Self::Fixed(value.clone()),
+            Self::Animated(animated) => Self::Animated(animated.resample(from_rate, to_rate, snap)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Content, Layer};
+    use alloc::vec;
+
+    #[test]
+    fn retime_scales_keyframes_and_instances() {
+        let opacity = Animated {
+            times: vec![Time::new(0.0), Time::new(3.0), Time::new(3.2)],
+            values: vec![0.0, 50.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let composition = Composition {
+            frames: 0.0..10.0,
+            layers: vec![Layer {
+                frames: 0.0..10.0,
+                stretch: 1.0,
+                start_frame: 2.0,
+                opacity: Value::Animated(opacity),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let retimed = composition
+            .retime_to_duration(15.0, FrameSnap::Nearest)
+            .unwrap();
+        assert_eq!(retimed.frames, 0.0..15.0);
+        let layer = &retimed.layers[0];
+        assert_eq!(layer.frames, 0.0..15.0);
+        assert_eq!(layer.start_frame, 3.0);
+        assert_eq!(layer.stretch, 1.5);
+        let Value::Animated(retimed_opacity) = &layer.opacity else {
+            panic!("opacity should remain animated");
+        };
+        let frames: Vec<f64> = retimed_opacity
+            .times
+            .iter()
+            .map(|time| time.frame)
+            .collect();
+        assert_eq!(frames, [0.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn retime_rejects_invalid_factors() {
+        let composition = Composition {
+            frames: 0.0..10.0,
+            ..Default::default()
+        };
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(
+                composition.retime(factor, FrameSnap::None).is_none(),
+                "factor {factor} should be rejected"
+            );
+        }
+        assert!(composition
+            .retime_to_duration(f64::NAN, FrameSnap::None)
+            .is_none());
+        let empty = Composition::default();
+        assert!(empty.retime_to_duration(10.0, FrameSnap::None).is_none());
+    }
+
+    #[test]
+    fn retime_snapping_stays_within_layers() {
+        let opacity = Animated {
+            times: vec![
+                Time::new(0.0),
+                Time::new(9.6),
+                Time::new(9.8),
+                Time::new(10.0),
+            ],
+            values: vec![0.0, 50.0, 75.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let composition = Composition {
+            frames: 0.0..10.0,
+            layers: vec![Layer {
+                frames: 0.0..10.0,
+                opacity: Value::Animated(opacity),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let retimed = composition.retime(1.0, FrameSnap::Nearest).unwrap();
+        let Value::Animated(retimed_opacity) = &retimed.layers[0].opacity else {
+            panic!("opacity should remain animated");
+        };
+        let frames: Vec<f64> = retimed_opacity
+            .times
+            .iter()
+            .map(|time| time.frame)
+            .collect();
+        assert_eq!(frames, [0.0, 10.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn resample_snaps_to_target_frames() {
+        let animated = Animated {
+            times: vec![Time::new(0.0), Time::new(1.0), Time::new(5.0)],
+            values: vec![0.0, 1.0, 2.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let resampled = animated.resample(24.0, 60.0, FrameSnap::Down);
+        let frames: Vec<f64> = resampled.times.iter().map(|time| time.frame).collect();
+        assert_eq!(frames, [0.0, 2.0, 12.0]);
+    }
+
+    #[test]
+    fn reverse_mirrors_values_and_easing() {
+        let animated = Animated {
+            times: vec![
+                Time {
+                    out_tangent: Some(EasingHandle { x: 0.4, y: 0.0 }),
+                    in_tangent: Some(EasingHandle { x: 1.0, y: 1.0 }),
+                    ..Time::new(0.0)
+                },
+                Time::new(10.0),
+                Time::new(30.0),
+            ],
+            values: vec![0.0, 100.0, 50.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let reversed = animated.reverse();
+        let frames: Vec<f64> = reversed.times.iter().map(|time| time.frame).collect();
+        assert_eq!(frames, [0.0, 20.0, 30.0]);
+        assert_eq!(reversed.values, [50.0, 100.0, 0.0]);
+        for frame in [0.0, 5.0, 12.5, 20.0, 27.0, 30.0] {
+            assert!(
+                (reversed.evaluate(frame) - animated.evaluate(30.0 - frame)).abs() < 1e-3,
+                "reversed animation should mirror the original at frame {frame}"
+            );
+        }
+        let stretched = animated.stretch(2.0).shift(5.0);
+        assert_eq!(stretched.times[2].frame, 65.0);
+        assert_eq!(stretched.evaluate(25.0), animated.evaluate(10.0));
+    }
+
+    #[test]
+    fn trim_rebases_layers_and_keyframes() {
+        let opacity = Animated {
+            times: (0..5).map(|i| Time::new(i as f64 * 10.0)).collect(),
+            values: vec![0.0, 10.0, 20.0, 30.0, 40.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let composition = Composition {
+            frames: 0.0..40.0,
+            layers: vec![
+                Layer {
+                    frames: 0.0..40.0,
+                    opacity: Value::Animated(opacity),
+                    ..Default::default()
+                },
+                Layer {
+                    frames: 0.0..5.0,
+                    content: Content::Shape(vec![]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let trimmed = composition.trim(15.0..25.0);
+        assert_eq!(trimmed.frames, 0.0..10.0);
+        assert_eq!(trimmed.layers.len(), 1);
+        let layer = &trimmed.layers[0];
+        assert_eq!(layer.frames, 0.0..10.0);
+        let Value::Animated(trimmed_opacity) = &layer.opacity else {
+            panic!("opacity should remain animated");
+        };
+        let frames: Vec<f64> = trimmed_opacity
+            .times
+            .iter()
+            .map(|time| time.frame)
+            .collect();
+        assert_eq!(frames, [-5.0, 5.0, 15.0]);
+        assert_eq!(trimmed_opacity.values, [10.0, 20.0, 30.0]);
+        assert!(
+            (layer.opacity.evaluate(0.0) - 15.0).abs() < 1e-6,
+            "value at the cut should be preserved"
+        );
+    }
+}
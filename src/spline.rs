@@ -2,7 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
 use alloc::vec::Vec;
-use kurbo::{PathEl, Point};
+use kurbo::{
+    ParamCurveArclen, ParamCurveArea, ParamCurveExtrema, PathEl, PathSeg, Point, Rect, Shape,
+};
 
 /// Helper trait for converting cubic splines to paths.
 pub(crate) trait SplineToPath {
@@ -10,36 +12,10 @@ pub(crate) trait SplineToPath {
     fn len(&self) -> usize;
 
     fn to_path(&self, is_closed: bool, path: &mut Vec<PathEl>) -> Option<()> {
-        use PathEl::*;
         if self.len() == 0 {
             return None;
         }
-        path.push(MoveTo(self.get(0)));
-        let n_vertices = self.len() / 3;
-        let mut add_element = |from_vertex, to_vertex| {
-            let from_index = 3 * from_vertex;
-            let to_index = 3 * to_vertex;
-            let p0: Point = self.get(from_index);
-            let p1: Point = self.get(to_index);
-            let mut c0: Point = self.get(from_index + 2);
-            c0.x += p0.x;
-            c0.y += p0.y;
-            let mut c1: Point = self.get(to_index + 1);
-            c1.x += p1.x;
-            c1.y += p1.y;
-            if c0 == p0 && c1 == p1 {
-                path.push(LineTo(p1));
-            } else {
-                path.push(CurveTo(c0, c1, p1));
-            }
-        };
-        for i in 1..n_vertices {
-            add_element(i - 1, i);
-        }
-        if is_closed && n_vertices != 0 {
-            add_element(n_vertices - 1, 0);
-            path.push(ClosePath);
-        }
+        path.extend(Elements::new(self, is_closed));
         Some(())
     }
 }
@@ -66,3 +42,166 @@ impl SplineToPath for (&'_ [Point], &'_ [Point], f64) {
         self.0[index].lerp(self.1[index], self.2)
     }
 }
+
+/// Iterator over the path elements of a spline, produced lazily.
+#[derive(Clone, Debug)]
+pub(crate) struct Elements<'a, S: ?Sized> {
+    spline: &'a S,
+    is_closed: bool,
+    /// Index of the next element to produce. Zero is the initial move,
+    /// followed by one element per vertex and the final close.
+    next: usize,
+}
+
+impl<'a, S: SplineToPath + ?Sized> Elements<'a, S> {
+    pub(crate) fn new(spline: &'a S, is_closed: bool) -> Self {
+        Self {
+            spline,
+            is_closed,
+            next: 0,
+        }
+    }
+
+    fn element(&self, from_vertex: usize, to_vertex: usize) -> PathEl {
+        let from_index = 3 * from_vertex;
+        let to_index = 3 * to_vertex;
+        let p0: Point = self.spline.get(from_index);
+        let p1: Point = self.spline.get(to_index);
+        let mut c0: Point = self.spline.get(from_index + 2);
+        c0.x += p0.x;
+        c0.y += p0.y;
+        let mut c1: Point = self.spline.get(to_index + 1);
+        c1.x += p1.x;
+        c1.y += p1.y;
+        if c0 == p0 && c1 == p1 {
+            PathEl::LineTo(p1)
+        } else {
+            PathEl::CurveTo(c0, c1, p1)
+        }
+    }
+}
+
+impl<S: SplineToPath + ?Sized> Iterator for Elements<'_, S> {
+    type Item = PathEl;
+
+    fn next(&mut self) -> Option<PathEl> {
+        if self.spline.len() == 0 {
+            return None;
+        }
+        let n_vertices = self.spline.len() / 3;
+        let index = self.next;
+        self.next += 1;
+        if index == 0 {
+            Some(PathEl::MoveTo(self.spline.get(0)))
+        } else if index < n_vertices {
+            Some(self.element(index - 1, index))
+        } else if !self.is_closed || n_vertices == 0 {
+            None
+        } else if index == n_vertices.max(1) {
+            Some(self.element(n_vertices - 1, 0))
+        } else if index == n_vertices.max(1) + 1 {
+            Some(PathEl::ClosePath)
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the path elements of a [`SplineShape`].
+#[derive(Clone, Debug)]
+pub struct SplinePathElements<'a>(Elements<'a, (&'a [Point], &'a [Point], f64)>);
+
+impl Iterator for SplinePathElements<'_> {
+    type Item = PathEl;
+
+    fn next(&mut self) -> Option<PathEl> {
+        self.0.next()
+    }
+}
+
+/// Animated spline evaluated at a single frame, usable as a [`Shape`]
+/// without collecting its elements into a path.
+#[derive(Clone, Debug)]
+pub struct SplineShape<'a> {
+    points: (&'a [Point], &'a [Point], f64),
+    is_closed: bool,
+}
+
+impl<'a> SplineShape<'a> {
+    pub(crate) fn new(from: &'a [Point], to: &'a [Point], t: f64, is_closed: bool) -> Self {
+        Self {
+            points: (from, to, t),
+            is_closed,
+        }
+    }
+
+    fn segments(&self) -> impl Iterator<Item = PathSeg> + '_ {
+        kurbo::segments(self.path_elements(0.0))
+    }
+}
+
+impl<'a> Shape for SplineShape<'a> {
+    type PathElementsIter<'iter>
+        = SplinePathElements<'iter>
+    where
+        Self: 'iter;
+
+    fn path_elements(&self, _tolerance: f64) -> Self::PathElementsIter<'_> {
+        SplinePathElements(Elements::new(&self.points, self.is_closed))
+    }
+
+    fn area(&self) -> f64 {
+        self.segments().map(|segment| segment.signed_area()).sum()
+    }
+
+    fn perimeter(&self, accuracy: f64) -> f64 {
+        self.segments()
+            .map(|segment| segment.arclen(accuracy))
+            .sum()
+    }
+
+    fn winding(&self, pt: Point) -> i32 {
+        // The winding of individual segments isn't exposed by kurbo, so
+        // this is the one query that collects into a path.
+        kurbo::BezPath::from_iter(self.path_elements(0.0)).winding(pt)
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.segments()
+            .map(|segment| ParamCurveExtrema::bounding_box(&segment))
+            .reduce(|a, b| a.union(b))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn spline_shape_matches_evaluated_path() {
+        // Vertex, in tangent and out tangent for each of three vertices.
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 0.0),
+            Point::new(5.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(-5.0, 0.0),
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 0.0),
+        ];
+        for is_closed in [false, true] {
+            let mut path = vec![];
+            points.as_slice().to_path(is_closed, &mut path);
+            let shape = SplineShape::new(&points, &points, 0.0, is_closed);
+            let elements: Vec<PathEl> = shape.path_elements(0.1).collect();
+            assert_eq!(elements, path);
+        }
+        let shape = SplineShape::new(&points, &points, 0.0, true);
+        assert_eq!(shape.bounding_box(), Rect::new(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(shape.winding(Point::new(9.0, 2.0)), 1);
+    }
+}
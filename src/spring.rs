@@ -0,0 +1,235 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use core::f64::consts::E;
+use core::time::Duration;
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use crate::Linear;
+
+/// Physical parameters of a [`Spring`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpringConfig {
+    /// Force pulling the value toward the target per unit of distance.
+    pub stiffness: f64,
+    /// Force opposing the motion per unit of velocity.
+    pub damping: f64,
+    /// Inertia of the value.
+    pub mass: f64,
+    /// Distance from the target and velocity below which the spring comes
+    /// to rest.
+    pub rest_threshold: f64,
+}
+
+impl Default for SpringConfig {
+    fn default() -> Self {
+        Self {
+            stiffness: 170.0,
+            damping: 26.0,
+            mass: 1.0,
+            rest_threshold: 1e-3,
+        }
+    }
+}
+
+impl SpringConfig {
+    /// Returns a configuration that settles as fast as possible without
+    /// oscillating.
+    pub fn critically_damped(stiffness: f64, mass: f64) -> Self {
+        Self {
+            stiffness,
+            damping: 2.0 * (stiffness * mass).sqrt(),
+            mass,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the damping ratio. Values below 1 oscillate, while values
+    /// above 1 settle slowly without oscillating.
+    pub fn damping_ratio(&self) -> f64 {
+        self.damping / (2.0 * (self.stiffness * self.mass).sqrt())
+    }
+}
+
+/// Value that follows a target as if attached to it by a damped spring.
+///
+/// Unlike keyframed animations, springs have no fixed duration. They are
+/// driven by [`Spring::advance`] with the time elapsed since the previous
+/// update, and the target can change at any time while the value keeps its
+/// velocity.
+#[derive(Clone, Debug)]
+pub struct Spring<T: Linear> {
+    config: SpringConfig,
+    value: T,
+    velocity: T,
+    target: T,
+}
+
+impl<T: Linear> Spring<T> {
+    /// Creates a spring at rest at the given value.
+    pub fn new(value: T, config: SpringConfig) -> Self {
+        Self {
+            config,
+            value,
+            velocity: T::default(),
+            target: value,
+        }
+    }
+
+    /// Returns the physical parameters of the spring.
+    pub fn config(&self) -> &SpringConfig {
+        &self.config
+    }
+
+    /// Sets the physical parameters of the spring.
+    pub fn set_config(&mut self, config: SpringConfig) {
+        self.config = config;
+    }
+
+    /// Returns the current value.
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    /// Returns the current velocity in units per second.
+    pub fn velocity(&self) -> T {
+        self.velocity
+    }
+
+    /// Returns the value the spring is moving toward.
+    pub fn target(&self) -> T {
+        self.target
+    }
+
+    /// Sets the value the spring is moving toward. The current value and
+    /// velocity are retained.
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+    }
+
+    /// Sets the current value and velocity, for example to hand off from a
+    /// keyframed animation.
+    pub fn set_state(&mut self, value: T, velocity: T) {
+        self.value = value;
+        self.velocity = velocity;
+    }
+
+    /// Returns true if the spring has settled at its target.
+    pub fn is_at_rest(&self) -> bool {
+        let offset = self.value.add_scaled(self.target, -1.0);
+        let threshold = self.config.rest_threshold * self.config.rest_threshold;
+        offset.dot(offset) <= threshold && self.velocity.dot(self.velocity) <= threshold
+    }
+
+    /// Advances the simulation by the given amount of time and returns the
+    /// new value.
+    ///
+    /// The motion is solved analytically, so the result doesn't depend on
+    /// how time is divided between calls.
+    pub fn advance(&mut self, elapsed: Duration) -> T {
+        let SpringConfig {
+            stiffness,
+            damping,
+            mass,
+            ..
+        } = self.config;
+        if stiffness <= 0.0 || mass <= 0.0 {
+            self.value = self.target;
+            self.velocity = T::default();
+            return self.value;
+        }
+        let t = elapsed.as_secs_f64();
+        let omega = (stiffness / mass).sqrt();
+        let zeta = damping / (2.0 * (stiffness * mass).sqrt());
+        // The offset from the target and the velocity evolve as a linear
+        // combination of their initial values.
+        let [a, b, c, d] = if (zeta - 1.0).abs() < 1e-6 {
+            let decay = E.powf(-omega * t);
+            [
+                decay * (1.0 + omega * t),
+                decay * t,
+                -decay * omega * omega * t,
+                decay * (1.0 - omega * t),
+            ]
+        } else if zeta < 1.0 {
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            let decay = E.powf(-zeta * omega * t);
+            let (sin, cos) = (omega_d * t).sin_cos();
+            [
+                decay * (cos + zeta * omega / omega_d * sin),
+                decay * sin / omega_d,
+                -decay * omega * omega / omega_d * sin,
+                decay * (cos - zeta * omega / omega_d * sin),
+            ]
+        } else {
+            let root = omega * (zeta * zeta - 1.0).sqrt();
+            let r1 = -zeta * omega + root;
+            let r2 = -zeta * omega - root;
+            let e1 = E.powf(r1 * t);
+            let e2 = E.powf(r2 * t);
+            let span = r1 - r2;
+            [
+                (r1 * e2 - r2 * e1) / span,
+                (e1 - e2) / span,
+                r1 * r2 * (e2 - e1) / span,
+                (r1 * e1 - r2 * e2) / span,
+            ]
+        };
+        let offset = self.value.add_scaled(self.target, -1.0);
+        let velocity = self.velocity;
+        self.value = self.target.add_scaled(offset, a).add_scaled(velocity, b);
+        self.velocity = T::default().add_scaled(offset, c).add_scaled(velocity, d);
+        if self.is_at_rest() {
+            self.value = self.target;
+            self.velocity = T::default();
+        }
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn critically_damped_spring_settles_without_overshoot() {
+        let mut spring = Spring::new(0.0, SpringConfig::critically_damped(100.0, 1.0));
+        spring.set_target(1.0);
+        let mut previous = 0.0;
+        for _ in 0..120 {
+            let value = spring.advance(Duration::from_secs_f64(1.0 / 60.0));
+            assert!(
+                value >= previous && value <= 1.0,
+                "critically damped springs should not overshoot"
+            );
+            previous = value;
+        }
+        assert!(spring.is_at_rest(), "spring should settle within 2 seconds");
+        assert_eq!(spring.value(), 1.0);
+    }
+
+    #[test]
+    fn spring_is_independent_of_step_size() {
+        let config = SpringConfig {
+            damping: 5.0,
+            ..Default::default()
+        };
+        let mut coarse = Spring::new(kurbo::Point::ZERO, config);
+        coarse.set_target(kurbo::Point::new(10.0, -5.0));
+        let mut fine = coarse.clone();
+        coarse.advance(Duration::from_millis(100));
+        for _ in 0..10 {
+            fine.advance(Duration::from_millis(10));
+        }
+        assert!(
+            (coarse.value() - fine.value()).hypot() < 1e-9,
+            "the result should not depend on how time is divided"
+        );
+        assert!(
+            coarse.value().x > 0.0 && coarse.velocity().x > 0.0,
+            "spring should be moving toward the target"
+        );
+    }
+}
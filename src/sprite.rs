@@ -0,0 +1,174 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+use kurbo::{Affine, Rect};
+
+use crate::{Composition, RenderBackend, Renderer};
+
+/// Frames of a composition laid out in a grid and drawn to a single scene.
+///
+/// Rasterizing the scene to an image of `width` by `height` pixels produces
+/// a sprite sheet, which can be played back by looking up the cell of each
+/// frame in `frames`.
+#[derive(Clone, Debug)]
+pub struct SpriteSheet<S> {
+    /// Scene containing every frame in its cell.
+    pub scene: S,
+    /// Width of the sheet in pixels.
+    pub width: usize,
+    /// Height of the sheet in pixels.
+    pub height: usize,
+    /// Frames in the order in which they were rendered.
+    pub frames: Vec<SpriteFrame>,
+}
+
+/// Entry of the manifest of a [`SpriteSheet`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpriteFrame {
+    /// Frame of the composition drawn in the cell.
+    pub frame: f64,
+    /// Cell of the sheet in pixels.
+    pub rect: Rect,
+    /// Time in seconds for which the frame is displayed, up to the next
+    /// frame of the sheet or the end of the composition.
+    pub duration: f64,
+}
+
+impl<S: RenderBackend> Renderer<S> {
+    /// Renders the given frames of the animation to a sprite sheet.
+    ///
+    /// Frames are scaled by `scale` and placed in rows of `columns` cells,
+    /// from left to right and top to bottom. Frames are expected in
+    /// ascending order.
+    pub fn render_sprite_sheet(
+        &mut self,
+        animation: &Composition,
+        frames: impl IntoIterator<Item = f64>,
+        scale: f64,
+        columns: usize,
+    ) -> SpriteSheet<S> {
+        let columns = columns.max(1);
+        let cell_width = (animation.width as f64 * scale).ceil().max(0.0);
+        let cell_height = (animation.height as f64 * scale).ceil().max(0.0);
+        let mut scene = S::default();
+        let mut sprites: Vec<SpriteFrame> = Vec::new();
+        for (ix, frame) in frames.into_iter().enumerate() {
+            let origin = (
+                (ix % columns) as f64 * cell_width,
+                (ix / columns) as f64 * cell_height,
+            );
+            let transform = Affine::translate(origin) * Affine::scale(scale);
+            self.append(animation, frame, transform, 1.0, &mut scene);
+            if let Some(previous) = sprites.last_mut() {
+                previous.duration = (frame - previous.frame).max(0.0);
+            }
+            sprites.push(SpriteFrame {
+                frame,
+                rect: Rect::from_origin_size(origin, (cell_width, cell_height)),
+                duration: (animation.frames.end - frame).max(0.0),
+            });
+        }
+        for sprite in &mut sprites {
+            sprite.duration /= animation.frame_rate;
+        }
+        let rows = sprites.len().div_ceil(columns);
+        SpriteSheet {
+            scene,
+            width: cell_width as usize * columns.min(sprites.len()),
+            height: cell_height as usize * rows,
+            frames: sprites,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::Shape;
+    use peniko::{BlendMode, Fill};
+
+    use super::*;
+    use crate::fixed;
+
+    /// Backend that records the transform of each pushed layer.
+    #[derive(Default)]
+    struct Layers(Vec<Affine>);
+
+    impl RenderBackend for Layers {
+        fn push_layer(
+            &mut self,
+            _blend: impl Into<BlendMode>,
+            _alpha: f32,
+            transform: Affine,
+            _clip: &impl Shape,
+        ) {
+            self.0.push(transform);
+        }
+
+        fn pop_layer(&mut self) {}
+
+        fn fill(
+            &mut self,
+            _style: Fill,
+            _transform: Affine,
+            _brush: &fixed::Brush,
+            _brush_transform: Option<Affine>,
+            _shape: &impl Shape,
+        ) {
+        }
+
+        fn stroke(
+            &mut self,
+            _style: &fixed::Stroke,
+            _transform: Affine,
+            _brush: &fixed::Brush,
+            _brush_transform: Option<Affine>,
+            _shape: &impl Shape,
+        ) {
+        }
+
+        fn append(&mut self, other: &Self, _transform: Option<Affine>) {
+            self.0.extend_from_slice(&other.0);
+        }
+
+        fn reset(&mut self) {
+            self.0.clear();
+        }
+    }
+
+    #[test]
+    fn sprite_sheet_lays_out_frames() {
+        let composition = Composition {
+            frames: 0.0..30.0,
+            frame_rate: 10.0,
+            width: 20,
+            height: 10,
+            ..Default::default()
+        };
+        let mut renderer = Renderer::<Layers>::new();
+        let sheet = renderer.render_sprite_sheet(&composition, [0.0, 10.0, 15.0], 0.5, 2);
+        assert_eq!((sheet.width, sheet.height), (20, 10));
+        let rects: Vec<Rect> = sheet.frames.iter().map(|sprite| sprite.rect).collect();
+        assert_eq!(
+            rects,
+            [
+                Rect::new(0.0, 0.0, 10.0, 5.0),
+                Rect::new(10.0, 0.0, 20.0, 5.0),
+                Rect::new(0.0, 5.0, 10.0, 10.0),
+            ]
+        );
+        let durations: Vec<f64> = sheet.frames.iter().map(|sprite| sprite.duration).collect();
+        assert_eq!(durations, [1.0, 0.5, 1.5]);
+        let origins: Vec<(f64, f64)> = sheet
+            .scene
+            .0
+            .iter()
+            .map(|transform| transform.translation().into())
+            .collect();
+        assert_eq!(origins, [(0.0, 0.0), (10.0, 0.0), (0.0, 5.0)]);
+    }
+}
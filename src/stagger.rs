@@ -0,0 +1,134 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+
+use crate::{Animated, Easing, Tween};
+
+/// Item from which a [`Stagger`] starts.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum StaggerFrom {
+    /// Start with the first item and proceed toward the last.
+    #[default]
+    Start,
+    /// Start with the last item and proceed toward the first.
+    End,
+    /// Start in the middle and proceed toward both ends.
+    Center,
+    /// Start at both ends and proceed toward the middle.
+    Edges,
+    /// Start with the item at the given index and proceed outward.
+    Index(usize),
+}
+
+/// Distribution of start delays across a collection of items, producing
+/// the cascading effect common in motion graphics.
+#[derive(Clone, Debug)]
+pub struct Stagger {
+    /// Delay in frames between consecutive items.
+    pub each: f64,
+    /// Item from which the cascade starts.
+    pub from: StaggerFrom,
+    /// Easing applied to the distribution of delays, or `None` to space
+    /// them evenly. With easing, the total spread of delays is unchanged
+    /// but items bunch up at one end.
+    pub easing: Option<Easing>,
+}
+
+impl Stagger {
+    /// Creates a stagger that delays each item by `each` frames relative to
+    /// the previous one.
+    pub fn new(each: f64) -> Self {
+        Self {
+            each,
+            from: StaggerFrom::Start,
+            easing: None,
+        }
+    }
+
+    /// Returns the delay in frames of the item at `index` in a collection of
+    /// `count` items.
+    pub fn delay(&self, index: usize, count: usize) -> f64 {
+        if count == 0 {
+            return 0.0;
+        }
+        let last = (count - 1) as f64;
+        let index = index as f64;
+        let (distance, max_distance) = match self.from {
+            StaggerFrom::Start => (index, last),
+            StaggerFrom::End => (last - index, last),
+            StaggerFrom::Center => ((index - last * 0.5).abs(), last * 0.5),
+            StaggerFrom::Edges => {
+                let half = last * 0.5;
+                (half - (index - half).abs(), half)
+            }
+            StaggerFrom::Index(origin) => {
+                let origin = origin as f64;
+                ((index - origin).abs(), origin.max(last - origin))
+            }
+        };
+        match &self.easing {
+            Some(easing) if max_distance > 0.0 => {
+                let eased = 0.0.tween(&1.0, distance / max_distance, easing);
+                eased * max_distance * self.each
+            }
+            _ => distance * self.each,
+        }
+    }
+
+    /// Returns the delays of all items in a collection of `count` items.
+    pub fn delays(&self, count: usize) -> Vec<f64> {
+        (0..count).map(|index| self.delay(index, count)).collect()
+    }
+
+    /// Returns copies of the animation for each of `count` items, shifted by
+    /// their delays.
+    pub fn apply<T: Tween>(&self, animated: &Animated<T>, count: usize) -> Vec<Animated<T>> {
+        (0..count)
+            .map(|index| animated.shift(self.delay(index, count)))
+            .collect()
+    }
+
+    /// Returns the value of the animation for the item at `index` in a
+    /// collection of `count` items, without creating a copy for each item.
+    pub fn evaluate<T: Tween>(
+        &self,
+        animated: &Animated<T>,
+        index: usize,
+        count: usize,
+        frame: f64,
+    ) -> T {
+        animated.evaluate(frame - self.delay(index, count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stagger_delays_follow_origin() {
+        let stagger = Stagger::new(2.0);
+        assert_eq!(stagger.delays(4), [0.0, 2.0, 4.0, 6.0]);
+        let from_end = Stagger {
+            from: StaggerFrom::End,
+            ..stagger.clone()
+        };
+        assert_eq!(from_end.delays(4), [6.0, 4.0, 2.0, 0.0]);
+        let from_center = Stagger {
+            from: StaggerFrom::Center,
+            ..stagger.clone()
+        };
+        assert_eq!(from_center.delays(5), [4.0, 2.0, 0.0, 2.0, 4.0]);
+        let from_edges = Stagger {
+            from: StaggerFrom::Edges,
+            ..stagger.clone()
+        };
+        assert_eq!(from_edges.delays(5), [0.0, 2.0, 4.0, 2.0, 0.0]);
+        let from_index = Stagger {
+            from: StaggerFrom::Index(1),
+            ..stagger.clone()
+        };
+        assert_eq!(from_index.delays(4), [2.0, 0.0, 2.0, 4.0]);
+    }
+}
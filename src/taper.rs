@@ -0,0 +1,108 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+use kurbo::{ParamCurve, ParamCurveArclen, PathEl, PathSeg, Point, Vec2};
+
+use crate::{
+    fixed,
+    modifier::{contours, direction, normal},
+};
+
+/// Accuracy of arc length computations when sampling the path.
+const ARCLEN_ACCURACY: f64 = 1e-3;
+
+/// Distance between samples of the outline along the path.
+const SAMPLE_STEP: f64 = 2.0;
+
+/// Minimum number of samples in each segment of the path, so that short
+/// curves remain smooth.
+const MIN_SAMPLES: usize = 8;
+
+/// Maximum number of samples in each segment of the path. This bounds the
+/// size of the outline.
+const MAX_SAMPLES: usize = 1024;
+
+impl fixed::Taper {
+    /// Returns the width at the given progress along a contour, from 0 to
+    /// 1, for a stroke of the given width.
+    pub fn width_at(&self, width: f64, progress: f64) -> f64 {
+        let t = self.ease.evaluate(progress.clamp(0.0, 1.0));
+        width * (self.start_width + (self.end_width - self.start_width) * t) / 100.0
+    }
+
+    /// Appends the outline of a tapered stroke of the given width along
+    /// `path` to `out`, for filling with the non-zero fill rule.
+    ///
+    /// Contours have butt ends, and corners are beveled.
+    pub fn outline(&self, width: f64, path: &[PathEl], out: &mut Vec<PathEl>) {
+        let mut samples: Vec<(Point, Vec2)> = Vec::new();
+        for contour in contours(path) {
+            let segments: Vec<(PathSeg, f64)> = kurbo::segments(contour.iter().copied())
+                .map(|segment| (segment, segment.arclen(ARCLEN_ACCURACY)))
+                .collect();
+            let total: f64 = segments.iter().map(|(_, length)| length).sum();
+            if total <= 0.0 || total.is_nan() {
+                continue;
+            }
+            samples.clear();
+            let mut distance = 0.0;
+            for (segment, length) in &segments {
+                let count =
+                    ((length / SAMPLE_STEP).ceil() as usize).clamp(MIN_SAMPLES, MAX_SAMPLES);
+                for k in 0..=count {
+                    let along = length * k as f64 / count as f64;
+                    let t = segment.inv_arclen(along, ARCLEN_ACCURACY);
+                    let half_width = self.width_at(width, (distance + along) / total) * 0.5;
+                    samples.push((segment.eval(t), normal(direction(*segment, t)) * half_width));
+                }
+                distance += length;
+            }
+            // One side forward and the other backward, so that closed
+            // contours produce a ring with opposite windings.
+            let mut points = samples
+                .iter()
+                .map(|(point, offset)| *point + *offset)
+                .chain(samples.iter().rev().map(|(point, offset)| *point - *offset));
+            if let Some(first) = points.next() {
+                out.push(PathEl::MoveTo(first));
+                out.extend(points.map(PathEl::LineTo));
+                out.push(PathEl::ClosePath);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Easing;
+
+    #[test]
+    fn taper_narrows_along_path() {
+        let taper = fixed::Taper {
+            start_width: 100.0,
+            end_width: 0.0,
+            ease: Easing::LERP,
+        };
+        let line = [
+            PathEl::MoveTo(Point::new(0.0, 0.0)),
+            PathEl::LineTo(Point::new(16.0, 0.0)),
+        ];
+        let mut out = Vec::new();
+        taper.outline(4.0, &line, &mut out);
+        // Nine samples along each side of the line.
+        assert_eq!(out.len(), 19);
+        assert_eq!(out[0], PathEl::MoveTo(Point::new(0.0, 2.0)));
+        assert_eq!(out[8], PathEl::LineTo(Point::new(16.0, 0.0)));
+        let PathEl::LineTo(middle) = out[4] else {
+            panic!("outline should be made of lines");
+        };
+        assert!((middle - Point::new(8.0, 1.0)).hypot() < 1e-6);
+        assert_eq!(out[17], PathEl::LineTo(Point::new(0.0, -2.0)));
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use crate::{Animated, EasingHandle, Linear};
+
+/// Easing at one side of a keyframe, described by speed and influence as
+/// in the After Effects keyframe velocity dialog.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TemporalEase {
+    /// Speed of the value at the keyframe in units per frame. For
+    /// multidimensional values, this is the speed along the direction of
+    /// motion. Speeds given in units per second should be divided by the
+    /// frame rate.
+    pub speed: f64,
+    /// Percentage of the segment duration over which the speed influences
+    /// the motion, clamped to the range 0.1 to 100.
+    pub influence: f64,
+}
+
+impl TemporalEase {
+    /// Ease that comes to a stop at the keyframe with the given influence,
+    /// as with the After Effects "Easy Ease" keyframe assistant at 33.33%.
+    pub fn stop(influence: f64) -> Self {
+        Self {
+            speed: 0.0,
+            influence,
+        }
+    }
+
+    /// Returns the influence as a fraction of the segment duration.
+    fn fraction(self) -> f64 {
+        self.influence.clamp(0.1, 100.0) / 100.0
+    }
+}
+
+impl<T: Linear> Animated<T> {
+    /// Sets the easing on both sides of the keyframe at `index` from speed
+    /// and influence, converting to the equivalent bezier tangents.
+    ///
+    /// The incoming ease applies to the segment ending at the keyframe and
+    /// the outgoing ease to the segment starting at it. Sides without an
+    /// adjacent segment are ignored, as are `None` values. Any custom easing
+    /// of the affected segments is removed.
+    pub fn set_temporal_ease(
+        &mut self,
+        index: usize,
+        incoming: Option<TemporalEase>,
+        outgoing: Option<TemporalEase>,
+    ) {
+        crate::edit::bump_generation();
+        if let (Some(ease), Some(start)) = (incoming, index.checked_sub(1)) {
+            if let Some((duration, distance)) = self.segment_extent(start) {
+                let x = ease.fraction();
+                let time = &mut self.times[start];
+                time.in_tangent = Some(EasingHandle {
+                    x: 1.0 - x,
+                    y: 1.0 - slope(ease.speed, duration, distance) * x,
+                });
+                time.easing = None;
+            }
+        }
+        if let Some(ease) = outgoing {
+            if let Some((duration, distance)) = self.segment_extent(index) {
+                let x = ease.fraction();
+                let time = &mut self.times[index];
+                time.out_tangent = Some(EasingHandle {
+                    x,
+                    y: slope(ease.speed, duration, distance) * x,
+                });
+                time.easing = None;
+            }
+        }
+    }
+
+    /// Returns the duration and the distance covered by the segment
+    /// starting at the given keyframe.
+    fn segment_extent(&self, start: usize) -> Option<(f64, f64)> {
+        let (t0, t1) = (self.times.get(start)?, self.times.get(start + 1)?);
+        let (v0, v1) = (self.values.get(start)?, self.values.get(start + 1)?);
+        let delta = v1.add_scaled(*v0, -1.0);
+        Some((t1.frame - t0.frame, delta.dot(delta).sqrt()))
+    }
+}
+
+/// Returns the slope of the normalized easing curve for the given speed.
+fn slope(speed: f64, duration: f64, distance: f64) -> f64 {
+    if distance > 0.0 {
+        speed * duration / distance
+    } else {
+        // Without motion, speed has no meaning, so fall back to linear.
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Time;
+    use alloc::vec;
+
+    #[test]
+    fn temporal_ease_matches_speed() {
+        let mut animated = Animated {
+            times: vec![Time::new(0.0), Time::new(10.0)],
+            values: vec![0.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        animated.set_temporal_ease(
+            0,
+            None,
+            Some(TemporalEase {
+                speed: 20.0,
+                influence: 50.0,
+            }),
+        );
+        animated.set_temporal_ease(1, Some(TemporalEase::stop(50.0)), None);
+        let out_tangent = animated.times[0].out_tangent.unwrap();
+        assert!(
+            (out_tangent.x - 0.5).abs() < 1e-12 && (out_tangent.y - 1.0).abs() < 1e-12,
+            "outgoing tangent should reflect speed and influence"
+        );
+        let velocity = (animated.evaluate(0.001) - animated.evaluate(0.0)) / 0.001;
+        assert!(
+            (velocity - 20.0).abs() < 0.1,
+            "motion should start at the given speed, got {velocity}"
+        );
+        let arrival = (animated.evaluate(10.0) - animated.evaluate(9.999)) / 0.001;
+        assert!(arrival.abs() < 0.1, "motion should stop at the keyframe");
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use crate::{keyframes::Keyframed, Composition, Easing, FrameSnap, Layer, Time};
+
+/// Overrides for the motion of a whole composition.
+///
+/// This allows a design system to enforce its motion guidelines on
+/// imported files, such as by replacing every cubic bezier curve with a
+/// standard curve and shortening all durations.
+#[derive(Clone, Debug)]
+pub struct MotionTheme {
+    /// Easing replacing segments that progress linearly.
+    pub linear: Option<Easing>,
+    /// Easing replacing cubic bezier segments that don't progress
+    /// linearly.
+    pub bezier: Option<Easing>,
+    /// Factor by which all durations are multiplied.
+    pub duration_scale: f64,
+    /// Alignment of scaled frames to whole frame numbers.
+    pub snap: FrameSnap,
+}
+
+impl Default for MotionTheme {
+    fn default() -> Self {
+        Self {
+            linear: None,
+            bezier: None,
+            duration_scale: 1.0,
+            snap: FrameSnap::None,
+        }
+    }
+}
+
+impl MotionTheme {
+    /// Replaces the easing of a keyframe segment if its class has an
+    /// override. Hold, stepped and custom easings are left untouched.
+    fn apply(&self, time: &mut Time) {
+        if time.hold {
+            return;
+        }
+        let replacement = match time.easing().as_ref() {
+            Easing::Bezier { o, i } if o.x == o.y && i.x == i.y => &self.linear,
+            Easing::Bezier { .. } => &self.bezier,
+            _ => &None,
+        };
+        if let Some(easing) = replacement {
+            time.set_easing(easing.clone());
+        }
+    }
+
+    fn apply_to_layers(&self, layers: &mut [Layer]) {
+        for layer in layers {
+            layer.edit_keyframes(&mut |times: &mut [Time]| {
+                for time in times.iter_mut() {
+                    self.apply(time);
+                }
+                0..times.len()
+            });
+        }
+    }
+}
+
+impl Composition {
+    /// Returns a copy of the composition with the overrides of a motion
+    /// theme applied to all layers, including those of assets.
+    ///
+    /// Durations are scaled as with [`Composition::retime`], unless the
+    /// scale is not positive and finite, in which case they are kept.
+    #[must_use]
+    pub fn with_theme(&self, theme: &MotionTheme) -> Self {
+        let mut result = if theme.duration_scale == 1.0 {
+            None
+        } else {
+            self.retime(theme.duration_scale, theme.snap)
+        }
+        .unwrap_or_else(|| self.clone());
+        theme.apply_to_layers(&mut result.layers);
+        for layers in result.assets.values_mut() {
+            theme.apply_to_layers(layers);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Animated, EasingHandle, Value};
+    use alloc::vec;
+
+    #[test]
+    fn theme_replaces_easing_classes() {
+        let time = |frame, out_tangent| Time {
+            out_tangent,
+            ..Time::new(frame)
+        };
+        let snappy = Some(EasingHandle { x: 0.9, y: 0.0 });
+        let opacity = Animated {
+            times: vec![time(0.0, None), time(10.0, snappy), time(20.0, None)],
+            values: vec![0.0, 50.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let composition = Composition {
+            frames: 0.0..20.0,
+            layers: vec![Layer {
+                frames: 0.0..20.0,
+                opacity: Value::Animated(opacity),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let standard = Easing::cubic_bezier(0.4, 0.0, 0.2, 1.0);
+        let themed = composition.with_theme(&MotionTheme {
+            bezier: Some(standard.clone()),
+            duration_scale: 0.5,
+            ..Default::default()
+        });
+        assert_eq!(themed.frames, 0.0..10.0);
+        let Value::Animated(themed_opacity) = &themed.layers[0].opacity else {
+            panic!("opacity should remain animated");
+        };
+        assert_eq!(
+            themed_opacity.evaluate(2.5),
+            25.0,
+            "linear segments are kept"
+        );
+        let expected = 50.0 + standard.evaluate(0.5) * 50.0;
+        assert!(
+            (themed_opacity.evaluate(7.5) - expected).abs() < 1e-9,
+            "bezier segments should use the standard curve"
+        );
+    }
+}
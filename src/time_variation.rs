@@ -0,0 +1,102 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use crate::{wiggle::lattice, Composition, Content};
+
+/// Seeded variation of the timing of instanced content.
+///
+/// Grids of layers instancing the same asset animate in unison, which can
+/// look mechanical. Giving each layer a slightly different time offset and
+/// speed breaks this up. The same seed always produces the same timing.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct TimeVariation {
+    /// Seed for the random offset and speed.
+    pub seed: u64,
+    /// Maximum number of frames by which the content is shifted in either
+    /// direction.
+    pub offset: f64,
+    /// Maximum relative change of the playback speed, such as 0.1 for up to
+    /// 10% faster or slower.
+    pub speed: f64,
+}
+
+impl TimeVariation {
+    /// Returns the frame of instanced content with the variation applied.
+    pub(crate) fn apply(&self, frame: f64) -> f64 {
+        let offset = lattice(0, self.seed) * self.offset;
+        let speed = 1.0 + lattice(1, self.seed) * self.speed;
+        frame * speed + offset
+    }
+}
+
+impl Composition {
+    /// Returns a copy of the composition with the timing of each top level
+    /// layer instancing the given asset varied independently.
+    ///
+    /// Each layer receives the variation with its seed combined with the
+    /// index of the layer. Layers instancing other assets are unchanged.
+    #[must_use]
+    pub fn vary_instance_timing(&self, asset: &str, variation: TimeVariation) -> Self {
+        let mut result = self.clone();
+        for (index, layer) in result.layers.iter_mut().enumerate() {
+            if matches!(&layer.content, Content::Instance { name, .. } if name == asset) {
+                layer.time_variation = Some(TimeVariation {
+                    seed: variation.seed.wrapping_add(index as u64),
+                    ..variation
+                });
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layer;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn instances_vary_within_bounds() {
+        let instance = |name: &str| Layer {
+            frames: 0.0..100.0,
+            stretch: 1.0,
+            content: Content::Instance {
+                name: name.into(),
+                time_remap: None,
+            },
+            ..Default::default()
+        };
+        let composition = Composition {
+            layers: (0..8)
+                .map(|_| instance("icon"))
+                .chain([instance("other")])
+                .collect(),
+            ..Default::default()
+        };
+        let variation = TimeVariation {
+            seed: 3,
+            offset: 5.0,
+            speed: 0.1,
+        };
+        let varied = composition.vary_instance_timing("icon", variation);
+        assert_eq!(varied.layers[8].instance_frame(50.0), 50.0);
+        let frames: Vec<f64> = varied.layers[..8]
+            .iter()
+            .map(|layer| layer.instance_frame(50.0))
+            .collect();
+        for frame in &frames {
+            // Up to 5 frames of offset and 10% of the 50 elapsed frames.
+            assert!(
+                (*frame - 50.0).abs() <= 5.0 + 5.0,
+                "variation should stay within its bounds, got {frame}"
+            );
+        }
+        assert!(
+            frames.windows(2).any(|pair| pair[0] != pair[1]),
+            "copies should not animate in unison"
+        );
+        let again = composition.vary_instance_timing("icon", variation);
+        assert_eq!(again.layers[3].instance_frame(50.0), frames[3]);
+    }
+}
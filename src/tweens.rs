@@ -0,0 +1,115 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Animated, Easing, Extrapolation, Time, Tween};
+
+/// Fluent builder for animations authored in code.
+///
+/// Each step is described by its target value and duration, so keyframe
+/// times never need to be computed by hand.
+#[derive(Clone, Debug)]
+pub struct Tweens<T: Tween> {
+    times: Vec<Time>,
+    values: Vec<T>,
+    delay: f64,
+}
+
+impl<T: Tween> Tweens<T> {
+    /// Starts an animation at the given value on frame zero.
+    pub fn of(value: T) -> Self {
+        Self::at(value, 0.0)
+    }
+
+    /// Starts an animation at the given value and frame.
+    pub fn at(value: T, frame: f64) -> Self {
+        Self {
+            times: vec![Time::new(frame)],
+            values: vec![value],
+            delay: 0.0,
+        }
+    }
+
+    /// Returns the frame at which the animation currently ends.
+    pub fn end_frame(&self) -> f64 {
+        self.times.last().map_or(0.0, |time| time.frame)
+    }
+
+    /// Adds a step that moves to `target` over `duration` frames. The step
+    /// is linear unless eased with [`Tweens::ease`].
+    #[must_use]
+    pub fn to(mut self, target: T, duration: f64) -> Self {
+        let mut frame = self.end_frame();
+        if self.delay > 0.0 {
+            frame += self.delay;
+            self.delay = 0.0;
+            let value = self.values.last().cloned().unwrap_or_default();
+            self.times.push(Time::new(frame));
+            self.values.push(value);
+        }
+        self.times.push(Time::new(frame + duration));
+        self.values.push(target);
+        self
+    }
+
+    /// Sets the easing of the most recently added step.
+    #[must_use]
+    pub fn ease(mut self, easing: Easing) -> Self {
+        let count = self.times.len();
+        if let Some(start) = count.checked_sub(2).map(|ix| &mut self.times[ix]) {
+            start.set_easing(easing);
+        }
+        self
+    }
+
+    /// Holds the current value for `frames` frames before the next step.
+    #[must_use]
+    pub fn delay(mut self, frames: f64) -> Self {
+        self.delay += frames;
+        self
+    }
+
+    /// Returns the animation described by the builder.
+    pub fn build(self) -> Animated<T> {
+        Animated {
+            times: self.times,
+            values: self.values,
+            extrapolation: Extrapolation::Hold,
+            interpolation: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EasingHandle;
+
+    #[test]
+    fn tweens_compute_keyframe_times() {
+        let ease_in = Easing::Bezier {
+            o: EasingHandle { x: 0.42, y: 0.0 },
+            i: EasingHandle { x: 1.0, y: 1.0 },
+        };
+        let animated = Tweens::at(0.0, 5.0)
+            .to(100.0, 10.0)
+            .ease(ease_in)
+            .delay(5.0)
+            .to(50.0, 10.0)
+            .build();
+        let frames: Vec<f64> = animated.times.iter().map(|time| time.frame).collect();
+        assert_eq!(frames, [5.0, 15.0, 20.0, 30.0]);
+        assert_eq!(animated.values, [0.0, 100.0, 100.0, 50.0]);
+        assert!(
+            animated.evaluate(10.0) < 50.0,
+            "first step should be eased in"
+        );
+        assert_eq!(animated.evaluate(17.0), 100.0);
+        assert!(
+            (animated.evaluate(25.0) - 75.0).abs() < 1e-3,
+            "second step should be linear"
+        );
+    }
+}
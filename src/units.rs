@@ -0,0 +1,141 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use crate::{Draw, Easing, GroupTransform, Layer, Mask, Tween};
+
+/// Opacity with an explicit unit.
+///
+/// The model stores opacities as percentages, as in Lottie, while colors
+/// and rendering use fractions. Converting through this type keeps the two
+/// from being mixed up.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Default, Debug)]
+pub struct Opacity(f64);
+
+impl Opacity {
+    /// Fully transparent.
+    pub const TRANSPARENT: Self = Self(0.0);
+    /// Fully opaque.
+    pub const OPAQUE: Self = Self(1.0);
+
+    /// Creates an opacity from a fraction in the range 0 to 1.
+    pub fn from_fraction(fraction: f64) -> Self {
+        Self(fraction)
+    }
+
+    /// Creates an opacity from a percentage in the range 0 to 100.
+    pub fn from_percent(percent: f64) -> Self {
+        Self(percent / 100.0)
+    }
+
+    /// Returns the opacity as a fraction in the range 0 to 1.
+    pub fn fraction(self) -> f64 {
+        self.0
+    }
+
+    /// Returns the opacity as a percentage in the range 0 to 100.
+    pub fn percent(self) -> f64 {
+        self.0 * 100.0
+    }
+}
+
+impl Tween for Opacity {
+    fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
+        Self(self.0.tween(&other.0, t, easing))
+    }
+
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        self.0.extrapolate(&previous.0, scale).map(Self)
+    }
+}
+
+/// Length in pixels, a percentage of a reference length, or a sum of both,
+/// as with CSS `calc()`.
+///
+/// Lengths with different units tween smoothly from one to the other, since
+/// each part is interpolated separately.
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct Length {
+    /// Absolute part in pixels.
+    pub px: f64,
+    /// Relative part as a percentage of the reference length.
+    pub percent: f64,
+}
+
+impl Length {
+    /// Creates a length in pixels.
+    pub fn px(px: f64) -> Self {
+        Self { px, percent: 0.0 }
+    }
+
+    /// Creates a length as a percentage of the reference length.
+    pub fn percent(percent: f64) -> Self {
+        Self { px: 0.0, percent }
+    }
+
+    /// Returns the length in pixels for the given reference length.
+    pub fn resolve(self, reference: f64) -> f64 {
+        self.px + self.percent / 100.0 * reference
+    }
+}
+
+impl Tween for Length {
+    fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
+        Self {
+            px: self.px.tween(&other.px, t, easing),
+            percent: self.percent.tween(&other.percent, t, easing),
+        }
+    }
+
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        Some(Self {
+            px: self.px.extrapolate(&previous.px, scale)?,
+            percent: self.percent.extrapolate(&previous.percent, scale)?,
+        })
+    }
+}
+
+macro_rules! opacity_at {
+    ($($name:ident),*) => {
+        $(
+            impl $name {
+                /// Returns the opacity at the specified frame.
+                pub fn opacity_at(&self, frame: f64) -> Opacity {
+                    Opacity::from_percent(self.opacity.evaluate(frame))
+                }
+            }
+        )*
+    };
+}
+
+opacity_at!(Layer, Mask, Draw, GroupTransform);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Angle, Value};
+
+    #[test]
+    fn units_convert_and_tween() {
+        let layer = Layer {
+            opacity: Value::Fixed(50.0),
+            ..Default::default()
+        };
+        assert_eq!(layer.opacity_at(0.0).fraction(), 0.5);
+        assert_eq!(Opacity::from_fraction(0.25).percent(), 25.0);
+
+        let from = Length::px(10.0);
+        let to = Length::percent(50.0);
+        let mid = from.tween(&to, 0.5, &Easing::LERP);
+        assert_eq!(mid.resolve(200.0), 55.0);
+
+        let right = Angle::from_radians(core::f64::consts::FRAC_PI_2);
+        assert!(
+            (right.degrees - 90.0).abs() < 1e-12,
+            "radians should convert to degrees"
+        );
+        assert!(
+            (Angle::from_degrees(180.0).radians() - core::f64::consts::PI).abs() < 1e-12,
+            "degrees should convert to radians"
+        );
+    }
+}
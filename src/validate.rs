@@ -0,0 +1,395 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use hashbrown::HashMap;
+
+use crate::{
+    Brush, Composition, Content, Draw, InstanceBrush, Layer, PatternBrush, PatternSource, Repeater,
+    Shape, Value,
+};
+
+/// Limits on the structure of a composition.
+///
+/// These protect against files that are crafted (or simply authored) to
+/// require unbounded work, such as deeply nested precompositions or
+/// repeaters with huge copy counts.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Limits {
+    /// Maximum depth of nested precomposition instances.
+    pub max_instance_depth: usize,
+    /// Maximum number of copies produced by a single repeater.
+    pub max_repeater_copies: usize,
+    /// Maximum nesting depth of shape groups.
+    pub max_shape_depth: usize,
+}
+
+impl Limits {
+    /// No limits.
+    pub const UNBOUNDED: Self = Self {
+        max_instance_depth: usize::MAX,
+        max_repeater_copies: usize::MAX,
+        max_shape_depth: usize::MAX,
+    };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::UNBOUNDED
+    }
+}
+
+/// Error produced when validating a composition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ValidationError {
+    /// The named asset instances itself, either directly or through other
+    /// assets.
+    CyclicAsset(String),
+    /// The named asset contains precomposition instances nested deeper than
+    /// [`Limits::max_instance_depth`].
+    InstanceDepth { asset: String, depth: usize },
+    /// A repeater in the named layer produces more copies than
+    /// [`Limits::max_repeater_copies`].
+    RepeaterCopies { layer: String, copies: usize },
+    /// The named layer contains shape groups nested deeper than
+    /// [`Limits::max_shape_depth`].
+    ShapeDepth { layer: String, depth: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CyclicAsset(name) => write!(f, "asset \"{name}\" instances itself"),
+            Self::InstanceDepth { asset, depth } => {
+                write!(f, "asset \"{asset}\" nests {depth} levels of instances")
+            }
+            Self::RepeaterCopies { layer, copies } => {
+                write!(f, "repeater in layer \"{layer}\" produces {copies} copies")
+            }
+            Self::ShapeDepth { layer, depth } => {
+                write!(f, "layer \"{layer}\" nests {depth} levels of shape groups")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValidationError {}
+
+impl Composition {
+    /// Checks the composition for structural problems that would prevent it
+    /// from being evaluated.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ValidationError::CyclicAsset`] if an asset instances
+    /// itself, directly or through other assets.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.validate_with_limits(&Limits::UNBOUNDED)
+    }
+
+    /// Checks the composition for structural problems and for structure
+    /// exceeding the given limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ValidationError::CyclicAsset`] if an asset instances
+    /// itself, or the error corresponding to the first limit found to be
+    /// exceeded.
+    pub fn validate_with_limits(&self, limits: &Limits) -> Result<(), ValidationError> {
+        self.validate_instances(limits)?;
+        for layer in self.layers.iter().chain(self.assets.values().flatten()) {
+            validate_shapes(layer, limits)?;
+        }
+        Ok(())
+    }
+
+    fn validate_instances<'a>(&'a self, limits: &Limits) -> Result<(), ValidationError> {
+        #[derive(Copy, Clone, PartialEq, Eq)]
+        enum Visit {
+            InProgress,
+            /// Depth of instances within the asset, including itself.
+            Done(usize),
+        }
+        let mut visits: HashMap<&str, Visit> = HashMap::new();
+        // Explicit stack of (asset name, names of the assets it instances,
+        // index of the next instance to visit, deepest instance seen so far)
+        // so that deeply nested assets can't overflow the call stack.
+        let mut stack: Vec<(&str, Vec<&str>, usize, usize)> = Vec::new();
+        let frame = |name: &'a str| {
+            let instances = self.assets[name].iter().flat_map(instance_names).collect();
+            (name, instances, 0, 0)
+        };
+        for root in self.assets.keys() {
+            if visits.contains_key(root.as_str()) {
+                continue;
+            }
+            visits.insert(root, Visit::InProgress);
+            stack.push(frame(root));
+            while let Some(top) = stack.last_mut() {
+                let (name, next, nested) = (top.0, top.2, top.3);
+                top.2 += 1;
+                let Some(instance) = top.1.get(next).copied() else {
+                    let depth = nested + 1;
+                    if depth > limits.max_instance_depth {
+                        return Err(ValidationError::InstanceDepth {
+                            asset: name.into(),
+                            depth,
+                        });
+                    }
+                    visits.insert(name, Visit::Done(depth));
+                    stack.pop();
+                    if let Some(parent) = stack.last_mut() {
+                        parent.3 = parent.3.max(depth);
+                    }
+                    continue;
+                };
+                let Some((instance, _)) = self.assets.get_key_value(instance) else {
+                    continue;
+                };
+                match visits.get(instance.as_str()) {
+                    Some(Visit::InProgress) => {
+                        return Err(ValidationError::CyclicAsset(instance.clone()));
+                    }
+                    Some(Visit::Done(depth)) => {
+                        top.3 = nested.max(*depth);
+                    }
+                    None => {
+                        visits.insert(instance, Visit::InProgress);
+                        stack.push(frame(instance));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the shape tree of a layer against the given limits.
+fn validate_shapes(layer: &Layer, limits: &Limits) -> Result<(), ValidationError> {
+    let Content::Shape(root) = &layer.content else {
+        return Ok(());
+    };
+    let mut stack: Vec<(&[Shape], usize)> = alloc::vec![(root, 1)];
+    while let Some((shapes, depth)) = stack.pop() {
+        if depth > limits.max_shape_depth {
+            return Err(ValidationError::ShapeDepth {
+                layer: layer.name.clone(),
+                depth,
+            });
+        }
+        for shape in shapes {
+            match shape {
+                Shape::Group(group, _) => stack.push((group, depth + 1)),
+                Shape::Repeater(repeater) => {
+                    let copies = max_copies(repeater);
+                    if copies > limits.max_repeater_copies as f64 {
+                        return Err(ValidationError::RepeaterCopies {
+                            layer: layer.name.clone(),
+                            copies: copies.round() as usize,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the largest number of copies a repeater may produce.
+fn max_copies(repeater: &Repeater) -> f64 {
+    match repeater {
+        Repeater::Fixed(repeater) => repeater.copies as f64,
+        Repeater::Animated(repeater) => match &repeater.copies {
+            Value::Fixed(copies) => *copies,
+            Value::Animated(copies) => copies.values.iter().copied().fold(0.0, f64::max),
+        },
+    }
+}
+
+/// Returns the names of the assets instanced by the layer, either as its
+/// content or by the instance brushes and patterns of its shapes.
+pub(crate) fn instance_names(layer: &Layer) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut stack: Vec<&[Shape]> = match &layer.content {
+        Content::Instance { name, .. } => {
+            names.push(name.as_str());
+            Vec::new()
+        }
+        Content::Shape(shapes) => alloc::vec![shapes],
+        _ => Vec::new(),
+    };
+    while let Some(shapes) = stack.pop() {
+        for shape in shapes {
+            match shape {
+                Shape::Group(group, _) => stack.push(group),
+                Shape::Draw(Draw {
+                    brush: Brush::Instance(InstanceBrush { name, .. }),
+                    ..
+                })
+                | Shape::Draw(Draw {
+                    brush:
+                        Brush::Pattern(PatternBrush {
+                            source: PatternSource::Instance { name, .. },
+                            ..
+                        }),
+                    ..
+                }) => names.push(name),
+                _ => {}
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn nested_assets_are_valid() {
+        let mut composition = Composition::default();
+        composition
+            .assets
+            .insert("a".into(), vec![Layer::instance("b")]);
+        composition
+            .assets
+            .insert("b".into(), vec![Layer::instance("c"), Layer::instance("c")]);
+        composition.assets.insert("c".into(), vec![]);
+        composition.layers = vec![Layer::instance("a"), Layer::instance("missing")];
+        assert_eq!(composition.validate(), Ok(()));
+    }
+
+    #[test]
+    fn instance_depth_is_limited() {
+        let mut composition = Composition::default();
+        composition
+            .assets
+            .insert("a".into(), vec![Layer::instance("b")]);
+        composition
+            .assets
+            .insert("b".into(), vec![Layer::instance("c")]);
+        composition.assets.insert("c".into(), vec![]);
+        let limits = |max_instance_depth| Limits {
+            max_instance_depth,
+            ..Limits::UNBOUNDED
+        };
+        assert_eq!(composition.validate_with_limits(&limits(3)), Ok(()));
+        assert_eq!(
+            composition.validate_with_limits(&limits(2)),
+            Err(ValidationError::InstanceDepth {
+                asset: "a".into(),
+                depth: 3
+            })
+        );
+    }
+
+    #[test]
+    fn shape_depth_is_limited() {
+        let group = |shapes| Shape::Group(shapes, None);
+        let layer = Layer {
+            name: "shapes".into(),
+            content: Content::Shape(vec![group(vec![group(vec![])])]),
+            ..Default::default()
+        };
+        let composition = Composition {
+            layers: vec![layer],
+            ..Default::default()
+        };
+        let limits = Limits {
+            max_shape_depth: 2,
+            ..Limits::UNBOUNDED
+        };
+        assert_eq!(
+            composition.validate_with_limits(&limits),
+            Err(ValidationError::ShapeDepth {
+                layer: "shapes".into(),
+                depth: 3
+            })
+        );
+    }
+
+    #[test]
+    fn cyclic_assets_are_rejected() {
+        let mut composition = Composition::default();
+        composition
+            .assets
+            .insert("a".into(), vec![Layer::instance("b")]);
+        composition
+            .assets
+            .insert("b".into(), vec![Layer::instance("a")]);
+        assert!(matches!(
+            composition.validate(),
+            Err(ValidationError::CyclicAsset(_))
+        ));
+        composition.assets.clear();
+        composition
+            .assets
+            .insert("a".into(), vec![Layer::instance("a")]);
+        assert_eq!(
+            composition.validate(),
+            Err(ValidationError::CyclicAsset("a".into()))
+        );
+    }
+
+    #[test]
+    fn instance_brushes_are_validated() {
+        let brush = Shape::Draw(Draw {
+            stroke: None,
+            brush: Brush::Instance(InstanceBrush {
+                name: "a".into(),
+                transform: Default::default(),
+            }),
+            opacity: Value::Fixed(100.0),
+        });
+        let mut composition = Composition::default();
+        composition.assets.insert(
+            "a".into(),
+            vec![Layer {
+                content: Content::Shape(vec![Shape::Group(vec![brush], None)]),
+                ..Default::default()
+            }],
+        );
+        assert_eq!(
+            composition.validate(),
+            Err(ValidationError::CyclicAsset("a".into()))
+        );
+    }
+
+    #[test]
+    fn instance_patterns_are_validated() {
+        let pattern = Shape::Draw(Draw {
+            stroke: None,
+            brush: Brush::Pattern(PatternBrush {
+                source: PatternSource::Instance {
+                    name: "b".into(),
+                    tile: kurbo::Rect::new(0.0, 0.0, 10.0, 10.0),
+                },
+                transform: Default::default(),
+            }),
+            opacity: Value::Fixed(100.0),
+        });
+        let mut composition = Composition::default();
+        composition.assets.insert(
+            "a".into(),
+            vec![Layer {
+                content: Content::Shape(vec![pattern]),
+                ..Default::default()
+            }],
+        );
+        composition
+            .assets
+            .insert("b".into(), vec![Layer::instance("a")]);
+        assert!(matches!(
+            composition.validate(),
+            Err(ValidationError::CyclicAsset(_))
+        ));
+    }
+}
@@ -1,9 +1,20 @@
 // Copyright 2024 the Interpoli Authors
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
-use alloc::vec::Vec;
+use alloc::{sync::Arc, vec::Vec};
+use core::ops::Range;
 use peniko::{self, kurbo};
 
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use hashbrown::HashMap;
+
+use crate::{
+    extrapolation::wrap, Extrapolation, Interpolation, Interpolator as _, Linear, MonotoneCubic,
+};
+
 /// Fixed or animated value.
 #[derive(Clone, Debug)]
 pub enum Value<T: Tween> {
@@ -26,6 +37,45 @@ impl<T: Tween> Value<T> {
             Self::Animated(animated) => animated.evaluate(frame),
         }
     }
+
+    /// Returns the value at the specified frame, using the hint to speed up
+    /// evaluation of nearby frames.
+    ///
+    /// See [`SeekHint`] for details.
+    pub fn evaluate_hinted(&self, frame: f64, hint: &mut SeekHint) -> T {
+        match self {
+            Self::Fixed(fixed) => fixed.clone(),
+            Self::Animated(animated) => animated.evaluate_hinted(frame, hint),
+        }
+    }
+
+    /// Returns the value at the specified frame, using the hint for this
+    /// value in the set.
+    pub fn evaluate_cached(&self, frame: f64, hints: &mut SeekHints) -> T {
+        match self {
+            Self::Fixed(fixed) => fixed.clone(),
+            Self::Animated(animated) => animated.evaluate_cached(frame, hints),
+        }
+    }
+
+    /// Appends the values at each of the specified frames to `out`.
+    ///
+    /// See [`Animated::evaluate_many`] for details.
+    pub fn evaluate_many(&self, frames: &[f64], out: &mut Vec<T>) {
+        match self {
+            Self::Fixed(fixed) => out.extend(frames.iter().map(|_| fixed.clone())),
+            Self::Animated(animated) => animated.evaluate_many(frames, out),
+        }
+    }
+
+    /// Returns true if the value is known to evaluate identically at both
+    /// frames.
+    pub fn is_constant_between(&self, a: f64, b: f64) -> bool {
+        match self {
+            Self::Fixed(_) => true,
+            Self::Animated(animated) => animated.is_constant_between(a, b),
+        }
+    }
 }
 
 impl<T: Tween> Default for Value<T> {
@@ -59,17 +109,288 @@ impl<T: Clone> ValueRef<'_, T> {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Easing {
-    pub o: EasingHandle,
-    pub i: EasingHandle,
+/// Easing function that maps linear progress through a keyframe segment to
+/// eased progress.
+#[derive(Clone)]
+pub enum Easing {
+    /// Cubic bezier curve from (0, 0) to (1, 1) with the given control
+    /// points.
+    Bezier { o: EasingHandle, i: EasingHandle },
+    /// Holds the value of the starting keyframe until the next keyframe,
+    /// as with Lottie hold keyframes.
+    Hold,
+    /// Divides the segment into the given number of equal steps, jumping
+    /// between values at step boundaries as with the CSS `steps()`
+    /// function.
+    Steps(u32, JumpTerm),
+    /// User provided easing function.
+    Custom(Arc<dyn EasingFn>),
+}
+
+/// Position of the jumps of [`Easing::Steps`], matching the CSS
+/// `<step-position>` keywords.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum JumpTerm {
+    /// The first jump happens at the start of the segment.
+    JumpStart,
+    /// The last jump happens at the end of the segment.
+    #[default]
+    JumpEnd,
+    /// There is no jump at either end, so the first and last steps hold
+    /// the keyframe values.
+    JumpNone,
+    /// There are jumps at both the start and the end of the segment.
+    JumpBoth,
+}
+
+impl core::fmt::Debug for Easing {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Bezier { o, i } => f
+                .debug_struct("Bezier")
+                .field("o", o)
+                .field("i", i)
+                .finish(),
+            Self::Hold => f.write_str("Hold"),
+            Self::Steps(steps, jump) => f.debug_tuple("Steps").field(steps).field(jump).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
 }
 
 impl Easing {
-    pub const LERP: Easing = Easing {
+    pub const LERP: Easing = Easing::Bezier {
         o: EasingHandle { x: 0.0, y: 0.0 },
         i: EasingHandle { x: 1.0, y: 1.0 },
     };
+
+    /// Creates an easing from the two control points of a cubic bezier
+    /// curve from (0, 0) to (1, 1), as with the CSS `cubic-bezier()`
+    /// function.
+    ///
+    /// The x coordinates are clamped to the range 0 to 1 so that the curve
+    /// is a function of time.
+    pub fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        Self::Bezier {
+            o: EasingHandle {
+                x: x1.clamp(0.0, 1.0),
+                y: y1,
+            },
+            i: EasingHandle {
+                x: x2.clamp(0.0, 1.0),
+                y: y2,
+            },
+        }
+    }
+
+    /// Creates an easing from a user provided function or closure.
+    pub fn custom(f: impl EasingFn + 'static) -> Self {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// Returns the easing that plays this one backwards in time, such that
+    /// `reversed.evaluate(t) == 1 - self.evaluate(1 - t)`.
+    ///
+    /// For stepped easing this holds everywhere except exactly at the step
+    /// boundaries.
+    #[must_use]
+    pub fn reversed(&self) -> Self {
+        match self {
+            Self::Bezier { o, i } => Self::Bezier {
+                o: EasingHandle {
+                    x: 1.0 - i.x,
+                    y: 1.0 - i.y,
+                },
+                i: EasingHandle {
+                    x: 1.0 - o.x,
+                    y: 1.0 - o.y,
+                },
+            },
+            Self::Hold => Self::Steps(1, JumpTerm::JumpStart),
+            Self::Steps(steps, jump) => Self::Steps(
+                *steps,
+                match jump {
+                    JumpTerm::JumpStart => JumpTerm::JumpEnd,
+                    JumpTerm::JumpEnd => JumpTerm::JumpStart,
+                    term => *term,
+                },
+            ),
+            Self::Custom(f) => {
+                let f = f.clone();
+                Self::custom(move |t: f64| 1.0 - f.ease(1.0 - t))
+            }
+        }
+    }
+
+    /// Returns an easing that follows this one over the first half of the
+    /// segment and its reverse over the second half, such as turning an
+    /// ease-in into an ease-in-out.
+    #[must_use]
+    pub fn mirrored(&self) -> Self {
+        self.then(&self.reversed())
+    }
+
+    /// Returns an easing that follows this one over the first half of the
+    /// segment, reaching half of the progress, and `other` over the second
+    /// half.
+    #[must_use]
+    pub fn then(&self, other: &Self) -> Self {
+        self.then_at(other, EasingHandle { x: 0.5, y: 0.5 })
+    }
+
+    /// Returns an easing that combines two curves on one segment, joined at
+    /// the given midpoint.
+    ///
+    /// This easing is followed until the linear progress reaches
+    /// `midpoint.x`, at which point the eased progress is `midpoint.y`, and
+    /// `other` is followed from there to the end of the segment. For
+    /// example, an exponential ease-out can run into a bouncing ease-in
+    /// without an intermediate keyframe.
+    ///
+    /// The midpoint is clamped to the unit square.
+    #[must_use]
+    pub fn then_at(&self, other: &Self, midpoint: EasingHandle) -> Self {
+        let (first, second) = (self.clone(), other.clone());
+        let x = midpoint.x.clamp(0.0, 1.0);
+        let y = midpoint.y.clamp(0.0, 1.0);
+        Self::custom(move |t: f64| {
+            if t < x {
+                first.evaluate(t / x) * y
+            } else if x < 1.0 {
+                y + second.evaluate((t - x) / (1.0 - x)) * (1.0 - y)
+            } else {
+                1.0
+            }
+        })
+    }
+
+    /// Returns an easing that follows the portion of this one between the
+    /// given linear progress values, stretched to cover the whole segment.
+    ///
+    /// For example, `scaled(0.0..0.5)` of an ease-in-out is an ease-in.
+    /// Returns a linear easing if the portion makes no progress.
+    #[must_use]
+    pub fn scaled(&self, segment: Range<f64>) -> Self {
+        let start = self.evaluate(segment.start);
+        let end = self.evaluate(segment.end);
+        if start == end {
+            return Self::LERP;
+        }
+        let easing = self.clone();
+        Self::custom(move |t: f64| {
+            let t = segment.start + (segment.end - segment.start) * t;
+            (easing.evaluate(t) - start) / (end - start)
+        })
+    }
+
+    /// Returns the eased progress for the given linear progress in the
+    /// range 0 to 1.
+    pub fn evaluate(&self, t: f64) -> f64 {
+        match self {
+            Self::Bezier { o, i } => {
+                // NaN occurs for zero length segments.
+                if t.is_nan() || t <= 0.0 {
+                    return 0.0;
+                }
+                if t >= 1.0 {
+                    return 1.0;
+                }
+                let s = solve_x(o.x, i.x, t);
+                bezier(o.y, i.y, s)
+            }
+            Self::Hold => 0.0,
+            Self::Steps(steps, jump) => {
+                let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+                let steps = (*steps).max(1) as f64;
+                let mut step = (t * steps).floor();
+                if matches!(jump, JumpTerm::JumpStart | JumpTerm::JumpBoth) {
+                    step += 1.0;
+                }
+                let jumps = match jump {
+                    JumpTerm::JumpStart | JumpTerm::JumpEnd => steps,
+                    JumpTerm::JumpNone => steps - 1.0,
+                    JumpTerm::JumpBoth => steps + 1.0,
+                };
+                if jumps > 0.0 {
+                    (step / jumps).min(1.0)
+                } else {
+                    // A single step without jumps never leaves the start.
+                    0.0
+                }
+            }
+            Self::Custom(f) => f.ease(if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) }),
+        }
+    }
+}
+
+/// Function that maps linear progress to eased progress, for use with
+/// [`Easing::Custom`].
+///
+/// This is implemented for all closures of type `Fn(f64) -> f64`.
+pub trait EasingFn: Send + Sync {
+    /// Returns the eased progress for linear progress `t` in the range 0
+    /// to 1. This should return 0 at 0 and 1 at 1 so that the animation is
+    /// continuous at keyframes; values outside the range overshoot the
+    /// keyframe values.
+    fn ease(&self, t: f64) -> f64;
+}
+
+impl<F: Fn(f64) -> f64 + Send + Sync> EasingFn for F {
+    fn ease(&self, t: f64) -> f64 {
+        self(t)
+    }
+}
+
+/// Returns the curve parameter at which the x coordinate equals `x`.
+fn solve_x(x1: f64, x2: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-12;
+    // Newton's method converges quickly for most curves.
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, s) - x;
+        if error.abs() < EPSILON {
+            return s;
+        }
+        let slope = bezier_slope(x1, x2, s);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s -= error / slope;
+    }
+    // Fall back to bisection, which always converges since x is
+    // monotonic in the parameter.
+    let (mut lo, mut hi) = (0.0, 1.0);
+    s = x;
+    for _ in 0..64 {
+        let error = bezier(x1, x2, s) - x;
+        if error.abs() < EPSILON {
+            break;
+        }
+        if error > 0.0 {
+            hi = s;
+        } else {
+            lo = s;
+        }
+        s = (lo + hi) * 0.5;
+    }
+    s
+}
+
+/// Evaluates one coordinate of a cubic bezier from 0 to 1 with the given
+/// control point coordinates.
+fn bezier(c1: f64, c2: f64, s: f64) -> f64 {
+    let a = 1.0 - 3.0 * c2 + 3.0 * c1;
+    let b = 3.0 * c2 - 6.0 * c1;
+    let c = 3.0 * c1;
+    ((a * s + b) * s + c) * s
+}
+
+/// Returns the derivative of [`bezier`] with respect to the parameter.
+fn bezier_slope(c1: f64, c2: f64, s: f64) -> f64 {
+    let a = 1.0 - 3.0 * c2 + 3.0 * c1;
+    let b = 3.0 * c2 - 6.0 * c1;
+    let c = 3.0 * c1;
+    (3.0 * a * s + 2.0 * b) * s + c
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -79,7 +400,11 @@ pub struct EasingHandle {
 }
 
 /// Time for a particular keyframe, represented as a frame number.
-#[derive(Copy, Clone, Debug)]
+///
+/// Use [`Time::new`] to create a keyframe time and set the fields that
+/// differ from the defaults.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct Time {
     /// Frame number.
     pub frame: f64,
@@ -89,68 +414,363 @@ pub struct Time {
     pub out_tangent: Option<EasingHandle>,
     /// Whether it's a hold frame.
     pub hold: bool,
+    /// Easing going into the next keyframe. When set, this takes precedence
+    /// over the tangents.
+    pub easing: Option<Easing>,
 }
 
 impl Time {
+    /// Creates a keyframe time at the given frame, with linear easing into
+    /// the next keyframe.
+    pub fn new(frame: f64) -> Self {
+        Self {
+            frame,
+            in_tangent: None,
+            out_tangent: None,
+            hold: false,
+            easing: None,
+        }
+    }
+
+    /// Returns the keyframe time with the given easing into the next
+    /// keyframe.
+    ///
+    /// Cubic bezier curves are stored as the tangents of the keyframe.
+    #[must_use]
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.set_easing(easing);
+        self
+    }
+
     /// Returns the frame indices and interpolation weight for the given frame,
     /// and whether to hold the frame
     pub(crate) fn frames_and_weight(
         times: &[Time],
         frame: f64,
-    ) -> Option<([usize; 2], f64, Easing, bool)> {
+    ) -> Option<([usize; 2], f64, ValueRef<'_, Easing>, bool)> {
+        Self::frames_and_weight_hinted(times, frame, &mut SeekHint::default())
+    }
+
+    /// Same as [`Time::frames_and_weight`], but checks the segment recorded
+    /// in the hint and its successor before searching, and records the
+    /// segment found.
+    pub(crate) fn frames_and_weight_hinted<'a>(
+        times: &'a [Time],
+        frame: f64,
+        hint: &mut SeekHint,
+    ) -> Option<([usize; 2], f64, ValueRef<'a, Easing>, bool)> {
         if times.is_empty() {
             return None;
         }
-        use core::cmp::Ordering::*;
-        let ix = match times.binary_search_by(|x| {
-            if x.frame < frame {
-                Less
-            } else if x.frame > frame {
-                Greater
-            } else {
-                Equal
-            }
-        }) {
-            Ok(ix) => ix,
-            Err(ix) => ix.saturating_sub(1),
+        // The segment starting at `ix` contains the frame if it starts at or
+        // before the frame and the next keyframe is after it.
+        let contains = |ix: usize| {
+            times.get(ix).is_some_and(|time| time.frame <= frame)
+                && times.get(ix + 1).map_or(true, |next| frame < next.frame)
+        };
+        let ix = if contains(hint.segment) {
+            hint.segment
+        } else if contains(hint.segment + 1) {
+            hint.segment + 1
+        } else {
+            times
+                .partition_point(|time| time.frame <= frame)
+                .saturating_sub(1)
         };
+        hint.segment = ix;
         let ix0 = ix.min(times.len() - 1);
         let ix1 = (ix0 + 1).min(times.len() - 1);
 
-        let t0 = times[ix0];
-        let t1 = times[ix1];
-        let (t0_ox, t0_oy) = t0.out_tangent.map(|o| (o.x, o.y)).unwrap_or((0.0, 0.0));
-        let (t0_ix, t0_iy) = t0.in_tangent.map(|i| (i.x, i.y)).unwrap_or((1.0, 1.0));
-        let easing = Easing {
-            o: EasingHandle { x: t0_ox, y: t0_oy },
-            i: EasingHandle { x: t0_ix, y: t0_iy },
-        };
+        let t0 = &times[ix0];
+        let t1 = &times[ix1];
+        let easing = t0.easing();
         let hold = t0.hold;
         let t = (frame - t0.frame) / (t1.frame - t0.frame);
         Some(([ix0, ix1], t.clamp(0.0, 1.0), easing, hold))
     }
+
+    /// Sets the easing for the segment starting at this keyframe, storing
+    /// cubic bezier curves as tangents.
+    pub(crate) fn set_easing(&mut self, easing: Easing) {
+        match easing {
+            Easing::Bezier { o, i } => {
+                self.out_tangent = Some(o);
+                self.in_tangent = Some(i);
+                self.easing = None;
+            }
+            _ => self.easing = Some(easing),
+        }
+    }
+
+    /// Returns the easing for the segment starting at this keyframe.
+    pub(crate) fn easing(&self) -> ValueRef<'_, Easing> {
+        if let Some(easing) = &self.easing {
+            return ValueRef::Borrowed(easing);
+        }
+        let (o_x, o_y) = self.out_tangent.map(|o| (o.x, o.y)).unwrap_or((0.0, 0.0));
+        let (i_x, i_y) = self.in_tangent.map(|i| (i.x, i.y)).unwrap_or((1.0, 1.0));
+        ValueRef::Owned(Easing::Bezier {
+            o: EasingHandle { x: o_x, y: o_y },
+            i: EasingHandle { x: i_x, y: i_y },
+        })
+    }
+
+    /// Same as [`Time::is_constant_between`], but also considers the
+    /// behavior outside of the keyframes.
+    pub(crate) fn is_constant_over(
+        times: &[Time],
+        extrapolation: Extrapolation,
+        a: f64,
+        b: f64,
+    ) -> bool {
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return true;
+        };
+        let in_range = |frame: f64| (first.frame..=last.frame).contains(&frame);
+        // Frames outside of the keyframes only repeat the first or last
+        // value when holding.
+        (extrapolation == Extrapolation::Hold || (in_range(a) && in_range(b)))
+            && Self::is_constant_between(times, a, b)
+    }
+
+    /// Returns true if keyframes with the given times are known to produce
+    /// the same value at both frames. This is the case when both frames lie
+    /// on the same side outside of the keyframe range or within the same
+    /// hold segment.
+    pub(crate) fn is_constant_between(times: &[Time], a: f64, b: f64) -> bool {
+        let (Some(first), Some(last)) = (times.first(), times.last()) else {
+            return true;
+        };
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        if hi <= first.frame || lo >= last.frame {
+            return true;
+        }
+        let ix = times.partition_point(|x| x.frame <= lo);
+        if ix == 0 {
+            return false;
+        }
+        let t0 = &times[ix - 1];
+        (t0.hold || matches!(t0.easing, Some(Easing::Hold)))
+            && times.get(ix).map_or(true, |t1| hi < t1.frame)
+    }
+}
+
+/// Position of the most recently evaluated segment of an animated value.
+///
+/// Evaluating an animated value takes time logarithmic in the number of
+/// keyframes. When frames are evaluated in order, as during playback,
+/// passing the same hint to each evaluation finds the segment in constant
+/// time instead. The hint is owned by the caller, so animations can be
+/// shared and evaluated from multiple threads, each with its own hint.
+///
+/// A hint only affects performance. A stale hint, or one last used with a
+/// different animation, still produces the correct value.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SeekHint {
+    segment: usize,
+}
+
+/// Set of seek hints for many animated values, such as all of the
+/// properties of a composition.
+///
+/// Hints are looked up by the address of the keyframes of each animated
+/// value. As with [`SeekHint`], hints that become stale when animations are
+/// modified or dropped only affect performance, but the set should be
+/// cleared when switching to a different composition to avoid growing
+/// without bound.
+#[derive(Clone, Default, Debug)]
+pub struct SeekHints {
+    hints: HashMap<usize, SeekHint>,
+}
+
+impl SeekHints {
+    /// Creates an empty set of hints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all hints.
+    pub fn clear(&mut self) {
+        self.hints.clear();
+    }
+
+    /// Returns the hint for the given animated value.
+    pub fn get<T: Tween>(&mut self, animated: &Animated<T>) -> &mut SeekHint {
+        let key = animated.times.as_ptr() as usize;
+        self.hints.entry(key).or_default()
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Animated<T: Tween> {
     pub times: Vec<Time>,
     pub values: Vec<T>,
+    /// Behavior outside of the range of the keyframes.
+    pub extrapolation: Extrapolation,
+    /// Interpolation between keyframes, unless overridden by a hold
+    /// keyframe.
+    pub interpolation: Interpolation,
 }
 
 impl<T: Tween> Animated<T> {
+    /// Returns true if the value is known to evaluate identically at both
+    /// frames.
+    pub fn is_constant_between(&self, a: f64, b: f64) -> bool {
+        if Time::is_constant_over(&self.times, self.extrapolation, a, b) {
+            return true;
+        }
+        let (Some(first), Some(last)) = (self.times.first(), self.times.last()) else {
+            return true;
+        };
+        // Held values only change at keyframes.
+        let in_range = |frame: f64| (first.frame..=last.frame).contains(&frame);
+        let segment = |frame: f64| self.times.partition_point(|time| time.frame <= frame);
+        matches!(self.interpolation, Interpolation::Hold)
+            && (self.extrapolation == Extrapolation::Hold || (in_range(a) && in_range(b)))
+            && segment(a) == segment(b)
+    }
+
     /// Returns the value at the specified frame.
+    ///
+    /// This takes time logarithmic in the number of keyframes.
     pub fn evaluate(&self, frame: f64) -> T {
-        self.evaluate_inner(frame).unwrap_or_default()
+        self.evaluate_hinted(frame, &mut SeekHint::default())
+    }
+
+    /// Returns the value at the specified frame, starting the search for
+    /// the surrounding keyframes at the segment recorded in the hint.
+    ///
+    /// This takes constant time when the frame is within the recorded
+    /// segment or the one after it, and logarithmic time otherwise.
+    pub fn evaluate_hinted(&self, frame: f64, hint: &mut SeekHint) -> T {
+        self.extrapolation.apply(&self.times, frame, |frame| {
+            self.evaluate_inner(frame, hint).unwrap_or_default()
+        })
+    }
+
+    /// Returns the value at the specified frame, using the hint for this
+    /// animation in the set.
+    pub fn evaluate_cached(&self, frame: f64, hints: &mut SeekHints) -> T {
+        self.evaluate_hinted(frame, hints.get(self))
+    }
+
+    /// Appends the values at each of the specified frames to `out`.
+    ///
+    /// The keyframe search is shared between consecutive frames, so
+    /// evaluating frames in increasing order takes constant time per frame.
+    pub fn evaluate_many(&self, frames: &[f64], out: &mut Vec<T>) {
+        let mut hint = SeekHint::default();
+        out.reserve(frames.len());
+        out.extend(
+            frames
+                .iter()
+                .map(|&frame| self.evaluate_hinted(frame, &mut hint)),
+        );
+    }
+
+    /// Returns the keyframe indices and interpolation weight for the given
+    /// frame, and whether to hold the value of the first keyframe.
+    pub(crate) fn segment(
+        &self,
+        frame: f64,
+        hint: &mut SeekHint,
+    ) -> Option<([usize; 2], f64, ValueRef<'_, Easing>, bool)> {
+        let (frames, t, easing, hold) = Time::frames_and_weight_hinted(&self.times, frame, hint)?;
+        let hold = hold || matches!(self.interpolation, Interpolation::Hold);
+        Some((frames, t, easing, hold))
     }
 
-    fn evaluate_inner(&self, frame: f64) -> Option<T> {
-        let ([ix0, ix1], t, easing, hold) = Time::frames_and_weight(&self.times, frame)?;
+    fn evaluate_inner(&self, frame: f64, hint: &mut SeekHint) -> Option<T> {
+        let ([ix0, ix1], t, easing, hold) = self.segment(frame, hint)?;
         let t = if hold { 0.0 } else { t };
 
+        if !hold && ix0 != ix1 && t > 0.0 && t < 1.0 {
+            if let Some(value) = T::interpolate(self, ix0, t) {
+                return Some(value);
+            }
+        }
+
         let v1 = self.values.get(ix0)?;
         let v2 = self.values.get(ix1)?;
 
-        Some(v1.tween(v2, t, &easing))
+        Some(v1.tween(v2, t, easing.as_ref()))
+    }
+}
+
+/// Distance in frames used to estimate the velocity of an animation.
+const VELOCITY_STEP: f64 = 1e-2;
+
+impl<T: Linear> Animated<T> {
+    /// Returns the rate of change of the value at the specified frame, in
+    /// units per frame.
+    ///
+    /// The velocity is estimated from nearby values within the same
+    /// keyframe segment, so it doesn't average across keyframes where the
+    /// motion changes abruptly. At a keyframe, this is the velocity of the
+    /// segment that starts there.
+    pub fn velocity(&self, frame: f64) -> T {
+        let (start, end) = self.segment_bounds(frame);
+        let step = VELOCITY_STEP.min((end - start) / 2.0);
+        // Differences are one-sided near the ends of the segment.
+        let (a, b) = if frame - step < start {
+            (frame, frame + step)
+        } else if frame + step >= end {
+            (frame - step, frame)
+        } else {
+            (frame - step, frame + step)
+        };
+        self.evaluate(b)
+            .add_scaled(self.evaluate(a), -1.0)
+            .map_components(|c| c / (b - a))
+    }
+
+    /// Returns the frames of the keyframes around `frame`, including the
+    /// repetitions of a looping extrapolation. The segment includes its
+    /// start but not its end.
+    fn segment_bounds(&self, frame: f64) -> (f64, f64) {
+        let bounds = |at: f64| {
+            let ix = self.times.partition_point(|time| time.frame <= at);
+            let start = ix
+                .checked_sub(1)
+                .map_or(f64::NEG_INFINITY, |ix| self.times[ix].frame);
+            let end = self.times.get(ix).map_or(f64::INFINITY, |time| time.frame);
+            (start, end)
+        };
+        let (Some(first), Some(last)) = (self.times.first(), self.times.last()) else {
+            return bounds(frame);
+        };
+        let (start, duration) = (first.frame, last.frame - first.frame);
+        if (start..last.frame).contains(&frame) || !frame.is_finite() || duration <= 0.0 {
+            return bounds(frame);
+        }
+        match self.extrapolation {
+            Extrapolation::Hold | Extrapolation::Linear => bounds(frame),
+            // The last keyframe ends the final segment rather than starting
+            // a repetition.
+            _ if frame == last.frame => {
+                let ix = self.times.partition_point(|time| time.frame < frame);
+                (self.times[ix - 1].frame, frame)
+            }
+            Extrapolation::Loop => {
+                let local = start + wrap(frame - start, duration);
+                let (a, b) = bounds(local);
+                (a + frame - local, b + frame - local)
+            }
+            Extrapolation::PingPong => {
+                let phase = wrap(frame - start, duration * 2.0);
+                if phase > duration {
+                    // Played backwards, so the local frame decreases as
+                    // the frame increases.
+                    let local = start + duration * 2.0 - phase;
+                    let (a, b) = bounds(local);
+                    (frame + local - b, frame + local - a)
+                } else {
+                    let local = start + phase;
+                    let (a, b) = bounds(local);
+                    (a + frame - local, b + frame - local)
+                }
+            }
+        }
     }
 }
 
@@ -158,19 +778,46 @@ impl<T: Tween> Animated<T> {
 pub trait Tween: Clone + Default {
     #[must_use]
     fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self;
+
+    /// Returns the value continuing past this one, away from `previous`, by
+    /// `scale` times the difference between them.
+    ///
+    /// This is used for [`Extrapolation::Linear`]. Values that can't be
+    /// extrapolated return `None`, which is the default.
+    #[must_use]
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        let _ = (previous, scale);
+        None
+    }
+
+    /// Returns the value within the segment of `animated` starting at the
+    /// keyframe at index `segment`, following its
+    /// [interpolation](Animated::interpolation).
+    ///
+    /// `t` is the linear progress through the segment. Values that only
+    /// support keyframe easing return `None`, which is the default.
+    #[must_use]
+    fn interpolate(animated: &Animated<Self>, segment: usize, t: f64) -> Option<Self> {
+        let _ = (animated, segment, t);
+        None
+    }
 }
 
 impl Tween for f64 {
     fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
-        keyframe::ease(
-            keyframe::functions::BezierCurve::from(
-                keyframe::mint::Vector2::from_slice(&[easing.o.x, easing.o.y]),
-                keyframe::mint::Vector2::from_slice(&[easing.i.x, easing.i.y]),
-            ),
-            *self,
-            *other,
-            t,
-        )
+        let t = easing.evaluate(t);
+        self + (other - self) * t
+    }
+
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        Some(self + (self - previous) * scale)
+    }
+
+    fn interpolate(animated: &Animated<Self>, segment: usize, t: f64) -> Option<Self> {
+        match animated.interpolation {
+            Interpolation::MonotoneCubic => Some(MonotoneCubic.interpolate(animated, segment, t)),
+            _ => crate::interpolate::interpolate_linear(animated, segment, t),
+        }
     }
 }
 
@@ -181,6 +828,16 @@ impl Tween for kurbo::Point {
             self.y.tween(&other.y, t, easing),
         )
     }
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        Some(Self::new(
+            self.x.extrapolate(&previous.x, scale)?,
+            self.y.extrapolate(&previous.y, scale)?,
+        ))
+    }
+
+    fn interpolate(animated: &Animated<Self>, segment: usize, t: f64) -> Option<Self> {
+        crate::interpolate::interpolate_linear(animated, segment, t)
+    }
 }
 
 impl Tween for kurbo::Vec2 {
@@ -190,6 +847,16 @@ impl Tween for kurbo::Vec2 {
             self.y.tween(&other.y, t, easing),
         )
     }
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        Some(Self::new(
+            self.x.extrapolate(&previous.x, scale)?,
+            self.y.extrapolate(&previous.y, scale)?,
+        ))
+    }
+
+    fn interpolate(animated: &Animated<Self>, segment: usize, t: f64) -> Option<Self> {
+        crate::interpolate::interpolate_linear(animated, segment, t)
+    }
 }
 
 impl Tween for kurbo::Size {
@@ -199,6 +866,16 @@ impl Tween for kurbo::Size {
             self.height.tween(&other.height, t, easing),
         )
     }
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        Some(Self::new(
+            self.width.extrapolate(&previous.width, scale)?,
+            self.height.extrapolate(&previous.height, scale)?,
+        ))
+    }
+
+    fn interpolate(animated: &Animated<Self>, segment: usize, t: f64) -> Option<Self> {
+        crate::interpolate::interpolate_linear(animated, segment, t)
+    }
 }
 
 impl Tween for peniko::Color {
@@ -210,3 +887,361 @@ impl Tween for peniko::Color {
         peniko::Color::rgba(r, g, b, a)
     }
 }
+
+impl Tween for kurbo::Rect {
+    fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
+        Self::new(
+            self.x0.tween(&other.x0, t, easing),
+            self.y0.tween(&other.y0, t, easing),
+            self.x1.tween(&other.x1, t, easing),
+            self.y1.tween(&other.y1, t, easing),
+        )
+    }
+}
+
+/// Affine transforms are decomposed into translation, rotation, scale and
+/// skew, which are interpolated separately. This avoids the shrinking that
+/// occurs when interpolating the coefficients of rotating transforms.
+impl Tween for kurbo::Affine {
+    fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
+        let a = Decomposed::new(*self);
+        let b = Decomposed::new(*other);
+        // Rotate the shortest way around.
+        let mut angle = b.angle - a.angle;
+        if angle > core::f64::consts::PI {
+            angle -= core::f64::consts::TAU;
+        } else if angle < -core::f64::consts::PI {
+            angle += core::f64::consts::TAU;
+        }
+        Decomposed {
+            translation: a.translation.tween(&b.translation, t, easing),
+            angle: a.angle.tween(&(a.angle + angle), t, easing),
+            scale: a.scale.tween(&b.scale, t, easing),
+            skew: a.skew.tween(&b.skew, t, easing),
+        }
+        .compose()
+    }
+}
+
+/// Affine transform decomposed as a translation of a rotation of an upper
+/// triangular matrix.
+struct Decomposed {
+    translation: kurbo::Vec2,
+    angle: f64,
+    scale: kurbo::Vec2,
+    skew: f64,
+}
+
+impl Decomposed {
+    fn new(transform: kurbo::Affine) -> Self {
+        let [a, b, c, d, e, f] = transform.as_coeffs();
+        let angle = b.atan2(a);
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            translation: kurbo::Vec2::new(e, f),
+            angle,
+            scale: kurbo::Vec2::new(a.hypot(b), cos * d - sin * c),
+            skew: cos * c + sin * d,
+        }
+    }
+
+    fn compose(&self) -> kurbo::Affine {
+        let (sin, cos) = self.angle.sin_cos();
+        let Self {
+            translation,
+            scale,
+            skew,
+            ..
+        } = *self;
+        kurbo::Affine::new([
+            scale.x * cos,
+            scale.x * sin,
+            skew * cos - scale.y * sin,
+            skew * sin + scale.y * cos,
+            translation.x,
+            translation.y,
+        ])
+    }
+}
+
+impl<const N: usize> Tween for [f64; N]
+where
+    Self: Default,
+{
+    fn tween(&self, other: &Self, t: f64, easing: &Easing) -> Self {
+        core::array::from_fn(|i| self[i].tween(&other[i], t, easing))
+    }
+    fn extrapolate(&self, previous: &Self, scale: f64) -> Option<Self> {
+        Some(core::array::from_fn(|i| {
+            self[i] + (self[i] - previous[i]) * scale
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cubic_bezier_matches_css() {
+        // Reference values for the CSS `ease` and `ease-in-out` curves.
+        let ease = Easing::cubic_bezier(0.25, 0.1, 0.25, 1.0);
+        assert!(
+            (ease.evaluate(0.5) - 0.802_403_387_7).abs() < 1e-9,
+            "ease should match the reference at 0.5"
+        );
+        let ease_in_out = Easing::cubic_bezier(0.42, 0.0, 0.58, 1.0);
+        assert!(
+            (ease_in_out.evaluate(0.5) - 0.5).abs() < 1e-12,
+            "ease-in-out should be symmetric"
+        );
+        assert_eq!(ease.evaluate(0.0), 0.0);
+        assert_eq!(ease.evaluate(1.0), 1.0);
+        assert_eq!(ease.evaluate(f64::NAN), 0.0);
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            assert!(
+                (Easing::LERP.evaluate(t) - t).abs() < 1e-9,
+                "linear easing should be the identity"
+            );
+        }
+    }
+
+    #[test]
+    fn custom_easing_drives_animation() {
+        let animated = Animated {
+            times: alloc::vec![
+                Time::new(0.0).with_easing(Easing::custom(|t: f64| t * t)),
+                Time::new(10.0),
+            ],
+            values: alloc::vec![0.0, 100.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        assert!(
+            (animated.evaluate(5.0) - 25.0).abs() < 1e-9,
+            "custom easing should replace the tangents"
+        );
+        let reversed = animated.times[0].easing().into_owned().reversed();
+        assert!(
+            (reversed.evaluate(0.5) - 0.75).abs() < 1e-9,
+            "reversed easing should mirror the custom function"
+        );
+    }
+
+    #[test]
+    fn hold_keyframes_and_hold_interpolation() {
+        let mut hold = Time::new(10.0);
+        hold.hold = true;
+        let mut animated = Animated {
+            times: alloc::vec![Time::new(0.0), hold, Time::new(20.0), Time::new(30.0)],
+            values: alloc::vec![0.0, 10.0, 20.0, 30.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        assert_eq!(animated.evaluate(5.0), 5.0);
+        assert_eq!(animated.evaluate(15.0), 10.0, "hold keyframes should hold");
+        assert_eq!(animated.evaluate(20.0), 20.0);
+        assert!(animated.is_constant_between(12.0, 18.0));
+        assert!(!animated.is_constant_between(22.0, 28.0));
+
+        animated.interpolation = Interpolation::Hold;
+        for (frame, value) in [(5.0, 0.0), (15.0, 10.0), (25.0, 20.0), (30.0, 30.0)] {
+            assert_eq!(
+                animated.evaluate(frame),
+                value,
+                "every keyframe should hold at frame {frame}"
+            );
+        }
+        assert!(animated.is_constant_between(21.0, 29.0));
+        assert!(!animated.is_constant_between(19.0, 21.0));
+    }
+
+    #[test]
+    fn velocity_stays_within_segments() {
+        let mut hold = Time::new(10.0);
+        hold.hold = true;
+        let mut animated = Animated {
+            times: alloc::vec![Time::new(0.0), hold, Time::new(20.0)],
+            values: alloc::vec![0.0, 10.0, 20.0],
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+        assert!(close(animated.velocity(5.0), 1.0));
+        assert!(close(animated.velocity(9.999), 1.0), "before a keyframe");
+        assert!(close(animated.velocity(10.0), 0.0), "at a keyframe");
+        assert!(close(animated.velocity(19.999), 0.0), "before a jump");
+        assert!(
+            close(animated.velocity(20.0), 0.0),
+            "after the last keyframe"
+        );
+
+        animated.extrapolation = Extrapolation::Loop;
+        assert!(close(animated.velocity(20.001), 1.0), "after looping");
+        assert!(
+            close(animated.velocity(29.999), 1.0),
+            "before a repeated keyframe"
+        );
+        assert!(
+            close(animated.velocity(39.999), 0.0),
+            "before looping again"
+        );
+        animated.extrapolation = Extrapolation::PingPong;
+        assert!(close(animated.velocity(35.0), -1.0), "playing backwards");
+        assert!(
+            close(animated.velocity(29.999), 0.0),
+            "before a repeated keyframe"
+        );
+        assert!(
+            close(animated.velocity(30.001), -1.0),
+            "after a repeated keyframe"
+        );
+        assert!(close(animated.velocity(40.0), 1.0), "at the turning point");
+    }
+
+    #[test]
+    fn steps_match_css() {
+        let eval = |easing: Easing| {
+            [0.0, 0.2, 0.5, 0.99, 1.0]
+                .iter()
+                .map(|t| easing.evaluate(*t))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            eval(Easing::Steps(4, JumpTerm::JumpEnd)),
+            [0.0, 0.0, 0.5, 0.75, 1.0]
+        );
+        assert_eq!(
+            eval(Easing::Steps(4, JumpTerm::JumpStart)),
+            [0.25, 0.25, 0.75, 1.0, 1.0]
+        );
+        assert_eq!(
+            eval(Easing::Steps(2, JumpTerm::JumpNone)),
+            [0.0, 0.0, 1.0, 1.0, 1.0]
+        );
+        assert_eq!(
+            eval(Easing::Steps(3, JumpTerm::JumpBoth)),
+            [0.25, 0.25, 0.5, 0.75, 1.0]
+        );
+        assert_eq!(eval(Easing::Hold), [0.0; 5]);
+    }
+
+    #[test]
+    fn combinators_build_from_presets() {
+        let ease_in = Easing::cubic_bezier(0.42, 0.0, 1.0, 1.0);
+        let ease_in_out = Easing::cubic_bezier(0.42, 0.0, 0.58, 1.0);
+        let mirrored = ease_in.mirrored();
+        let scaled = ease_in_out.scaled(0.0..0.5);
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            assert!(
+                (mirrored.evaluate(t) - (1.0 - mirrored.evaluate(1.0 - t))).abs() < 1e-9,
+                "mirrored easing should be symmetric"
+            );
+            let expected = ease_in_out.evaluate(t * 0.5) * 2.0;
+            assert!(
+                (scaled.evaluate(t) - expected).abs() < 1e-9,
+                "scaled easing should follow the selected portion"
+            );
+        }
+        let chained = Easing::LERP.then(&ease_in);
+        assert_eq!(chained.evaluate(0.25), 0.25);
+        assert_eq!(chained.evaluate(1.0), 1.0);
+        let ease_out = ease_in.reversed();
+        let composite = ease_out.then_at(&ease_in, EasingHandle { x: 0.25, y: 0.75 });
+        assert_eq!(composite.evaluate(0.25), 0.75);
+        assert_eq!(composite.evaluate(1.0), 1.0);
+        assert!(
+            (composite.evaluate(0.125) - ease_out.evaluate(0.5) * 0.75).abs() < 1e-9,
+            "the first curve should be compressed into its portion"
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_tweens_each_field() {
+        #[derive(Clone, Default, Debug, PartialEq, crate::Tween)]
+        struct HealthBar {
+            color: peniko::Color,
+            width: f64,
+        }
+        #[derive(Clone, Default, Debug, PartialEq, crate::Tween)]
+        struct Pair<T>(T, T);
+
+        let a = HealthBar {
+            color: peniko::Color::rgb8(0, 255, 0),
+            width: 100.0,
+        };
+        let b = HealthBar {
+            color: peniko::Color::rgb8(255, 0, 0),
+            width: 0.0,
+        };
+        let mid = a.tween(&b, 0.5, &Easing::LERP);
+        assert_eq!(mid.width, 50.0);
+        assert_eq!((mid.color.r, mid.color.g), (128, 128));
+        let pair = Pair(0.0, 10.0).tween(&Pair(10.0, 20.0), 0.5, &Easing::LERP);
+        assert_eq!(pair, Pair(5.0, 15.0));
+    }
+
+    #[test]
+    fn affine_tween_rotates() {
+        let a = kurbo::Affine::rotate(0.0).then_translate((10.0, 0.0).into());
+        let b = kurbo::Affine::rotate(core::f64::consts::PI)
+            .pre_scale(2.0)
+            .then_translate((20.0, 10.0).into());
+        let expected = kurbo::Affine::rotate(core::f64::consts::FRAC_PI_2)
+            .pre_scale(1.5)
+            .then_translate((15.0, 5.0).into());
+        let mid = a.tween(&b, 0.5, &Easing::LERP);
+        for (x, y) in mid.as_coeffs().iter().zip(expected.as_coeffs()) {
+            assert!(
+                (x - y).abs() < 1e-9,
+                "rotation should be interpolated by angle"
+            );
+        }
+        assert_eq!(a.tween(&b, 1.0, &Easing::LERP), b);
+        let array = [0.0, 10.0, 20.0].tween(&[10.0, 10.0, 0.0], 0.5, &Easing::LERP);
+        assert_eq!(array, [5.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn hinted_seek_matches_search() {
+        let animated = Animated {
+            times: (0..100)
+                .map(|frame| Time::new(frame as f64 * 2.0))
+                .collect(),
+            values: (0..100).map(|value| (value * value) as f64).collect(),
+            extrapolation: Default::default(),
+            interpolation: Default::default(),
+        };
+        let mut hint = SeekHint::default();
+        // Play forwards, then jump backwards and outside of the keyframes.
+        let frames: Vec<f64> = (0..400)
+            .map(|frame| frame as f64 * 0.5)
+            .chain([31.0, 7.5, -10.0, 500.0, 64.0])
+            .collect();
+        for &frame in &frames {
+            assert_eq!(
+                animated.evaluate_hinted(frame, &mut hint),
+                animated.evaluate(frame),
+                "hinted evaluation should match at frame {frame}"
+            );
+        }
+        let mut values = vec![];
+        Value::Animated(animated.clone()).evaluate_many(&frames, &mut values);
+        let expected: Vec<f64> = frames
+            .iter()
+            .map(|&frame| animated.evaluate(frame))
+            .collect();
+        assert_eq!(values, expected, "batch evaluation should match");
+        let mut hints = SeekHints::new();
+        for &frame in &frames {
+            assert_eq!(
+                animated.evaluate_cached(frame, &mut hints),
+                animated.evaluate(frame),
+                "cached evaluation should match at frame {frame}"
+            );
+        }
+    }
+}
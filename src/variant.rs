@@ -0,0 +1,159 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use hashbrown::HashMap;
+
+use crate::{Composition, Layer};
+
+/// Change applied to a base composition to produce a variant.
+#[derive(Clone, Debug)]
+pub enum Patch {
+    /// Replaces the top level layer at the given index. Ignored if the index
+    /// is out of bounds.
+    ReplaceLayer(usize, Box<Layer>),
+    /// Inserts or replaces the named asset.
+    SetAsset(String, Vec<Layer>),
+    /// Removes the named asset.
+    RemoveAsset(String),
+}
+
+impl Patch {
+    /// Applies the patch to a composition.
+    pub fn apply(&self, composition: &mut Composition) {
+        match self {
+            Self::ReplaceLayer(index, layer) => {
+                if let Some(target) = composition.layers.get_mut(*index) {
+                    *target = (**layer).clone();
+                }
+            }
+            Self::SetAsset(name, layers) => {
+                composition.assets.insert(name.clone(), layers.clone());
+            }
+            Self::RemoveAsset(name) => {
+                composition.assets.remove(name);
+            }
+        }
+    }
+}
+
+/// Family of compositions stored as a shared base and per-variant patches.
+///
+/// This is useful for sets of compositions that differ only slightly, such
+/// as localized or themed versions of the same animation, where storing
+/// every variant in full would be wasteful.
+#[derive(Clone, Default, Debug)]
+pub struct VariantSet {
+    /// Composition shared by all variants.
+    pub base: Composition,
+    /// Patches that produce each named variant from the base.
+    pub variants: HashMap<String, Vec<Patch>>,
+}
+
+impl VariantSet {
+    /// Creates a new variant set with the given base composition.
+    pub fn new(base: Composition) -> Self {
+        Self {
+            base,
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Adds or replaces a variant.
+    pub fn insert(&mut self, name: impl Into<String>, patches: Vec<Patch>) {
+        self.variants.insert(name.into(), patches);
+    }
+
+    /// Returns the composition for the named variant, or `None` if there is
+    /// no such variant.
+    pub fn materialize(&self, name: &str) -> Option<Composition> {
+        let patches = self.variants.get(name)?;
+        let mut composition = self.base.clone();
+        for patch in patches {
+            patch.apply(&mut composition);
+        }
+        Some(composition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use alloc::vec;
+
+    fn layer(opacity: f64) -> Layer {
+        Layer {
+            opacity: Value::Fixed(opacity),
+            ..Default::default()
+        }
+    }
+
+    fn opacity(layer: &Layer) -> f64 {
+        match layer.opacity {
+            Value::Fixed(opacity) => opacity,
+            Value::Animated(_) => panic!("opacity should be fixed"),
+        }
+    }
+
+    fn variants() -> VariantSet {
+        let mut base = Composition {
+            layers: vec![layer(100.0), layer(100.0)],
+            ..Default::default()
+        };
+        base.assets.insert("logo".into(), vec![layer(100.0)]);
+        VariantSet::new(base)
+    }
+
+    #[test]
+    fn variant_applies_patches_to_base() {
+        let mut set = variants();
+        set.insert(
+            "dimmed",
+            vec![
+                Patch::ReplaceLayer(1, Box::new(layer(50.0))),
+                Patch::SetAsset("badge".into(), vec![layer(25.0)]),
+                Patch::RemoveAsset("logo".into()),
+            ],
+        );
+        let dimmed = set.materialize("dimmed").unwrap();
+        assert_eq!(opacity(&dimmed.layers[0]), 100.0);
+        assert_eq!(opacity(&dimmed.layers[1]), 50.0);
+        assert_eq!(opacity(&dimmed.assets["badge"][0]), 25.0);
+        assert!(!dimmed.assets.contains_key("logo"));
+        assert_eq!(
+            opacity(&set.base.layers[1]),
+            100.0,
+            "the base should not be modified"
+        );
+        assert!(set.materialize("missing").is_none());
+    }
+
+    #[test]
+    fn later_patches_take_precedence() {
+        let mut set = variants();
+        set.insert(
+            "overlapping",
+            vec![
+                Patch::ReplaceLayer(0, Box::new(layer(50.0))),
+                Patch::SetAsset("logo".into(), vec![layer(50.0)]),
+                Patch::ReplaceLayer(0, Box::new(layer(25.0))),
+                Patch::RemoveAsset("logo".into()),
+                Patch::SetAsset("logo".into(), vec![layer(10.0)]),
+            ],
+        );
+        let overlapping = set.materialize("overlapping").unwrap();
+        assert_eq!(opacity(&overlapping.layers[0]), 25.0);
+        assert_eq!(opacity(&overlapping.assets["logo"][0]), 10.0);
+    }
+
+    #[test]
+    fn patch_for_missing_layer_is_ignored() {
+        let mut set = variants();
+        set.insert("extra", vec![Patch::ReplaceLayer(2, Box::new(layer(0.0)))]);
+        let extra = set.materialize("extra").unwrap();
+        assert_eq!(extra.layers.len(), 2);
+        assert!(extra.layers.iter().all(|layer| opacity(layer) == 100.0));
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use kurbo::common::FloatFuncs as _;
+
+use crate::Linear;
+
+/// Deterministic noise that can be layered on top of an animated value.
+///
+/// This mirrors the `wiggle()` expression of After Effects. The offset
+/// wanders smoothly and randomly within the amplitude, with each component
+/// of the value varying independently. The same seed always produces the
+/// same motion.
+#[derive(Copy, Clone, Debug)]
+pub struct Wiggle<T: Linear> {
+    /// Number of wiggles per second.
+    pub frequency: f64,
+    /// Largest offset in each component.
+    pub amplitude: T,
+    /// Number of layers of noise. Each additional layer doubles the
+    /// frequency and halves the amplitude of the previous one, adding
+    /// finer detail.
+    pub octaves: u32,
+    /// Seed of the random sequence.
+    pub seed: u64,
+}
+
+impl<T: Linear> Wiggle<T> {
+    /// Creates a wiggle with a single octave and a seed of zero.
+    pub fn new(frequency: f64, amplitude: T) -> Self {
+        Self {
+            frequency,
+            amplitude,
+            octaves: 1,
+            seed: 0,
+        }
+    }
+
+    /// Returns the offset at the given time in seconds.
+    pub fn offset(&self, time: f64) -> T {
+        let mut channel = 0;
+        self.amplitude.map_components(|amplitude| {
+            channel += 1;
+            amplitude * self.noise(time * self.frequency, channel)
+        })
+    }
+
+    /// Returns `value` with the offset at the given time in seconds added.
+    pub fn apply(&self, value: T, time: f64) -> T {
+        value.add_scaled(self.offset(time), 1.0)
+    }
+
+    /// Returns fractal noise in the range -1 to 1 for one component.
+    fn noise(&self, x: f64, channel: u64) -> f64 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut scale = 1.0;
+        let mut frequency = 1.0;
+        for octave in 0..self.octaves.max(1) {
+            let seed = self.seed ^ (channel << 32) ^ (u64::from(octave) << 48);
+            sum += scale * value_noise(x * frequency, seed);
+            total += scale;
+            scale *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / total
+    }
+}
+
+/// Returns smoothly interpolated random values in the range -1 to 1 at
+/// integer coordinates.
+fn value_noise(x: f64, seed: u64) -> f64 {
+    let floor = x.floor();
+    let t = x - floor;
+    // Quintic smoothing gives continuous velocity and acceleration.
+    let t = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let ix = floor as i64;
+    let a = lattice(ix, seed);
+    let b = lattice(ix.wrapping_add(1), seed);
+    a + (b - a) * t
+}
+
+/// Returns a random value in the range -1 to 1 for a lattice point.
+pub(crate) fn lattice(ix: i64, seed: u64) -> f64 {
+    // SplitMix64 finalizer.
+    let mut z = (ix as u64).wrapping_add(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1_u64 << 53) as f64 * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kurbo::Point;
+
+    #[test]
+    fn wiggle_is_deterministic_and_bounded() {
+        let wiggle = Wiggle {
+            octaves: 3,
+            seed: 7,
+            ..Wiggle::new(2.0, Point::new(10.0, 5.0))
+        };
+        let other_seed = Wiggle { seed: 8, ..wiggle };
+        let mut moved = false;
+        let mut previous = wiggle.offset(0.0);
+        for step in 1..200 {
+            let time = step as f64 / 60.0;
+            let offset = wiggle.offset(time);
+            assert_eq!(offset, wiggle.offset(time));
+            assert!(
+                offset.x.abs() <= 10.0 && offset.y.abs() <= 5.0,
+                "offset should stay within the amplitude"
+            );
+            assert!(
+                (offset - previous).hypot() < 5.0,
+                "offset should change smoothly"
+            );
+            moved |= offset != other_seed.offset(time);
+            previous = offset;
+        }
+        assert!(moved, "different seeds should produce different motion");
+        assert_eq!(
+            wiggle.apply(Point::new(1.0, 1.0), 0.5),
+            Point::new(1.0, 1.0) + wiggle.offset(0.5).to_vec2()
+        );
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2024 the Interpoli Authors
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use kurbo::{BezPath, PathEl, Shape as _};
+
+/// Direction in which a closed contour is traversed, in a coordinate system
+/// where the y axis points down.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum Winding {
+    /// Clockwise on screen, corresponding to a positive signed area.
+    #[default]
+    Clockwise,
+    /// Counterclockwise on screen, corresponding to a negative signed area.
+    CounterClockwise,
+}
+
+impl Winding {
+    /// Returns the opposite direction.
+    #[must_use]
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::Clockwise => Self::CounterClockwise,
+            Self::CounterClockwise => Self::Clockwise,
+        }
+    }
+
+    /// Returns the direction of a contour with the given signed area, or
+    /// `None` if the area is zero.
+    pub fn from_area(area: f64) -> Option<Self> {
+        if area > 0.0 {
+            Some(Self::Clockwise)
+        } else if area < 0.0 {
+            Some(Self::CounterClockwise)
+        } else {
+            None
+        }
+    }
+
+    /// Rewrites a path so that outer contours wind in this direction and
+    /// holes wind in the opposite direction.
+    ///
+    /// Authoring tools disagree on contour directions, so imported geometry
+    /// may render with unexpected holes under the non-zero fill rule. After
+    /// normalization, the non-zero and even-odd fill rules produce the same
+    /// result for paths whose contours don't intersect.
+    pub fn normalize(self, path: &mut Vec<PathEl>) {
+        let contours = Contour::find(path);
+        if contours.iter().all(|contour| {
+            Self::from_area(contour.area).map_or(true, |winding| winding == contour.expected(self))
+        }) {
+            return;
+        }
+        let mut normalized = Vec::with_capacity(path.len());
+        for contour in &contours {
+            let elements = &path[contour.elements.clone()];
+            match Self::from_area(contour.area) {
+                Some(winding) if winding != contour.expected(self) => {
+                    let reversed = BezPath::from_vec(elements.to_vec()).reverse_subpaths();
+                    normalized.extend_from_slice(reversed.elements());
+                }
+                _ => normalized.extend_from_slice(elements),
+            }
+        }
+        *path = normalized;
+    }
+}
+
+/// Single contour of a path.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Contour {
+    /// Range of the path elements of the contour.
+    pub elements: Range<usize>,
+    /// Signed area of the contour.
+    pub area: f64,
+    /// Number of other contours enclosing this one.
+    pub depth: usize,
+}
+
+impl Contour {
+    /// Returns the contours of a path along with their nesting depth.
+    ///
+    /// Contours that aren't explicitly closed are treated as closed, as they
+    /// are when filled.
+    pub fn find(path: &[PathEl]) -> Vec<Self> {
+        let mut contours = Vec::new();
+        let mut start = 0;
+        for (ix, element) in path.iter().enumerate() {
+            if matches!(element, PathEl::MoveTo(_)) && ix > start {
+                contours.push(start..ix);
+                start = ix;
+            }
+        }
+        if start < path.len() {
+            contours.push(start..path.len());
+        }
+        let mut contours: Vec<Self> = contours
+            .into_iter()
+            .map(|elements| Self {
+                area: (&path[elements.clone()]).area(),
+                elements,
+                depth: 0,
+            })
+            .collect();
+        for ix in 0..contours.len() {
+            let Some(point) = path[contours[ix].elements.start].end_point() else {
+                continue;
+            };
+            let area = contours[ix].area.abs();
+            contours[ix].depth = contours
+                .iter()
+                .enumerate()
+                .filter(|(other_ix, other)| {
+                    *other_ix != ix
+                        && other.area.abs() > area
+                        && (&path[other.elements.clone()]).winding(point) != 0
+                })
+                .count();
+        }
+        contours
+    }
+
+    /// Returns true if the contour is a hole in an enclosing contour.
+    pub fn is_hole(&self) -> bool {
+        self.depth % 2 == 1
+    }
+
+    fn expected(&self, outer: Winding) -> Winding {
+        if self.is_hole() {
+            outer.reversed()
+        } else {
+            outer
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use kurbo::Rect;
+
+    #[test]
+    fn normalize_reverses_holes() {
+        let mut path: Vec<PathEl> = vec![];
+        path.extend(Rect::new(0.0, 0.0, 100.0, 100.0).path_elements(0.1));
+        path.extend(Rect::new(25.0, 25.0, 75.0, 75.0).path_elements(0.1));
+        let contours = Contour::find(&path);
+        assert_eq!(contours.len(), 2);
+        assert!(!contours[0].is_hole(), "outer contour is not a hole");
+        assert!(contours[1].is_hole(), "inner contour is a hole");
+        assert_eq!(
+            Winding::from_area(contours[0].area),
+            Winding::from_area(contours[1].area)
+        );
+        Winding::CounterClockwise.normalize(&mut path);
+        let normalized = Contour::find(&path);
+        assert_eq!(
+            Winding::from_area(normalized[0].area),
+            Some(Winding::CounterClockwise)
+        );
+        assert_eq!(
+            Winding::from_area(normalized[1].area),
+            Some(Winding::Clockwise)
+        );
+        assert_eq!(BezPath::from_vec(path).winding((50.0, 50.0).into()), 0);
+    }
+}